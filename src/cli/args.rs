@@ -5,9 +5,18 @@
 // file, You can obtain one at http://mozilla.org/MPL/2.0/.
 
 use cli;
+use cli::copy::Location;
 use failure::Error;
-use lib::types::{HmacAlgorithm, OtpRecord, Record};
-use lib::utils;
+use lib::backend::{BackendKind, Crypto};
+use lib::config::{Config, OutputFormat};
+use lib::export::ExportFormat;
+use lib::import::csv::{CsvFormat, Mapping};
+use lib::otp;
+use lib::types::{
+    add_tags, remove_tags, ConflictPolicy, Field, Filter, HmacAlgorithm, OtpRecord, Record,
+    SortOrder,
+};
+use std::collections::BTreeMap;
 use std::env;
 use std::io;
 use std::path::PathBuf;
@@ -18,12 +27,21 @@ use structopt::StructOpt;
 #[structopt(name = "sigil")]
 pub struct Sigil {
     #[structopt(short = "V", long = "vault", parse(from_os_str))]
-    /// Path to the vault. Defaults to the SIGIL_VAULT environment variable
+    /// Path to the vault. Defaults to the vault of the selected profile, then
+    /// to the SIGIL_VAULT environment variable, then to the vault of the
+    /// configuration file
     pub vault: Option<PathBuf>,
     #[structopt(short = "K", long = "key")]
-    /// The GPG key to use for encryption. Required for operations that will
-    /// write on a vault. Defaults to the SIGIL_GPGKEY environment variable
+    /// The key to use for encryption: a GPG key, or comma-separated age
+    /// recipients for age vaults. Required for operations that will write on
+    /// a GPG vault. Defaults to the key of the selected profile, then to the
+    /// SIGIL_GPGKEY environment variable, then to the key of the
+    /// configuration file
     pub key: Option<String>,
+    #[structopt(short = "P", long = "profile")]
+    /// The vault profile to use, as listed by `sigil vaults ls`. Defaults to
+    /// the profile of the configuration file
+    pub profile: Option<String>,
     #[structopt(subcommand)]
     pub cmd: Command,
 }
@@ -42,19 +60,145 @@ pub enum Command {
         #[structopt(subcommand)]
         cmd: OtpCommand,
     },
+    #[structopt(name = "audit")]
+    /// Audit the passwords in a vault. Without a subcommand, report reused,
+    /// weak and common passwords and fail if there are any
+    Audit {
+        #[structopt(subcommand)]
+        cmd: Option<AuditCommand>,
+        #[structopt(long = "min-score", default_value = "3")]
+        /// The minimum strength of passwords, from 0 (too guessable) to 4
+        /// (very unguessable)
+        min_score: u8,
+        #[structopt(long = "wordlist", parse(from_os_str))]
+        /// A list of common passwords to check against, with one password per
+        /// line
+        wordlist: Option<PathBuf>,
+    },
+    #[structopt(name = "export")]
+    /// Export a vault to a format other password managers can import
+    Export {
+        #[structopt(short = "f", long = "format")]
+        /// The export format (csv|json|keepass-xml|pass). CSV and JSON
+        /// follow the Bitwarden formats
+        format: ExportFormat,
+        #[structopt(parse(from_os_str))]
+        /// The file to write, or the directory of the pass store
+        dest: PathBuf,
+        #[structopt(long = "encrypt-to")]
+        /// Encrypt the export to this GPG recipient. Pass stores default to
+        /// the vault key
+        encrypt_to: Option<String>,
+        #[structopt(long = "plaintext", raw(takes_value = "false"))]
+        /// Don't ask for confirmation before writing an unencrypted export
+        plaintext: bool,
+        #[structopt(long = "force", raw(takes_value = "false"))]
+        /// Overwrite an existing destination
+        force: bool,
+    },
+    #[structopt(name = "import")]
+    /// Import passwords and OTP generators from other password managers
+    Import {
+        #[structopt(subcommand)]
+        cmd: ImportCommand,
+    },
     #[structopt(name = "touch")]
     /// Initialize an empty vault file
     Touch {
         #[structopt(short = "f", long = "force", raw(takes_value = "false"))]
         /// Overwrite an existing file
         force: bool,
+        #[structopt(short = "b", long = "backend", default_value = "gpg")]
+        /// The encryption backend (gpg|age). Vaults are then read and written
+        /// with the backend that created them
+        backend: BackendKind,
+        #[structopt(long = "passphrase", raw(takes_value = "false"))]
+        /// Encrypt an age vault with a passphrase instead of recipients
+        passphrase: bool,
+    },
+    #[structopt(name = "rekey")]
+    /// Re-encrypt a vault to new keys, checking that it can still be decrypted
+    /// before replacing it
+    Rekey {
+        #[structopt(long = "to", raw(number_of_values = "1", required = "true"))]
+        /// A key to encrypt the vault to, can be repeated. GPG keys must be
+        /// usable for encryption, not expired nor revoked
+        to: Vec<String>,
     },
     #[structopt(name = "ls")]
-    /// List all secrets in a vault
+    /// List all secrets in a vault, grouped by folder
     List {
         #[structopt(long = "disclose", raw(takes_value = "false"))]
         /// Disclose secrets
         disclose: bool,
+        /// Only list the records in this folder, such as "work/aws"
+        folder: Option<String>,
+        #[structopt(long = "tag")]
+        /// Only list the records with this tag
+        tag: Option<String>,
+        #[structopt(long = "sort", default_value = "name")]
+        /// How to order the records (name|created|modified|last-used)
+        sort: SortOrder,
+        #[structopt(long = "json", raw(takes_value = "false"))]
+        /// Print the records as JSON
+        json: bool,
+    },
+    #[structopt(name = "find")]
+    /// Fuzzy search passwords and OTP generators by name and metadata
+    Find {
+        /// What to look for, such as part of a name, a username or a URL
+        query: String,
+    },
+    #[structopt(name = "mv")]
+    /// Rename a password and OTP generator, or move a folder, possibly to
+    /// another vault, such as "sigil mv --to-vault personal foo"
+    Move {
+        #[structopt(long = "from-vault")]
+        /// The profile of the vault to move from, defaults to the current vault
+        from_vault: Option<String>,
+        #[structopt(long = "to-vault")]
+        /// The profile of the vault to move to, defaults to the current vault
+        to_vault: Option<String>,
+        /// The record or folder to move
+        from: String,
+        /// Its new name, required unless it moves to another vault
+        to: Option<String>,
+    },
+    #[structopt(name = "cp")]
+    /// Copy a password and OTP generator, or a folder, possibly to another
+    /// vault, such as "sigil cp --from-vault work --to-vault personal foo"
+    Copy {
+        #[structopt(long = "from-vault")]
+        /// The profile of the vault to copy from, defaults to the current vault
+        from_vault: Option<String>,
+        #[structopt(long = "to-vault")]
+        /// The profile of the vault to copy to, defaults to the current vault
+        to_vault: Option<String>,
+        /// The record or folder to copy
+        from: String,
+        /// The name of the copy, defaults to the same name
+        to: Option<String>,
+    },
+    #[structopt(name = "vaults")]
+    /// Operate on the vault profiles of the configuration file
+    Vaults {
+        #[structopt(subcommand)]
+        cmd: VaultsCommand,
+    },
+    #[structopt(name = "rm")]
+    /// Remove the password and OTP generator with a name, or a folder
+    Remove {
+        #[structopt(short = "r", long = "recursive", raw(takes_value = "false"))]
+        /// Remove a folder and all of its contents
+        recursive: bool,
+        /// The record or folder to remove
+        name: String,
+    },
+    #[structopt(name = "config")]
+    /// Read or change the configuration file, ~/.config/sigil/config.toml
+    Config {
+        #[structopt(subcommand)]
+        cmd: ConfigCommand,
     },
     #[structopt(name = "completion")]
     /// Generate a completion script for Sigil
@@ -99,12 +243,56 @@ pub enum OtpCommand {
         #[structopt(requires = "secret", long = "period")]
         /// Token validity in seconds
         period: Option<u64>,
+        #[structopt(requires = "secret", long = "tag", raw(number_of_values = "1"))]
+        /// A tag for this generator, can be repeated
+        tags: Vec<String>,
+        #[structopt(requires = "secret", long = "notes")]
+        /// Free-form notes about this generator
+        notes: Option<String>,
+    },
+    #[structopt(name = "edit")]
+    /// Edit the tags and notes of an OTP generator
+    Edit {
+        #[structopt()]
+        /// Generator name
+        name: String,
+        #[structopt(long = "notes")]
+        /// Replace the notes of this generator, an empty string removes them
+        notes: Option<String>,
+        #[structopt(long = "tag", raw(number_of_values = "1"))]
+        /// Add a tag to this generator, can be repeated
+        tags: Vec<String>,
+        #[structopt(long = "untag", raw(number_of_values = "1"))]
+        /// Remove a tag from this generator, can be repeated
+        untags: Vec<String>,
     },
     #[structopt(name = "import")]
-    /// Import an OTP generator to a vault using an `otpauth://` URI
+    /// Import OTP generators to a vault using an `otpauth://` or
+    /// `otpauth-migration://` URI or a QR code image
     ImportUrl {
+        #[structopt(required_unless = "image")]
+        /// The `otpauth://` or `otpauth-migration://` URI
+        url: Option<String>,
+        #[structopt(long = "image", conflicts_with = "url", parse(from_os_str))]
+        /// An image containing `otpauth://` or `otpauth-migration://` QR codes
+        image: Option<PathBuf>,
+        #[structopt(long = "on-conflict")]
+        /// How to handle generators whose name is already in use
+        /// (skip|rename|overwrite). Fails by default
+        on_conflict: Option<ConflictPolicy>,
+    },
+    #[structopt(name = "export")]
+    /// Export an OTP generator as an `otpauth://` URI
+    Export {
         #[structopt()]
-        url: String,
+        /// Generator name
+        name: String,
+        #[structopt(long = "qr", raw(takes_value = "false"))]
+        /// Render the URI as a QR code
+        qr: bool,
+        #[structopt(short = "o", long = "output", requires = "qr", parse(from_os_str))]
+        /// Write the QR code to a PNG or SVG file instead of the terminal
+        output: Option<PathBuf>,
     },
     #[structopt(name = "rm")]
     /// Remove an OTP generator
@@ -119,11 +307,158 @@ pub enum OtpCommand {
         #[structopt()]
         /// Generator name
         name: String,
-        /// Counter for HOTP, ignored for TOTP
+        /// Use this HOTP counter instead of the stored one, ignored for TOTP
         counter: Option<u64>,
     },
 }
 
+#[derive(Debug, StructOpt)]
+pub enum VaultsCommand {
+    #[structopt(name = "ls")]
+    /// List the vault profiles, marking the selected one with a star
+    List,
+}
+
+#[derive(Debug, StructOpt)]
+pub enum ConfigCommand {
+    #[structopt(name = "get")]
+    /// Print a setting, such as "key" or "otp.digits"
+    Get {
+        /// The setting, tables and keys are separated by dots
+        key: String,
+    },
+    #[structopt(name = "set")]
+    /// Change a setting, such as "key" or "otp.digits"
+    Set {
+        /// The setting, tables and keys are separated by dots
+        key: String,
+        /// Its new value, a string or a TOML value such as 8, true or ["a", "b"]
+        value: String,
+    },
+    #[structopt(name = "pin")]
+    /// Pin a GPG key, so that vaults may be encrypted to it
+    Pin {
+        /// The full fingerprint of the key, as listed by `gpg --fingerprint`
+        fingerprint: String,
+    },
+}
+
+#[derive(Debug, StructOpt)]
+pub enum AuditCommand {
+    #[structopt(name = "breached")]
+    /// List the passwords found in a local copy of the Have I Been Pwned
+    /// Pwned Passwords file. Fails if there are any
+    Breached {
+        #[structopt(long = "hibp-db", parse(from_os_str))]
+        /// The SHA-1 Pwned Passwords file, ordered by hash
+        hibp_db: PathBuf,
+    },
+    #[structopt(name = "stale")]
+    /// List the passwords older than their maximum age. Fails if there are any
+    Stale {
+        #[structopt(long = "max-age")]
+        /// The maximum age, in days, of passwords without a policy. These are
+        /// not audited by default
+        max_age: Option<u32>,
+    },
+    #[structopt(name = "max-age")]
+    /// Set the maximum age of the passwords with a tag, or list the maximum
+    /// ages of all tags if no tag is provided
+    MaxAge {
+        /// The tag
+        tag: Option<String>,
+        /// The maximum age in days, 0 removes it
+        days: Option<u32>,
+    },
+}
+
+#[derive(Debug, StructOpt)]
+pub enum ImportCommand {
+    #[structopt(name = "aegis")]
+    /// Import OTP generators from an unencrypted Aegis JSON export
+    Aegis {
+        #[structopt(parse(from_os_str))]
+        /// Path to the JSON file
+        file: PathBuf,
+        #[structopt(long = "on-conflict")]
+        /// How to handle records whose name is already in use
+        /// (skip|rename|overwrite). Fails by default
+        on_conflict: Option<ConflictPolicy>,
+    },
+    #[structopt(name = "andotp")]
+    /// Import OTP generators from a plain-text andOTP JSON backup
+    AndOtp {
+        #[structopt(parse(from_os_str))]
+        /// Path to the JSON file
+        file: PathBuf,
+        #[structopt(long = "on-conflict")]
+        /// How to handle records whose name is already in use
+        /// (skip|rename|overwrite). Fails by default
+        on_conflict: Option<ConflictPolicy>,
+    },
+    #[structopt(name = "bitwarden")]
+    /// Import logins and TOTP generators from an unencrypted Bitwarden JSON
+    /// export
+    Bitwarden {
+        #[structopt(parse(from_os_str))]
+        /// Path to the JSON file
+        file: PathBuf,
+        #[structopt(long = "on-conflict")]
+        /// How to handle records whose name is already in use
+        /// (skip|rename|overwrite). Fails by default
+        on_conflict: Option<ConflictPolicy>,
+    },
+    #[structopt(name = "csv")]
+    /// Import passwords from a CSV export. Use --dry-run first to review
+    /// what will be imported
+    Csv {
+        #[structopt(parse(from_os_str))]
+        /// Path to the CSV file
+        file: PathBuf,
+        #[structopt(long = "format", required_unless = "columns")]
+        /// The password manager that exported the file
+        /// (firefox|chrome|bitwarden|1password|lastpass)
+        format: Option<CsvFormat>,
+        #[structopt(long = "columns")]
+        /// Custom column mapping, such as "name=Title,password=Secret".
+        /// Fields are name, url, username, email, password, notes and totp.
+        /// Overrides --format
+        columns: Option<String>,
+        #[structopt(long = "on-conflict")]
+        /// How to handle records whose name is already in use
+        /// (skip|rename|overwrite). Fails by default
+        on_conflict: Option<ConflictPolicy>,
+        #[structopt(long = "dry-run", raw(takes_value = "false"))]
+        /// Report what would be imported without modifying the vault
+        dry_run: bool,
+    },
+    #[structopt(name = "keepass")]
+    /// Import the entries of a KeePass KDBX 3.1 or 4 database
+    Keepass {
+        #[structopt(parse(from_os_str))]
+        /// Path to the database
+        database: PathBuf,
+        #[structopt(long = "keyfile", parse(from_os_str))]
+        /// Path to the key file of the database, if any
+        keyfile: Option<PathBuf>,
+        #[structopt(long = "on-conflict")]
+        /// How to handle records whose name is already in use
+        /// (skip|rename|overwrite). Fails by default
+        on_conflict: Option<ConflictPolicy>,
+    },
+    #[structopt(name = "pass")]
+    /// Import the entries of a pass password store
+    Pass {
+        #[structopt(parse(from_os_str))]
+        /// Path to the password store, usually ~/.password-store
+        store: PathBuf,
+        #[structopt(long = "on-conflict")]
+        /// How to handle records whose name is already in use
+        /// (skip|rename|overwrite). Fails by default
+        on_conflict: Option<ConflictPolicy>,
+    },
+}
+
 #[derive(Debug, StructOpt)]
 pub enum PasswordCommand {
     #[structopt(name = "add")]
@@ -144,6 +479,63 @@ pub enum PasswordCommand {
         #[structopt(long = "home", requires = "password")]
         /// The homepage for this service
         home: Option<String>,
+        #[structopt(long = "tag", requires = "password", raw(number_of_values = "1"))]
+        /// A tag for this password, can be repeated
+        tags: Vec<String>,
+        #[structopt(long = "notes", requires = "password")]
+        /// Free-form notes about this password
+        notes: Option<String>,
+        #[structopt(long = "max-age", requires = "password")]
+        /// How many days this password may go without being changed
+        max_age: Option<u32>,
+        #[structopt(long = "field", requires = "password", raw(number_of_values = "1"))]
+        /// A custom key=value field, can be repeated
+        fields: Vec<String>,
+        #[structopt(
+            long = "secret-field",
+            requires = "password",
+            raw(number_of_values = "1")
+        )]
+        /// A custom key=value field only shown with --disclose, can be repeated
+        secret_fields: Vec<String>,
+    },
+    #[structopt(name = "edit")]
+    /// Edit the metadata, tags and notes of a password. Empty strings remove fields
+    Edit {
+        #[structopt()]
+        /// Password name
+        name: String,
+        #[structopt(short = "u", long = "username")]
+        /// The username associated with this password
+        username: Option<String>,
+        #[structopt(long = "email")]
+        /// The email associated with this password
+        email: Option<String>,
+        #[structopt(long = "home")]
+        /// The homepage for this service
+        home: Option<String>,
+        #[structopt(long = "notes")]
+        /// Free-form notes about this password
+        notes: Option<String>,
+        #[structopt(long = "tag", raw(number_of_values = "1"))]
+        /// Add a tag to this password, can be repeated
+        tags: Vec<String>,
+        #[structopt(long = "untag", raw(number_of_values = "1"))]
+        /// Remove a tag from this password, can be repeated
+        untags: Vec<String>,
+        #[structopt(long = "max-age")]
+        /// How many days this password may go without being changed, 0
+        /// removes it
+        max_age: Option<u32>,
+        #[structopt(long = "field", raw(number_of_values = "1"))]
+        /// Set a custom key=value field, can be repeated
+        fields: Vec<String>,
+        #[structopt(long = "secret-field", raw(number_of_values = "1"))]
+        /// Set a custom key=value field only shown with --disclose, can be repeated
+        secret_fields: Vec<String>,
+        #[structopt(long = "remove-field", raw(number_of_values = "1"))]
+        /// Remove a custom field, can be repeated
+        removed_fields: Vec<String>,
     },
     #[structopt(name = "rm")]
     /// Remove a password from a vault
@@ -157,43 +549,249 @@ pub enum PasswordCommand {
         #[structopt()]
         /// Password name
         name: String,
+        #[structopt(long = "field")]
+        /// Get this custom field instead of the password
+        field: Option<String>,
     },
     #[structopt(name = "generate")]
     /// Generate a random password
     Generate {
         #[structopt()]
-        /// Password length
-        chars: usize,
+        /// Password length. Defaults to the generator.length setting, then to
+        /// 20
+        chars: Option<usize>,
+        #[structopt(long = "symbols", raw(takes_value = "false"))]
+        /// Include punctuation. Defaults to the generator.symbols setting
+        symbols: bool,
     },
 }
 
 pub fn match_args(sigil: Sigil) -> Result<(), Error> {
-    // Try to fetch sigil key and vault from the environment
+    let config_path = Config::path()
+        .ok_or_else(|| format_err!("Cannot locate the configuration file, HOME is not set"));
+    // The configuration file can be fixed even when it is invalid
+    if let Command::Config { ref cmd } = sigil.cmd {
+        return match cmd {
+            ConfigCommand::Get { key } => cli::config::get_config(&config_path?, key),
+            ConfigCommand::Set { key, value } => cli::config::set_config(&config_path?, key, value),
+            ConfigCommand::Pin { fingerprint } => cli::config::pin_key(&config_path?, fingerprint),
+        };
+    }
+    let config = match config_path {
+        Ok(path) => Config::load(&path)
+            .map_err(|e| format_err!("Failed to read {} ({})", path.display(), e))?,
+        Err(_) => Config::default(),
+    };
+
+    // A profile selected with --profile takes precedence over the
+    // environment, while the default one only takes precedence over the rest
+    // of the configuration file
+    let (profile, default_profile) = match (&sigil.profile, &config.profile) {
+        (Some(name), _) => (Some(config.profile(name)?), None),
+        (None, Some(name)) => (None, Some(config.profile(name)?)),
+        (None, None) => (None, None),
+    };
+
+    // Try to fetch sigil key and vault from the arguments, then from the
+    // environment and then from the configuration file
     // Not all commands will need these
     let key = sigil
         .key
+        .or_else(|| profile.and_then(|p| p.key.clone()))
         .or_else(|| env::var_os("SIGIL_GPGKEY").map(|n| n.to_string_lossy().to_string()))
+        .or_else(|| default_profile.and_then(|p| p.key.clone()))
+        .or_else(|| config.key.clone())
         .ok_or_else(|| {
-            format_err!("No GPG key was passed either as an argument (--key) or as an environment variable (SIGIL_GPGKEY)")
+            format_err!("No GPG key was passed either as an argument (--key), as an environment variable (SIGIL_GPGKEY) or in the configuration file (key)")
         });
     let vault = sigil
         .vault
+        .or_else(|| profile.and_then(|p| p.vault.clone()))
         .or_else(|| env::var_os("SIGIL_VAULT").map(PathBuf::from))
+        .or_else(|| default_profile.and_then(|p| p.vault.clone()))
+        .or_else(|| config.vault.clone())
         .ok_or_else(|| {
-            format_err!("No vault path was passed either as an argument (--vault) or as an environment variable (SIGIL_VAULT)")
+            format_err!("No vault path was passed either as an argument (--vault), as an environment variable (SIGIL_VAULT) or in the configuration file (vault)")
         });
-    // Not all commands will need a context
-    let ctx = utils::create_context()
-        .map_err(|_| format_err!("Failed to create a GPG cryptographic context"));
+    // Recording when records are used re-encrypts the vault on every read,
+    // so it is opt-in
+    let track_usage = env::var_os("SIGIL_TRACK_USAGE")
+        .map(|v| !v.is_empty() && v != "0")
+        .or(config.track_usage)
+        .unwrap_or(false);
+    // age vaults can be written back with the passphrase or identities that
+    // decrypted them, so they don't need a key
+    let is_age = vault
+        .as_ref()
+        .ok()
+        .and_then(|v| BackendKind::detect_file(v))
+        == Some(BackendKind::Age);
+    let key = key.or_else(|e| if is_age { Ok(String::new()) } else { Err(e) });
+    // Backends are only created when a vault needs them, and only encrypt
+    // vaults to the GPG keys pinned in the configuration file
+    let ctx = Crypto::new(
+        env::var_os("SIGIL_AGE_IDENTITY")
+            .map(PathBuf::from)
+            .or_else(|| config.age_identity.clone()),
+        config.pinned_fingerprints(),
+    );
+    // Test builds can leave vaults unencrypted, for the integration tests
+    #[cfg(feature = "test-plaintext")]
+    let ctx = match env::var_os("SIGIL_PLAINTEXT") {
+        Some(_) => Crypto::plaintext(),
+        None => ctx,
+    };
 
     match sigil.cmd {
-        Command::Touch { force } => cli::touch::touch_vault(&vault?, &key?, force),
-        Command::List { disclose } => cli::list::list_vault(&vault?, disclose),
+        Command::Touch {
+            force,
+            backend,
+            passphrase,
+        } => {
+            let key = match (backend, passphrase) {
+                (BackendKind::Age, true) => String::new(),
+                (BackendKind::Gpg, true) => {
+                    bail!("Only age vaults can be encrypted with a passphrase")
+                }
+                _ => key?,
+            };
+            cli::touch::touch_vault(&vault?, &key, ctx, backend, passphrase, force)
+        }
+        Command::Rekey { to } => cli::rekey::rekey_vault(&vault?, ctx, &to),
+        Command::List {
+            disclose,
+            folder,
+            tag,
+            sort,
+            json,
+        } => {
+            let filter = Filter {
+                folder: folder.as_ref().map(|f| &f[..]),
+                tag: tag.as_ref().map(|t| &t[..]),
+                sort,
+            };
+            let json = json || config.output == Some(OutputFormat::Json);
+            cli::list::list_vault(&vault?, ctx, disclose, &filter, json)
+        }
+        Command::Find { query } => cli::find::find_records(&vault?, ctx, &query),
+        Command::Move {
+            from_vault: None,
+            to_vault: None,
+            from,
+            to,
+        } => {
+            let to =
+                to.ok_or_else(|| format_err!("Give a new name, or a vault with --to-vault"))?;
+            cli::rename::move_records(&vault?, &key?, ctx, &from, &to)
+        }
+        Command::Move {
+            from_vault,
+            to_vault,
+            from,
+            to,
+        } => {
+            let to = to.unwrap_or_else(|| from.clone());
+            let from = locate(&config, from_vault.as_ref(), from, &vault, &key)?;
+            let to = locate(&config, to_vault.as_ref(), to, &vault, &key)?;
+            cli::copy::copy_records(&from, &to, ctx, true)
+        }
+        Command::Copy {
+            from_vault,
+            to_vault,
+            from,
+            to,
+        } => {
+            let to = to.unwrap_or_else(|| from.clone());
+            let from = locate(&config, from_vault.as_ref(), from, &vault, &key)?;
+            let to = locate(&config, to_vault.as_ref(), to, &vault, &key)?;
+            cli::copy::copy_records(&from, &to, ctx, false)
+        }
+        Command::Vaults { cmd } => match cmd {
+            VaultsCommand::List => cli::vaults::list_vaults(
+                &config,
+                sigil
+                    .profile
+                    .as_ref()
+                    .or(config.profile.as_ref())
+                    .map(|p| &p[..]),
+            ),
+        },
+        Command::Remove { recursive, name } => {
+            cli::remove::remove_records(&vault?, &key?, ctx, &name, recursive)
+        }
+        Command::Config { .. } => unreachable!(),
         Command::Completion { shell } => {
             Sigil::clap().gen_completions_to("sigil", shell, &mut io::stdout());
 
             Ok(())
         }
+        Command::Audit {
+            cmd,
+            min_score,
+            wordlist,
+        } => match cmd {
+            None => cli::audit::audit_passwords(&vault?, ctx, min_score, wordlist.as_ref()),
+            Some(AuditCommand::Breached { hibp_db }) => {
+                cli::audit::audit_breached(&vault?, ctx, &hibp_db)
+            }
+            Some(AuditCommand::Stale { max_age }) => cli::audit::audit_stale(&vault?, ctx, max_age),
+            Some(AuditCommand::MaxAge { tag, days }) => {
+                cli::audit::set_max_age(&vault?, &key?, ctx, tag.as_ref().map(|t| &t[..]), days)
+            }
+        },
+        Command::Export {
+            format,
+            dest,
+            encrypt_to,
+            plaintext,
+            force,
+        } => {
+            // Pass stores are always encrypted. Recipients given explicitly
+            // don't need to be pinned, unlike the key of the vault
+            let mut ctx = ctx;
+            let recipient = match (format, encrypt_to) {
+                (ExportFormat::Pass, None) => Some(key?),
+                (_, None) => None,
+                (_, Some(encrypt_to)) => {
+                    ctx.allow_unpinned_keys();
+                    Some(encrypt_to)
+                }
+            };
+            cli::export::export_vault(&vault?, ctx, format, &dest, recipient, force, plaintext)
+        }
+        Command::Import { cmd } => match cmd {
+            ImportCommand::Aegis { file, on_conflict } => {
+                cli::import::import_aegis(&vault?, &key?, ctx, &file, on_conflict)
+            }
+            ImportCommand::AndOtp { file, on_conflict } => {
+                cli::import::import_andotp(&vault?, &key?, ctx, &file, on_conflict)
+            }
+            ImportCommand::Bitwarden { file, on_conflict } => {
+                cli::import::import_bitwarden(&vault?, &key?, ctx, &file, on_conflict)
+            }
+            ImportCommand::Csv {
+                file,
+                format,
+                columns,
+                on_conflict,
+                dry_run,
+            } => {
+                // Custom columns override those of the preset
+                let mut mapping = format.map(Mapping::preset).unwrap_or_default();
+                if let Some(columns) = columns {
+                    mapping.apply_spec(&columns)?;
+                }
+                cli::import::import_csv(&vault?, &key?, ctx, &file, &mapping, on_conflict, dry_run)
+            }
+            ImportCommand::Keepass {
+                database,
+                keyfile,
+                on_conflict,
+            } => cli::import::import_keepass(&vault?, &key?, ctx, &database, keyfile, on_conflict),
+            ImportCommand::Pass { store, on_conflict } => {
+                cli::import::import_pass(&vault?, &key?, ctx, &store, on_conflict)
+            }
+        },
         Command::Password { cmd } => match cmd {
             PasswordCommand::Add {
                 name,
@@ -201,27 +799,78 @@ pub fn match_args(sigil: Sigil) -> Result<(), Error> {
                 username,
                 email,
                 home,
+                tags,
+                notes,
+                max_age,
+                fields,
+                secret_fields,
             } => {
                 if name.is_some() && password.is_some() {
                     // Safe unwraps because we checked them before and they are required args
-                    cli::password::add_record(
-                        &vault?,
-                        &key?,
-                        ctx?,
-                        Record::new(password.unwrap(), username, email, home),
-                        name.unwrap(),
-                    )
+                    let mut record = Record::new(password.unwrap(), username, email, home);
+                    record.notes = notes;
+                    record.max_age = max_age.filter(|days| *days > 0);
+                    add_tags(&mut record.tags, tags);
+                    record.fields = parse_fields(&fields, &secret_fields)?;
+                    cli::password::add_record(&vault?, &key?, ctx, record, name.unwrap())
                 } else {
-                    cli::password::add_record_interactive(&vault?, &key?, ctx?)
+                    cli::password::add_record_interactive(&vault?, &key?, ctx)
                 }
             }
+            PasswordCommand::Edit {
+                name,
+                username,
+                email,
+                home,
+                notes,
+                tags,
+                untags,
+                max_age,
+                fields,
+                secret_fields,
+                removed_fields,
+            } => {
+                let fields = parse_fields(&fields, &secret_fields)?;
+                cli::password::edit_record(&vault?, &key?, ctx, &name, |record| {
+                    // Empty strings remove a field, missing ones leave it untouched
+                    for (field, value) in [
+                        (&mut record.username, username),
+                        (&mut record.email, email),
+                        (&mut record.home, home),
+                        (&mut record.notes, notes),
+                    ] {
+                        if let Some(value) = value {
+                            *field = Some(value).filter(|v| !v.is_empty());
+                        }
+                    }
+                    add_tags(&mut record.tags, tags);
+                    remove_tags(&mut record.tags, &untags);
+                    if let Some(max_age) = max_age {
+                        record.max_age = Some(max_age).filter(|days| *days > 0);
+                    }
+                    for key in &removed_fields {
+                        record.fields.remove(key);
+                    }
+                    record.fields.extend(fields);
+                })
+            }
             PasswordCommand::Remove { name } => {
-                cli::password::remove_record(&vault?, &key?, ctx?, name)
+                cli::password::remove_record(&vault?, &key?, ctx, name)
             }
-            PasswordCommand::GetPassword { name } => {
-                cli::password::get_password(&vault?, ctx?, &name)
+            PasswordCommand::GetPassword { name, field } => {
+                let usage_key = if track_usage { Some(key?) } else { None };
+                cli::password::get_password(
+                    &vault?,
+                    ctx,
+                    &name,
+                    field.as_ref().map(|f| &f[..]),
+                    usage_key.as_ref().map(|k| &k[..]),
+                )
             }
-            PasswordCommand::Generate { chars } => cli::password::generate_password(chars),
+            PasswordCommand::Generate { chars, symbols } => cli::password::generate_password(
+                chars.or(config.generator.length).unwrap_or(20),
+                symbols || config.generator.symbols.unwrap_or(false),
+            ),
         },
         Command::Otp { cmd } => match cmd {
             OtpCommand::Add {
@@ -233,48 +882,112 @@ pub fn match_args(sigil: Sigil) -> Result<(), Error> {
                 algorithm,
                 digits,
                 period,
+                tags,
+                notes,
             } => {
                 if secret.is_some() && name.is_some() {
                     // Safe unwraps because we checked them before and they are required args
-                    if totp {
-                        cli::otp::add_record(
-                            &vault?,
-                            &key?,
-                            ctx?,
-                            OtpRecord::new_totp(
-                                secret.unwrap(),
-                                issuer,
-                                algorithm.unwrap_or(HmacAlgorithm::SHA1),
-                                digits.unwrap_or(6),
-                                period.unwrap_or(30),
-                            ),
-                            name.unwrap(),
-                        )
+                    let secret = otp::normalize_secret(&secret.unwrap())?;
+                    let algorithm = algorithm
+                        .or_else(|| config.otp.algorithm.clone())
+                        .unwrap_or(HmacAlgorithm::SHA1);
+                    let digits = digits.or(config.otp.digits).unwrap_or(6);
+                    otp::check_digits(digits)?;
+                    let mut record = if totp {
+                        let period = period.or(config.otp.period).unwrap_or(30);
+                        otp::check_period(period)?;
+                        OtpRecord::new_totp(secret, issuer, algorithm, digits, period)
                     } else if hotp {
-                        cli::otp::add_record(
-                            &vault?,
-                            &key?,
-                            ctx?,
-                            OtpRecord::new_hotp(
-                                secret.unwrap(),
-                                issuer,
-                                algorithm.unwrap_or(HmacAlgorithm::SHA1),
-                                digits.unwrap_or(6),
-                            ),
-                            name.unwrap(),
-                        )
+                        OtpRecord::new_hotp(secret, issuer, algorithm, digits, 0)
                     } else {
                         unreachable!()
-                    }
+                    };
+                    *record.notes_mut() = notes;
+                    add_tags(record.tags_mut(), tags);
+                    cli::otp::add_record(&vault?, &key?, ctx, record, name.unwrap())
                 } else {
-                    cli::otp::add_record_interactive(&vault?, &key?, ctx?)
+                    cli::otp::add_record_interactive(&vault?, &key?, ctx, &config.otp)
                 }
             }
-            OtpCommand::ImportUrl { url } => cli::otp::import_url(&vault?, &key?, ctx?, &url),
-            OtpCommand::GetToken { name, counter } => {
-                cli::otp::get_token(&vault?, ctx?, &name, counter)
+            OtpCommand::Edit {
+                name,
+                notes,
+                tags,
+                untags,
+            } => cli::otp::edit_record(&vault?, &key?, ctx, &name, |record| {
+                // An empty string removes the notes
+                if let Some(notes) = notes {
+                    *record.notes_mut() = Some(notes).filter(|n| !n.is_empty());
+                }
+                add_tags(record.tags_mut(), tags);
+                remove_tags(record.tags_mut(), &untags);
+            }),
+            OtpCommand::ImportUrl {
+                url,
+                image,
+                on_conflict,
+            } => match (url, image) {
+                (Some(url), _) => cli::otp::import_url(&vault?, &key?, ctx, &url, on_conflict),
+                (_, Some(image)) => {
+                    cli::otp::import_image(&vault?, &key?, ctx, &image, on_conflict)
+                }
+                _ => unreachable!(),
+            },
+            OtpCommand::Export { name, qr, output } => {
+                cli::otp::export_record(&vault?, ctx, &name, qr, output)
             }
-            OtpCommand::Remove { name } => cli::otp::remove_record(&vault?, &key?, ctx?, name),
+            OtpCommand::GetToken { name, counter } => cli::otp::get_token(
+                &vault?,
+                key.as_ref().ok().map(|k| &k[..]),
+                ctx,
+                &name,
+                counter,
+                track_usage,
+            ),
+            OtpCommand::Remove { name } => cli::otp::remove_record(&vault?, &key?, ctx, name),
         },
     }
 }
+
+/// Locates a record in the vault of a profile, or in the current vault
+fn locate(
+    config: &Config,
+    profile: Option<&String>,
+    name: String,
+    vault: &Result<PathBuf, Error>,
+    key: &Result<String, Error>,
+) -> Result<Location, Error> {
+    let (vault, key) = match profile {
+        Some(profile) => {
+            let profile = config.profile(profile)?;
+            // Safe unwrap, profiles without a vault are refused
+            let vault = profile.vault.clone().unwrap();
+            // age vaults don't need a key, see `match_args`
+            let is_age = BackendKind::detect_file(&vault) == Some(BackendKind::Age);
+            let key = match (&profile.key, key) {
+                (Some(key), _) => key.clone(),
+                (None, Ok(key)) => key.clone(),
+                (None, Err(_)) if is_age => String::new(),
+                (None, Err(e)) => bail!("{}", e),
+            };
+            (vault, key)
+        }
+        None => match (vault, key) {
+            (Ok(vault), Ok(key)) => (vault.clone(), key.clone()),
+            (Err(e), _) | (_, Err(e)) => bail!("{}", e),
+        },
+    };
+
+    Ok(Location { vault, key, name })
+}
+
+/// Parses the "key=value" custom fields of a password. Secret fields take
+/// precedence over plain ones with the same key
+fn parse_fields(
+    fields: &[String],
+    secret_fields: &[String],
+) -> Result<BTreeMap<String, Field>, Error> {
+    let fields = fields.iter().map(|spec| Field::parse(spec, false));
+    let secret_fields = secret_fields.iter().map(|spec| Field::parse(spec, true));
+    Ok(fields.chain(secret_fields).collect::<Result<_, _>>()?)
+}
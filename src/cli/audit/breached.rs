@@ -0,0 +1,57 @@
+// Copyright (C) 2018 Alberto Coscia
+//
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at http://mozilla.org/MPL/2.0/.
+
+use failure::Error;
+use lib::audit::breached::breached_passwords;
+use lib::backend::Crypto;
+use lib::utils;
+use std::fs::File;
+use std::path::PathBuf;
+
+/// Reports the passwords found in a local copy of the Pwned Passwords file by
+/// record ID, and fails if there are any. Nothing is sent over the network
+/**
+ * Blueprint
+ *  1. `read_vault`, bail on error
+ *  2. Look up every password in `hibp_db`
+ *  3. Print every breached password, most seen first
+ *  4. Bail if any password was found
+ */
+pub fn audit_breached(
+    vault_path: &PathBuf,
+    mut ctx: Crypto,
+    hibp_db: &PathBuf,
+) -> Result<(), Error> {
+    tracepoint!();
+
+    // (1)
+    // TODO These unwraps are due to the fact that the errors cannot be made
+    // into failure::Error's. Find a workaround
+    let vault = utils::read_vault(vault_path, &mut ctx).unwrap();
+
+    // (2)
+    let db = File::open(hibp_db)
+        .map_err(|e| format_err!("Failed to open {}: {}", hibp_db.display(), e))?;
+    let breached = breached_passwords(&vault, db)?;
+
+    // (3)
+    for record in &breached {
+        match record.count {
+            1 => println!("{}: seen once in breaches", record.id),
+            n => println!("{}: seen {} times in breaches", record.id, n),
+        }
+    }
+
+    // (4)
+    match breached.len() {
+        0 => {}
+        1 => bail!("1 password was found in breaches"),
+        n => bail!("{} passwords were found in breaches", n),
+    }
+    println!("No password was found in breaches");
+
+    Ok(())
+}
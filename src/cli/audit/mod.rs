@@ -0,0 +1,15 @@
+// Copyright (C) 2018 Alberto Coscia
+//
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at http://mozilla.org/MPL/2.0/.
+
+mod breached;
+mod passwords;
+mod policy;
+mod stale;
+
+pub use self::breached::audit_breached;
+pub use self::passwords::audit_passwords;
+pub use self::policy::set_max_age;
+pub use self::stale::audit_stale;
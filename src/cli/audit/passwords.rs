@@ -0,0 +1,99 @@
+// Copyright (C) 2018 Alberto Coscia
+//
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at http://mozilla.org/MPL/2.0/.
+
+use failure::Error;
+use lib::audit::passwords::{common_passwords, listed_passwords, reused_passwords, weak_passwords};
+use lib::audit::strength::Estimator;
+use lib::backend::Crypto;
+use lib::utils;
+use std::collections::BTreeSet;
+use std::fs::File;
+use std::io::BufReader;
+use std::path::PathBuf;
+
+/// Reports reused, weak and common passwords by record ID, never printing the
+/// passwords themselves, and fails if there are any
+/**
+ * Blueprint
+ *  1. `read_vault`, bail on error
+ *  2. Report the groups of records sharing a password
+ *  3. Report the passwords scoring below `min_score`, weakest first
+ *  4. Report the passwords found among the most common ones or in `wordlist`
+ *  5. Bail if any password was reported
+ */
+pub fn audit_passwords(
+    vault_path: &PathBuf,
+    mut ctx: Crypto,
+    min_score: u8,
+    wordlist: Option<&PathBuf>,
+) -> Result<(), Error> {
+    tracepoint!();
+    ensure!(min_score <= 4, "The minimum score must be between 0 and 4");
+
+    // (1)
+    // TODO These unwraps are due to the fact that the errors cannot be made
+    // into failure::Error's. Find a workaround
+    let vault = utils::read_vault(vault_path, &mut ctx).unwrap();
+    let estimator = Estimator::new();
+    let mut flagged = BTreeSet::new();
+
+    // (2)
+    let reused = reused_passwords(&vault);
+    if !reused.is_empty() {
+        println!("Reused passwords:");
+        for ids in &reused {
+            println!("    {}", ids.join(", "));
+            flagged.extend(ids.iter().cloned());
+        }
+    }
+
+    // (3)
+    let weak = weak_passwords(&vault, &estimator, min_score);
+    if !weak.is_empty() {
+        println!("Weak passwords:");
+        for record in &weak {
+            match record.strength.pattern {
+                Some(pattern) => println!(
+                    "    {}: {}, mostly a {}",
+                    record.id,
+                    record.strength.describe(),
+                    pattern
+                ),
+                None => println!(
+                    "    {}: {}, too short",
+                    record.id,
+                    record.strength.describe()
+                ),
+            }
+            flagged.insert(record.id);
+        }
+    }
+
+    // (4)
+    let mut common = common_passwords(&vault, &estimator);
+    if let Some(wordlist) = wordlist {
+        let file = File::open(wordlist)
+            .map_err(|e| format_err!("Failed to open {}: {}", wordlist.display(), e))?;
+        common.extend(listed_passwords(&vault, BufReader::new(file))?);
+    }
+    if !common.is_empty() {
+        println!("Common passwords:");
+        for id in &common {
+            println!("    {}", id);
+        }
+        flagged.extend(common);
+    }
+
+    // (5)
+    match flagged.len() {
+        0 => {}
+        1 => bail!("1 password failed the audit"),
+        n => bail!("{} passwords failed the audit", n),
+    }
+    println!("No reused, weak or common password found");
+
+    Ok(())
+}
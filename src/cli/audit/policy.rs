@@ -0,0 +1,59 @@
+// Copyright (C) 2018 Alberto Coscia
+//
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at http://mozilla.org/MPL/2.0/.
+
+use failure::Error;
+use lib::backend::Crypto;
+use lib::utils;
+use std::path::PathBuf;
+
+/// Sets the maximum age of the passwords with a tag, or lists the maximum
+/// ages of all tags
+/**
+ * Blueprint
+ *  1. `read_vault`, bail on error
+ *  2. If no tag is provided, print every tag policy
+ *  3. Otherwise, replace the policy of the tag, ignoring its case, unless
+ *     `days` is 0
+ *  4. `write_vault`, bail on error
+ */
+pub fn set_max_age(
+    vault_path: &PathBuf,
+    key: &str,
+    mut ctx: Crypto,
+    tag: Option<&str>,
+    days: Option<u32>,
+) -> Result<(), Error> {
+    tracepoint!();
+
+    // (1)
+    // TODO These unwraps are due to the fact that the errors cannot be made
+    // into failure::Error's. Find a workaround
+    let mut vault = utils::read_vault(vault_path, &mut ctx).unwrap();
+
+    // (2)
+    let (tag, days) = match (tag, days) {
+        (Some(tag), Some(days)) => (tag.trim(), days),
+        (Some(tag), None) => bail!("Please provide the maximum age of {}, in days", tag),
+        _ => {
+            for (tag, days) in &vault.max_age {
+                println!("{}: {} days", tag, days);
+            }
+            return Ok(());
+        }
+    };
+    ensure!(!tag.is_empty(), "Please provide a non-empty tag");
+
+    // (3)
+    vault.max_age.retain(|t, _| !t.eq_ignore_ascii_case(tag));
+    if days > 0 {
+        vault.max_age.insert(tag.to_string(), days);
+    }
+
+    // (4)
+    utils::write_vault(vault_path, &vault, &mut ctx, key).unwrap();
+
+    Ok(())
+}
@@ -0,0 +1,61 @@
+// Copyright (C) 2018 Alberto Coscia
+//
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at http://mozilla.org/MPL/2.0/.
+
+use chrono::Utc;
+use failure::Error;
+use lib::audit::stale::stale_records;
+use lib::backend::Crypto;
+use lib::utils;
+use std::path::PathBuf;
+
+/// Lists the passwords older than their maximum age, failing if there are
+/// any so that it can be scheduled
+/**
+ * Blueprint
+ *  1. `read_vault`, bail on error
+ *  2. `stale_records`, using `default` for passwords without a policy
+ *  3. Print every stale password, most overdue first
+ *  4. Bail if any password is stale
+ */
+pub fn audit_stale(
+    vault_path: &PathBuf,
+    mut ctx: Crypto,
+    default: Option<u32>,
+) -> Result<(), Error> {
+    tracepoint!();
+
+    // (1)
+    // TODO These unwraps are due to the fact that the errors cannot be made
+    // into failure::Error's. Find a workaround
+    let vault = utils::read_vault(vault_path, &mut ctx).unwrap();
+
+    // (2)
+    let stale = stale_records(&vault, default, Utc::now());
+
+    // (3)
+    for record in &stale {
+        let overdue = match record.overdue.map(|o| o.num_days()) {
+            Some(0) => "overdue by less than a day".to_string(),
+            Some(1) => "overdue by 1 day".to_string(),
+            Some(days) => format!("overdue by {} days", days),
+            None => "never dated".to_string(),
+        };
+        println!(
+            "{}: {} (max age {} days)",
+            record.id, overdue, record.max_age
+        );
+    }
+
+    // (4)
+    match stale.len() {
+        0 => {}
+        1 => bail!("1 password is older than its maximum age"),
+        n => bail!("{} passwords are older than their maximum age", n),
+    }
+    println!("No password is older than its maximum age");
+
+    Ok(())
+}
@@ -0,0 +1,90 @@
+// Copyright (C) 2018 Alberto Coscia
+//
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at http://mozilla.org/MPL/2.0/.
+
+use failure::Error;
+use lib::config;
+use std::fs::{create_dir_all, read_to_string, write};
+use std::io;
+use std::path::PathBuf;
+use toml::value::Table;
+use toml::{from_str, to_string, Value};
+
+/// Prints a setting of the configuration file
+pub fn get_config(config_path: &PathBuf, key: &str) -> Result<(), Error> {
+    tracepoint!();
+    let config = read_config(config_path)?;
+    match config::get_value(&config, key)? {
+        Some(value) => println!("{}", config::display_value(value)),
+        None => bail!("{} is not set in {}", key, config_path.display()),
+    }
+
+    Ok(())
+}
+
+/// Changes a setting of the configuration file
+/**
+ * Blueprint
+ *  1. Read the configuration file, an empty one if it does not exist
+ *  2. `config::set_value`, bail on error
+ *  3. Write the configuration file, creating its directory if needed
+ */
+pub fn set_config(config_path: &PathBuf, key: &str, value: &str) -> Result<(), Error> {
+    tracepoint!();
+    // (1)
+    let mut config = read_config(config_path)?;
+
+    // (2)
+    config::set_value(&mut config, key, value)?;
+
+    // (3)
+    write_config(config_path, config)
+}
+
+/// Pins a GPG key, so that vaults may be encrypted to it
+/**
+ * Blueprint
+ *  1. Read the configuration file, an empty one if it does not exist
+ *  2. `config::pin_key`, bail on error
+ *  3. Write the configuration file if the key was not pinned yet
+ */
+pub fn pin_key(config_path: &PathBuf, fingerprint: &str) -> Result<(), Error> {
+    tracepoint!();
+    // (1)
+    let mut config = read_config(config_path)?;
+
+    // (2)
+    let fingerprint = config::normalize_fingerprint(fingerprint);
+    if !config::pin_key(&mut config, &fingerprint)? {
+        println!("{} is already pinned", fingerprint);
+        return Ok(());
+    }
+
+    // (3)
+    write_config(config_path, config)?;
+    println!("Pinned {}", fingerprint);
+
+    Ok(())
+}
+
+fn read_config(config_path: &PathBuf) -> Result<Table, Error> {
+    match read_to_string(config_path) {
+        Ok(contents) => from_str(&contents)
+            .map_err(|e| format_err!("Failed to read {} ({})", config_path.display(), e)),
+        Err(ref e) if e.kind() == io::ErrorKind::NotFound => Ok(Table::new()),
+        Err(e) => Err(From::from(e)),
+    }
+}
+
+/// Writes the configuration file, creating its directory if needed
+fn write_config(config_path: &PathBuf, config: Table) -> Result<(), Error> {
+    if let Some(parent) = config_path.parent() {
+        create_dir_all(parent)?;
+    }
+    // Values are written before tables only when serialized as a `Value`
+    write(config_path, to_string(&Value::Table(config))?)?;
+
+    Ok(())
+}
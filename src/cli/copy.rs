@@ -0,0 +1,92 @@
+// Copyright (C) 2018 Alberto Coscia
+//
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at http://mozilla.org/MPL/2.0/.
+
+use failure::Error;
+use lib::backend::Crypto;
+use lib::utils;
+use std::fs;
+use std::path::PathBuf;
+
+/// A record or folder in a vault, with the key the vault is encrypted to
+pub struct Location {
+    pub vault: PathBuf,
+    pub key: String,
+    pub name: String,
+}
+
+/// Copies a record, or a folder and its contents, to another name and
+/// possibly another vault. If `remove`, the originals are removed afterwards
+/**
+ * Blueprint
+ *  1. `read_vault` the source vault, bail on error
+ *  2. If both are the same vault file, however their paths are spelled,
+ *     `vault.move_records` or
+ *     `vault.copy_records_to` a copy of it and `write_vault`, bail on error
+ *  3. Otherwise, `read_vault` the destination vault,
+ *     `vault.copy_records_to` it and `write_vault` it first, so that records
+ *     cannot be lost, then `vault.remove_records` and `write_vault` the
+ *     source vault if `remove`, bail on error
+ */
+pub fn copy_records(
+    from: &Location,
+    to: &Location,
+    mut ctx: Crypto,
+    remove: bool,
+) -> Result<(), Error> {
+    tracepoint!();
+
+    // (1)
+    let mut source = utils::read_vault(&from.vault, &mut ctx)
+        .map_err(|e| format_err!("Failed to read {} ({})", from.vault.display(), e))?;
+
+    // (2)
+    if same_file(&from.vault, &to.vault) {
+        let copied = if remove {
+            source.move_records(&from.name, &to.name)?
+        } else {
+            source
+                .clone()
+                .copy_records_to(&from.name, &mut source, &to.name)?
+        };
+        utils::write_vault(&to.vault, &source, &mut ctx, &to.key)
+            .map_err(|e| format_err!("Failed to write {} ({})", to.vault.display(), e))?;
+        println!(
+            "{} records {}",
+            copied,
+            if remove { "moved" } else { "copied" }
+        );
+
+        return Ok(());
+    }
+
+    // (3)
+    let mut dest = utils::read_vault(&to.vault, &mut ctx)
+        .map_err(|e| format_err!("Failed to read {} ({})", to.vault.display(), e))?;
+    let copied = source.copy_records_to(&from.name, &mut dest, &to.name)?;
+    utils::write_vault(&to.vault, &dest, &mut ctx, &to.key)
+        .map_err(|e| format_err!("Failed to write {} ({})", to.vault.display(), e))?;
+    if remove {
+        source.remove_records(&from.name, true)?;
+        utils::write_vault(&from.vault, &source, &mut ctx, &from.key)
+            .map_err(|e| format_err!("Failed to write {} ({})", from.vault.display(), e))?;
+    }
+    println!(
+        "{} records {} to {}",
+        copied,
+        if remove { "moved" } else { "copied" },
+        to.vault.display()
+    );
+
+    Ok(())
+}
+
+/// Whether two paths lead to the same file, such as `vault` and `./vault`
+fn same_file(a: &PathBuf, b: &PathBuf) -> bool {
+    match (fs::canonicalize(a), fs::canonicalize(b)) {
+        (Ok(a), Ok(b)) => a == b,
+        _ => a == b,
+    }
+}
@@ -0,0 +1,152 @@
+// Copyright (C) 2018 Alberto Coscia
+//
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at http://mozilla.org/MPL/2.0/.
+
+use failure::Error;
+use lib::backend::{BackendKind, Crypto};
+use lib::export::{self, csv, json, keepass_xml, pass, ExportFormat};
+use lib::utils;
+#[cfg(unix)]
+use std::fs::Permissions;
+use std::fs::{create_dir_all, OpenOptions};
+use std::io::Write;
+#[cfg(unix)]
+use std::os::unix::fs::{OpenOptionsExt, PermissionsExt};
+use std::path::{Path, PathBuf};
+
+/// Exports a vault to a format other password managers can import
+/**
+ * Blueprint
+ *  1. Check that we're not writing on an existing destination, bail if true
+ *     and not --force'ing
+ *  2. Unless the export is encrypted, ask for confirmation before writing
+ *     secrets in plaintext, bail if not given
+ *  3. `read_vault`, bail on error
+ *  4. Serialize the vault to `format` and write it to `dest`, encrypting it
+ *     to `recipient` if provided
+ *      i) Pass: write a `.gpg-id` file listing one recipient per line and
+ *         an encrypted file for every entry, skipping record IDs that would
+ *         escape the store
+ *      ii) Other formats: write a single file
+ */
+pub fn export_vault(
+    vault_path: &PathBuf,
+    mut ctx: Crypto,
+    format: ExportFormat,
+    dest: &Path,
+    recipient: Option<String>,
+    force: bool,
+    plaintext: bool,
+) -> Result<(), Error> {
+    tracepoint!();
+
+    // (1)
+    if dest.exists() && !force {
+        bail!(
+            "Export destination already exists, use --force to overwrite ({})",
+            dest.display()
+        )
+    }
+
+    // (2)
+    if recipient.is_none() && !plaintext {
+        let answer = question!(
+            |s: String| -> Result<String, Error> { Ok(s) },
+            "This will write your secrets UNENCRYPTED to {}. Type \"yes\" to continue: ",
+            dest.display()
+        )?;
+        ensure!(
+            answer == "yes",
+            "Export aborted, use --encrypt-to to encrypt it instead"
+        );
+    }
+
+    // (3)
+    // TODO These unwraps are due to the fact that the errors cannot be made
+    // into failure::Error's. Find a workaround
+    let vault = utils::read_vault(vault_path, &mut ctx).unwrap();
+
+    // (4)
+    let contents = match format {
+        // (4.i)
+        ExportFormat::Pass => {
+            let recipient = recipient
+                .ok_or_else(|| format_err!("A pass store must be encrypted to a recipient"))?;
+            ensure!(
+                !dest.is_file(),
+                "Export destination is not a directory ({})",
+                dest.display()
+            );
+            create_dir_all(dest)?;
+            let gpg_id: String = recipient
+                .split(',')
+                .map(str::trim)
+                .filter(|r| !r.is_empty())
+                .map(|r| format!("{}\n", r))
+                .collect();
+            write_file(&dest.join(".gpg-id"), &gpg_id)?;
+
+            let mut exported = 0;
+            for entry in export::entries(&vault) {
+                let path = match pass::entry_path(entry.id) {
+                    Some(path) => dest.join(path),
+                    None => {
+                        println!("Skipped {}, its name is not a valid path", entry.id);
+                        continue;
+                    }
+                };
+                if let Some(parent) = path.parent() {
+                    create_dir_all(parent)?;
+                }
+                let contents = encrypt_to(pass::to_entry(&entry).as_bytes(), &mut ctx, &recipient)?;
+                write_file(&path, &contents)?;
+                exported += 1;
+            }
+            println!("{} entries exported to {}", exported, dest.display());
+
+            return Ok(());
+        }
+        // (4.ii)
+        ExportFormat::Csv => csv::to_csv(&vault)?,
+        ExportFormat::Json => json::to_json(&vault)?,
+        ExportFormat::KeepassXml => keepass_xml::to_keepass_xml(&vault),
+    };
+    let contents = match recipient {
+        Some(ref recipient) => encrypt_to(contents.as_bytes(), &mut ctx, recipient)?,
+        None => contents,
+    };
+    write_file(dest, &contents)?;
+    println!("Vault exported to {}", dest.display());
+
+    Ok(())
+}
+
+/// Encrypts an export to comma-separated GPG recipients
+fn encrypt_to(data: &[u8], ctx: &mut Crypto, recipient: &str) -> Result<String, Error> {
+    let output = ctx
+        .backend(BackendKind::Gpg)
+        .and_then(|backend| backend.encrypt(data, recipient))
+        .map_err(|e| format_err!("Failed to encrypt the export ({})", e))?;
+
+    Ok(String::from_utf8_lossy(&output.ciphertext).into_owned())
+}
+
+/// Writes a file only the current user can read
+fn write_file(path: &Path, contents: &str) -> Result<(), Error> {
+    let mut options = OpenOptions::new();
+    options.write(true).create(true).truncate(true);
+    #[cfg(unix)]
+    options.mode(0o600);
+    let mut file = options
+        .open(path)
+        .map_err(|e| format_err!("Failed to write {} ({})", path.display(), e))?;
+    // The mode only applies to new files, files being overwritten with
+    // --force keep theirs otherwise
+    #[cfg(unix)]
+    file.set_permissions(Permissions::from_mode(0o600))?;
+    file.write_all(contents.as_bytes())?;
+
+    Ok(())
+}
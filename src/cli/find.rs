@@ -0,0 +1,42 @@
+// Copyright (C) 2018 Alberto Coscia
+//
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at http://mozilla.org/MPL/2.0/.
+
+use failure::Error;
+use lib::backend::Crypto;
+use lib::search::{search, Kind};
+use lib::utils;
+use std::path::PathBuf;
+
+/// Fuzzy searches the passwords and OTP generators of a vault
+/**
+ * Blueprint
+ *  1. `read_vault`, bail on error
+ *  2. `search` record IDs and metadata, bail if nothing matches
+ *  3. Print the matches, best first, with the field that matched
+ */
+pub fn find_records(vault_path: &PathBuf, mut ctx: Crypto, query: &str) -> Result<(), Error> {
+    tracepoint!();
+
+    // (1)
+    // TODO These unwraps are due to the fact that the errors cannot be made
+    // into failure::Error's. Find a workaround
+    let vault = utils::read_vault(vault_path, &mut ctx).unwrap();
+
+    // (2)
+    let matches = search(&vault, query, &[Kind::Password, Kind::Otp]);
+    ensure!(!matches.is_empty(), "No record matches {}", query);
+
+    // (3)
+    for m in matches {
+        if m.field == "name" {
+            println!("{} ({})", m.id, m.kind);
+        } else {
+            println!("{} ({}, matched by {})", m.id, m.kind, m.field);
+        }
+    }
+
+    Ok(())
+}
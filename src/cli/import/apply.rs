@@ -0,0 +1,148 @@
+// Copyright (C) 2018 Alberto Coscia
+//
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at http://mozilla.org/MPL/2.0/.
+
+use failure::Error;
+use lib::backend::Crypto;
+use lib::import::Import;
+use lib::types::ConflictPolicy;
+use lib::utils;
+use std::path::PathBuf;
+
+/// Adds the records of an `Import` to a vault in a single write
+/**
+ * Blueprint
+ *  1. `read_vault`, bail on error
+ *  2. Add every record to the vault. If an ID is already in use, handle it
+ *     according to `on_conflict`. If no policy was provided, bail or, on a
+ *     dry run, report the conflict
+ *  3. `write_vault` unless this is a dry run, bail on error
+ *  4. Report what was imported and what was skipped
+ */
+pub fn apply_import(
+    vault_path: &PathBuf,
+    key: &str,
+    mut ctx: Crypto,
+    import: Import,
+    on_conflict: Option<ConflictPolicy>,
+    dry_run: bool,
+) -> Result<(), Error> {
+    tracepoint!();
+
+    // (1)
+    // TODO These unwraps are due to the fact that the errors cannot be made
+    // into failure::Error's. Find a workaround
+    let mut vault = utils::read_vault(vault_path, &mut ctx).unwrap();
+
+    // (2)
+    let mut outcome = Outcome {
+        dry_run,
+        imported: 0,
+        skipped: import.skipped.len(),
+        conflicts: 0,
+    };
+    for (record_id, record) in import.passwords {
+        let stored = match on_conflict {
+            Some(policy) => vault.import_record(record, record_id.clone(), policy),
+            None if vault.passwords.contains_key(&record_id) => {
+                ensure!(
+                    dry_run,
+                    "A password named {} already exists, use --on-conflict to skip, rename or overwrite it",
+                    record_id
+                );
+                outcome.conflict("password", &record_id);
+                continue;
+            }
+            None => {
+                vault.add_record(record, record_id.clone())?;
+                Some(record_id.clone())
+            }
+        };
+        outcome.report("password", &record_id, stored);
+    }
+    for (record_id, record) in import.otps {
+        let stored = match on_conflict {
+            Some(policy) => vault.import_otp_record(record, record_id.clone(), policy),
+            None if vault.otps.contains_key(&record_id) => {
+                ensure!(
+                    dry_run,
+                    "A generator named {} already exists, use --on-conflict to skip, rename or overwrite it",
+                    record_id
+                );
+                outcome.conflict("generator", &record_id);
+                continue;
+            }
+            None => {
+                vault.add_otp_record(record, record_id.clone())?;
+                Some(record_id.clone())
+            }
+        };
+        outcome.report("generator", &record_id, stored);
+    }
+
+    // (3)
+    if !dry_run {
+        utils::write_vault(vault_path, &vault, &mut ctx, key).unwrap();
+    }
+
+    // (4)
+    for (entry, reason) in &import.skipped {
+        println!("Skipped {} ({})", entry, reason);
+    }
+    if dry_run {
+        println!(
+            "Dry run: {} records would be imported, {} skipped, {} conflicting",
+            outcome.imported, outcome.skipped, outcome.conflicts
+        );
+        if outcome.conflicts > 0 {
+            println!("Use --on-conflict to skip, rename or overwrite conflicting records");
+        }
+    } else {
+        println!(
+            "{} records imported, {} skipped",
+            outcome.imported, outcome.skipped
+        );
+    }
+
+    Ok(())
+}
+
+/// Keeps track of what an import did, or would do on a dry run
+struct Outcome {
+    dry_run: bool,
+    imported: usize,
+    skipped: usize,
+    conflicts: usize,
+}
+
+impl Outcome {
+    /// Prints and counts the outcome of importing a record
+    fn report(&mut self, kind: &str, record_id: &str, stored: Option<String>) {
+        let verb = if self.dry_run {
+            "Would import"
+        } else {
+            "Imported"
+        };
+        match stored {
+            Some(ref id) if id != record_id => {
+                println!("{} {} {} as {}", verb, kind, record_id, id);
+                self.imported += 1;
+            }
+            Some(_) => {
+                println!("{} {} {}", verb, kind, record_id);
+                self.imported += 1;
+            }
+            None => {
+                println!("Skipped {} {}, its name is already in use", kind, record_id);
+                self.skipped += 1;
+            }
+        }
+    }
+
+    fn conflict(&mut self, kind: &str, record_id: &str) {
+        println!("Conflict: a {} named {} already exists", kind, record_id);
+        self.conflicts += 1;
+    }
+}
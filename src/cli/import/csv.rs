@@ -0,0 +1,39 @@
+// Copyright (C) 2018 Alberto Coscia
+//
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at http://mozilla.org/MPL/2.0/.
+
+use cli::import::apply_import;
+use failure::Error;
+use lib::backend::Crypto;
+use lib::import::csv::{self, Mapping};
+use lib::types::ConflictPolicy;
+use std::fs::File;
+use std::path::{Path, PathBuf};
+
+/// Imports passwords from a CSV export
+/**
+ * Blueprint
+ *  1. Open and read the CSV file using `mapping`, bail on error
+ *  2. `apply_import`
+ */
+pub fn import_csv(
+    vault_path: &PathBuf,
+    key: &str,
+    ctx: Crypto,
+    file: &Path,
+    mapping: &Mapping,
+    on_conflict: Option<ConflictPolicy>,
+    dry_run: bool,
+) -> Result<(), Error> {
+    tracepoint!();
+
+    // (1)
+    let mut source =
+        File::open(file).map_err(|e| format_err!("Failed to open {} ({})", file.display(), e))?;
+    let import = csv::read_csv(&mut source, mapping)?;
+
+    // (2)
+    apply_import(vault_path, key, ctx, import, on_conflict, dry_run)
+}
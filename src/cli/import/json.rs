@@ -0,0 +1,63 @@
+// Copyright (C) 2018 Alberto Coscia
+//
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at http://mozilla.org/MPL/2.0/.
+
+use cli::import::apply_import;
+use failure::Error;
+use lib::backend::Crypto;
+use lib::import::{aegis, andotp, bitwarden, Import};
+use lib::types::ConflictPolicy;
+use std::fs::File;
+use std::io::Read;
+use std::path::{Path, PathBuf};
+
+/// Imports logins and TOTP generators from an unencrypted Bitwarden JSON
+/// export
+pub fn import_bitwarden(
+    vault_path: &PathBuf,
+    key: &str,
+    ctx: Crypto,
+    file: &Path,
+    on_conflict: Option<ConflictPolicy>,
+) -> Result<(), Error> {
+    tracepoint!();
+    let import = read_file(file, bitwarden::read_export)?;
+    apply_import(vault_path, key, ctx, import, on_conflict, false)
+}
+
+/// Imports OTP generators from an unencrypted Aegis JSON export
+pub fn import_aegis(
+    vault_path: &PathBuf,
+    key: &str,
+    ctx: Crypto,
+    file: &Path,
+    on_conflict: Option<ConflictPolicy>,
+) -> Result<(), Error> {
+    tracepoint!();
+    let import = read_file(file, aegis::read_export)?;
+    apply_import(vault_path, key, ctx, import, on_conflict, false)
+}
+
+/// Imports OTP generators from a plain-text andOTP JSON backup
+pub fn import_andotp(
+    vault_path: &PathBuf,
+    key: &str,
+    ctx: Crypto,
+    file: &Path,
+    on_conflict: Option<ConflictPolicy>,
+) -> Result<(), Error> {
+    tracepoint!();
+    let import = read_file(file, andotp::read_backup)?;
+    apply_import(vault_path, key, ctx, import, on_conflict, false)
+}
+
+fn read_file<F>(file: &Path, read: F) -> Result<Import, Error>
+where
+    F: Fn(&mut dyn Read) -> Result<Import, Error>,
+{
+    let mut source =
+        File::open(file).map_err(|e| format_err!("Failed to open {} ({})", file.display(), e))?;
+    read(&mut source)
+}
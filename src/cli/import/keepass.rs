@@ -0,0 +1,63 @@
+// Copyright (C) 2018 Alberto Coscia
+//
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at http://mozilla.org/MPL/2.0/.
+
+use cli::import::apply_import;
+use failure::Error;
+use lib::backend::Crypto;
+use lib::import::keepass;
+use lib::types::ConflictPolicy;
+use rpassword;
+use std::fs::File;
+use std::io::Read;
+use std::path::{Path, PathBuf};
+
+/// Imports the entries of a KeePass KDBX database
+/**
+ * Blueprint
+ *  1. Open the database and key files, bail on error
+ *  2. Ask for the master password, which may be empty if a key file is used
+ *  3. Read every entry of the database, bail on error
+ *  4. `apply_import`
+ */
+pub fn import_keepass(
+    vault_path: &PathBuf,
+    key: &str,
+    ctx: Crypto,
+    database: &Path,
+    keyfile: Option<PathBuf>,
+    on_conflict: Option<ConflictPolicy>,
+) -> Result<(), Error> {
+    tracepoint!();
+
+    // (1)
+    let mut database = File::open(database)
+        .map_err(|e| format_err!("Failed to open {} ({})", database.display(), e))?;
+    let mut keyfile = match keyfile {
+        Some(path) => Some(
+            File::open(&path)
+                .map_err(|e| format_err!("Failed to open {} ({})", path.display(), e))?,
+        ),
+        None => None,
+    };
+
+    // (2)
+    // The password is hidden and used as is, spaces included
+    let password = rpassword::prompt_password("Master password of the database: ")?;
+    ensure!(
+        !password.is_empty() || keyfile.is_some(),
+        "Please provide the master password"
+    );
+
+    // (3)
+    let import = keepass::read_database(
+        &mut database,
+        Some(&password[..]).filter(|p| !p.is_empty()),
+        keyfile.as_mut().map(|f| f as &mut dyn Read),
+    )?;
+
+    // (4)
+    apply_import(vault_path, key, ctx, import, on_conflict, false)
+}
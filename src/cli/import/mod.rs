@@ -0,0 +1,17 @@
+// Copyright (C) 2018 Alberto Coscia
+//
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at http://mozilla.org/MPL/2.0/.
+
+mod apply;
+mod csv;
+mod json;
+mod keepass;
+mod pass;
+
+pub use self::apply::apply_import;
+pub use self::csv::import_csv;
+pub use self::json::{import_aegis, import_andotp, import_bitwarden};
+pub use self::keepass::import_keepass;
+pub use self::pass::import_pass;
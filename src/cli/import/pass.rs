@@ -0,0 +1,108 @@
+// Copyright (C) 2018 Alberto Coscia
+//
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at http://mozilla.org/MPL/2.0/.
+
+use cli::import::apply_import;
+use failure::Error;
+use lib::backend::Crypto;
+use lib::import::{pass, Import};
+use lib::types::ConflictPolicy;
+use lib::utils;
+use std::fs::read_dir;
+use std::path::{Path, PathBuf};
+
+/// Imports the entries of a [pass](https://www.passwordstore.org/) store
+/**
+ * Blueprint
+ *  1. Find every `.gpg` file in the store, skipping hidden files and
+ *     directories, bail on error
+ *  2. Decrypt every entry, bail on error
+ *  3. Parse every entry to a `Record` and/or an `OtpRecord` named after its
+ *     path, skip it if it's empty. An invalid OTP URL only skips the
+ *     `OtpRecord`
+ *  4. `apply_import`
+ */
+pub fn import_pass(
+    vault_path: &PathBuf,
+    key: &str,
+    mut ctx: Crypto,
+    store: &Path,
+    on_conflict: Option<ConflictPolicy>,
+) -> Result<(), Error> {
+    tracepoint!();
+    ensure!(
+        store.is_dir(),
+        "Password store is not a directory ({})",
+        store.display()
+    );
+
+    // (1)
+    let mut entries = Vec::new();
+    find_entries(store, &mut entries)?;
+    entries.sort();
+
+    let mut import = Import::default();
+    for entry in entries {
+        // Safe unwrap because all entries are inside the store
+        let record_id = pass::record_id(store, &entry).unwrap();
+
+        // (2)
+        let contents = ctx
+            .gpg()
+            .and_then(|gpg| utils::unlock_file(&entry, gpg))
+            .map_err(|e| format_err!("Failed to decrypt {} ({})", entry.display(), e))?;
+
+        // (3)
+        let (record, otp) = pass::parse_entry(&contents);
+        let otp = match otp {
+            Ok(otp) => otp,
+            Err(err) => {
+                import.skip_totp(&record_id, &err);
+                if record.is_none() {
+                    continue;
+                }
+                None
+            }
+        };
+        match (record, otp) {
+            (None, None) => import
+                .skipped
+                .push((record_id, "No password or OTP URL".to_string())),
+            (record, otp) => {
+                if let Some(record) = record {
+                    import.passwords.push((record_id.clone(), record));
+                }
+                if let Some(otp) = otp {
+                    import.otps.push((record_id, otp));
+                }
+            }
+        }
+    }
+
+    // (4)
+    apply_import(vault_path, key, ctx, import, on_conflict, false)
+}
+
+/// Recursively finds every `.gpg` file in a directory
+fn find_entries(dir: &Path, entries: &mut Vec<PathBuf>) -> Result<(), Error> {
+    for entry in read_dir(dir)? {
+        let path = entry?.path();
+        // Skip .git, .gpg-id and the like
+        let hidden = path
+            .file_name()
+            .map_or(false, |n| n.to_string_lossy().starts_with('.'));
+        if hidden {
+            continue;
+        }
+
+        if path.is_dir() {
+            find_entries(&path, entries)?;
+        } else if path.extension().map_or(false, |e| e == "gpg") {
+            entries.push(path);
+        }
+    }
+
+    Ok(())
+}
@@ -5,26 +5,36 @@
 // file, You can obtain one at http://mozilla.org/MPL/2.0/.
 
 use failure::Error;
+use lib::backend::Crypto;
+use lib::types::Filter;
 use lib::utils;
 use std::path::PathBuf;
 
-/// Lists all records in a vault
+/// Lists the records in a vault matching a filter, as a tree or as JSON
 /**
  * Blueprint
  *  1. `read_vault`, bail on error
- *  2. `vault.display`
+ *  2. `vault.display`, or `vault.display_json` if `json`
  */
-pub fn list_vault(vault_path: &PathBuf, disclose: bool) -> Result<(), Error> {
+pub fn list_vault(
+    vault_path: &PathBuf,
+    mut ctx: Crypto,
+    disclose: bool,
+    filter: &Filter,
+    json: bool,
+) -> Result<(), Error> {
     tracepoint!();
     // (1)
-    // Acquire a GPGME context
     // TODO Can we handle these failures more nicely?
-    let mut ctx = utils::create_context().unwrap();
     let vault = utils::read_vault(&vault_path, &mut ctx).unwrap();
 
     // (2)
+    if json {
+        println!("{}", vault.display_json(disclose, filter)?);
+        return Ok(());
+    }
     println!("{}", vault_path.display());
-    print!("{}", vault.display(disclose, 0));
+    print!("{}", vault.display(disclose, 0, filter));
 
     Ok(())
 }
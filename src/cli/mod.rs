@@ -40,7 +40,17 @@ macro_rules! question {
 }
 
 pub mod args;
+pub mod audit;
+pub mod config;
+pub mod copy;
+pub mod export;
+pub mod find;
+pub mod import;
 pub mod list;
 pub mod otp;
 pub mod password;
+pub mod rekey;
+pub mod remove;
+pub mod rename;
 pub mod touch;
+pub mod vaults;
@@ -5,15 +5,17 @@
 // file, You can obtain one at http://mozilla.org/MPL/2.0/.
 
 use failure::Error;
-use gpgme::Context;
-use lib::types::{HmacAlgorithm, OtpRecord};
+use lib::backend::Crypto;
+use lib::config::OtpConfig;
+use lib::otp;
+use lib::types::{add_tags, HmacAlgorithm, OtpRecord};
 use lib::utils;
 use std::path::PathBuf;
 
 pub fn add_record(
     vault_path: &PathBuf,
     key: &str,
-    mut ctx: Context,
+    mut ctx: Crypto,
     record: OtpRecord,
     record_id: String,
 ) -> Result<(), Error> {
@@ -30,11 +32,13 @@ pub fn add_record(
  *  1. Get the OTP record kind from the user (allow Hotp and Totp)
  *  2. Get the information necessary to construct a record from the user or from
  *     the args. Trim all strings.
- *      i) Secret: mandatory
+ *      i) Secret: mandatory, must be valid base32
  *      ii) Issuer
- *      iii) Algorithm: default to SHA1
- *      iv) Digits: default to 6
- *      v) Period: default to 30s (TOTP only)
+ *      iii) Algorithm: default to the configured one or SHA1
+ *      iv) Digits: default to the configured ones or 6, must be 6 to 8
+ *      v) Period: default to the configured one or 30s, must be above 0
+ *         (TOTP only)
+ *      vi) Tags, comma-separated
  *  3. Construct a `OtpRecord`
  *  4. Get a record ID from the user, bail if not provided
  *  5. `read_vault`, `vault::add_otp_record`, `write_vault`, bail on error
@@ -42,9 +46,13 @@ pub fn add_record(
 pub fn add_record_interactive(
     vault_path: &PathBuf,
     key: &str,
-    mut ctx: Context,
+    mut ctx: Crypto,
+    defaults: &OtpConfig,
 ) -> Result<(), Error> {
     tracepoint!();
+    let default_algorithm = defaults.algorithm.clone().unwrap_or(HmacAlgorithm::SHA1);
+    let default_digits = defaults.digits.unwrap_or(6);
+    let default_period = defaults.period.unwrap_or(30);
     println!("We are going to add a one-time password generator to the vault.");
     println!("Once a generator has been added, it will be safely stored and you'll be able to generate tokens at any time.");
     println!();
@@ -69,7 +77,7 @@ pub fn add_record_interactive(
         |s: String| if s.is_empty() {
             Err(format_err!("Please provide a non-empty secret"))
         } else {
-            Ok(s)
+            Ok(otp::normalize_secret(&s)?)
         },
         "What is the base-32 encoded secret? "
     )?;
@@ -87,52 +95,65 @@ pub fn add_record_interactive(
     // 2.iii
     let algorithm = question!(
         |s: String| if s.is_empty() {
-            Ok(HmacAlgorithm::SHA1)
+            Ok(default_algorithm.clone())
         } else {
             Ok(s.parse()?)
         },
-        "What HMAC algorithm should be used to generate tokens? ([SHA1]|SHA256|SHA512) "
+        "What HMAC algorithm should be used to generate tokens? (SHA1|SHA256|SHA512) [{:?}] ",
+        default_algorithm
     )?;
 
     // 2.iv
     let digits = question!(
-        |s: String| if s.is_empty() { Ok(6) } else { Ok(s.parse()?) },
-        "How many digits long should a token be? [6] "
+        |s: String| -> Result<u32, Error> {
+            let digits = if s.is_empty() {
+                default_digits
+            } else {
+                s.parse()?
+            };
+            otp::check_digits(digits)?;
+            Ok(digits)
+        },
+        "How many digits long should a token be? [{}] ",
+        default_digits
     )?;
 
-    let record = match &kind[..] {
+    let mut record = match &kind[..] {
         "hotp" => {
             // (3)
-            OtpRecord::Hotp {
-                secret,
-                issuer,
-                algorithm,
-                digits,
-            }
+            OtpRecord::new_hotp(secret, issuer, algorithm, digits, 0)
         }
         "totp" => {
-            // (2.vi)
+            // (2.v)
             let period = question!(
-                |s: String| if s.is_empty() {
-                    Ok(30u64)
-                } else {
-                    Ok(s.parse()?)
+                |s: String| -> Result<u64, Error> {
+                    let period = if s.is_empty() {
+                        default_period
+                    } else {
+                        s.parse()?
+                    };
+                    otp::check_period(period)?;
+                    Ok(period)
                 },
-                "How often, in seconds, should a new token be generated? [30] "
+                "How often, in seconds, should a new token be generated? [{}] ",
+                default_period
             )?;
 
             // (3)
-            OtpRecord::Totp {
-                secret,
-                issuer,
-                algorithm,
-                period,
-                digits,
-            }
+            OtpRecord::new_totp(secret, issuer, algorithm, digits, period)
         }
         _ => unreachable!(),
     };
 
+    // (2.vi)
+    let tags = question!(
+        |s: String| -> Result<Vec<String>, Error> {
+            Ok(s.split(',').map(|t| t.to_string()).collect())
+        },
+        "What tags should this generator have, separated by commas? [None] "
+    )?;
+    add_tags(record.tags_mut(), tags);
+
     // (4)
     let record_id = question!(
         |s: String| if s.is_empty() {
@@ -0,0 +1,119 @@
+// Copyright (C) 2018 Alberto Coscia
+//
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at http://mozilla.org/MPL/2.0/.
+
+use failure::Error;
+use image::{GrayImage, Luma};
+use lib::backend::Crypto;
+use lib::otpauth;
+use lib::utils;
+use qrcode::render::{svg, unicode};
+use qrcode::QrCode;
+use std::fs::write;
+use std::path::PathBuf;
+
+/// Exports an OTP record as an
+/// [otpauth:// URL](https://github.com/google/google-authenticator/wiki/Key-Uri-Format),
+/// optionally rendered as a QR code
+/**
+ * Blueprint
+ *  1. `read_vault`, `vault::get_otp_record`, bail on error
+ *  2. Serialize the record to an `otpauth://` URL
+ *  3. If no QR code was requested, print the URL and return
+ *  4. Encode the URL as a QR code, bail on error
+ *  5. If an output file was provided, render the QR code as a PNG or SVG
+ *     image depending on its extension and write it, bail on error. Otherwise
+ *     print it to the terminal using Unicode blocks
+ */
+pub fn export_record(
+    vault_path: &PathBuf,
+    mut ctx: Crypto,
+    record_id: &str,
+    qr: bool,
+    output: Option<PathBuf>,
+) -> Result<(), Error> {
+    tracepoint!();
+
+    // (1)
+    let vault = utils::read_vault(vault_path, &mut ctx).unwrap();
+    let record = vault.get_otp_record(record_id)?;
+
+    // (2)
+    let url = otpauth::to_url(record_id, record);
+
+    // (3)
+    if !qr {
+        println!("{}", url);
+        return Ok(());
+    }
+
+    // (4)
+    let code = QrCode::new(url.as_bytes())?;
+
+    // (5)
+    match output {
+        Some(path) => {
+            let extension = path
+                .extension()
+                .map(|e| e.to_string_lossy().to_ascii_lowercase());
+            match extension.as_ref().map(|e| &e[..]) {
+                Some("png") => to_png(&code).save(&path)?,
+                Some("svg") => write(
+                    &path,
+                    code.render::<svg::Color>().min_dimensions(256, 256).build(),
+                )?,
+                _ => bail!(
+                    "Unsupported QR code image format, use a .png or .svg file ({})",
+                    path.display()
+                ),
+            }
+        }
+        None => {
+            // Dark and light are swapped so that the code is readable on
+            // terminals with a dark background
+            let image = code
+                .render::<unicode::Dense1x2>()
+                .dark_color(unicode::Dense1x2::Light)
+                .light_color(unicode::Dense1x2::Dark)
+                .build();
+            println!("{}", image);
+        }
+    }
+
+    Ok(())
+}
+
+/// Renders a QR code as a grayscale image, large enough for phone cameras
+fn to_png(code: &QrCode) -> GrayImage {
+    code.render::<Luma<u8>>().min_dimensions(256, 256).build()
+}
+
+#[cfg(test)]
+mod tests {
+    use cli::otp::export::to_png;
+    use lib::otpauth;
+    use lib::types::{HmacAlgorithm, OtpRecord};
+    use qrcode::QrCode;
+    use rqrr::PreparedImage;
+
+    #[test]
+    fn png_round_trip() {
+        let record = OtpRecord::new_hotp(
+            "GEZDGNBVGY3TQOJQGEZDGNBVGY3TQOJQ".to_string(),
+            Some("service".to_string()),
+            HmacAlgorithm::SHA256,
+            8,
+            42,
+        );
+        let url = otpauth::to_url("Bob:service", &record);
+        let code = QrCode::new(url.as_bytes()).unwrap();
+
+        let mut image = PreparedImage::prepare(to_png(&code));
+        let grids = image.detect_grids();
+        assert_eq!(grids.len(), 1);
+        let (_, content) = grids[0].decode().unwrap();
+        assert_eq!(content, url);
+    }
+}
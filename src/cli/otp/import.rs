@@ -4,101 +4,108 @@
 // License, v. 2.0. If a copy of the MPL was not distributed with this
 // file, You can obtain one at http://mozilla.org/MPL/2.0/.
 
+use cli::import::apply_import;
 use failure::Error;
-use gpgme::Context;
-use lib::types::{HmacAlgorithm, OtpRecord};
-use lib::utils;
-use std::collections::HashMap;
+use image;
+use lib::backend::Crypto;
+use lib::import::Import;
+use lib::types::ConflictPolicy;
+use lib::{migration, otpauth};
+use rqrr::PreparedImage;
 use std::path::PathBuf;
-use url::Url;
 
-/// Imports a record from a
+/// Imports records from a
 /// [otpauth:// URL](https://github.com/google/google-authenticator/wiki/Key-Uri-Format)
+/// or a Google Authenticator `otpauth-migration://` URL
+pub fn import_url(
+    vault_path: &PathBuf,
+    key: &str,
+    ctx: Crypto,
+    url: &str,
+    on_conflict: Option<ConflictPolicy>,
+) -> Result<(), Error> {
+    tracepoint!();
+    import_urls(vault_path, key, ctx, &[url.to_string()], on_conflict)
+}
+
+/// Imports records from the `otpauth://` and `otpauth-migration://` URLs found
+/// in the QR codes of an image
 /**
  * Blueprint
- *  1. Parse the URL according to spec, bail on error
- *  2. Construct a `OtpRecord`
- *  3. `read_vault`, `vault::add_otp_record`, `write_vault`, bail on error
+ *  1. Open the image and convert it to grayscale, bail on error
+ *  2. Detect and decode every QR code in the image, skip those that cannot
+ *     be decoded with a warning
+ *  3. Keep the `otpauth://` and `otpauth-migration://` URLs, bail if there
+ *     are none
+ *  4. `import_urls`
  */
-pub fn import_url(
+pub fn import_image(
     vault_path: &PathBuf,
     key: &str,
-    mut ctx: Context,
-    url: &str,
+    ctx: Crypto,
+    image_path: &PathBuf,
+    on_conflict: Option<ConflictPolicy>,
 ) -> Result<(), Error> {
     tracepoint!();
 
     // (1)
-    let url = Url::parse(&url)?;
-    // Can't work on other schemes
-    ensure!(
-        url.scheme().eq_ignore_ascii_case("otpauth"),
-        "Invalid URL scheme"
-    );
+    let image = image::open(image_path)?.to_luma8();
 
-    // TOTP/HOTP is the host
-    let kind = url.host_str().unwrap();
-    // The first and only segment is this record's label
-    let segments = url
-        .path_segments()
-        .map(|c| c.collect::<Vec<&str>>())
-        .unwrap();
-    ensure!(segments.len() == 1, "Expected 1 path segment");
-    let record_id = segments[0].to_string();
-
-    // Iterator -> HashMap
-    let mut query: HashMap<String, String> = HashMap::new();
-    let pairs = url.query_pairs();
-    for pair in pairs {
-        query.insert(pair.0.to_string(), pair.1.to_string());
+    // (2)
+    let mut image = PreparedImage::prepare(image);
+    let mut contents: Vec<String> = Vec::new();
+    for (i, grid) in image.detect_grids().iter().enumerate() {
+        match grid.decode() {
+            Ok((_, content)) => {
+                // The content holds the secret, never log it
+                debug!("Decoded QR code {}", i + 1);
+                contents.push(content);
+            }
+            Err(err) => warn!("Skipping QR code {} ({})", i + 1, err),
+        }
     }
 
-    // Parse query string
-    let secret = (&query["secret"]).to_string();
-    let issuer = query.get("issuer").map(|n| n.to_string());
+    // (3)
+    let urls: Vec<String> = contents
+        .into_iter()
+        .filter(|c| c.starts_with("otpauth://") || c.starts_with("otpauth-migration://"))
+        .collect();
+    ensure!(
+        !urls.is_empty(),
+        "No otpauth:// QR code was found in the image ({})",
+        image_path.display()
+    );
 
-    let algorithm: HmacAlgorithm = query
-        .get("algorithm")
-        .map(|n| n.to_string().to_ascii_uppercase())
-        .unwrap_or_else(|| "SHA1".to_string())
-        .parse()?;
+    // (4)
+    import_urls(vault_path, key, ctx, &urls, on_conflict)
+}
 
-    let digits: u32 = query
-        .get("digits")
-        .unwrap_or(&"6".to_string())
-        .trim()
-        .parse()?;
+/// Imports records from a set of `otpauth://` and `otpauth-migration://` URLs
+/// in a single vault write
+/**
+ * Blueprint
+ *  1. Parse every URL to one or more `OtpRecord`s, bail on error
+ *  2. `apply_import`
+ */
+fn import_urls(
+    vault_path: &PathBuf,
+    key: &str,
+    ctx: Crypto,
+    urls: &[String],
+    on_conflict: Option<ConflictPolicy>,
+) -> Result<(), Error> {
+    tracepoint!();
 
-    let period: u64 = query
-        .get("period")
-        .unwrap_or(&"30".to_string())
-        .trim()
-        .parse()?;
+    // (1)
+    let mut import = Import::default();
+    for url in urls {
+        if url.starts_with("otpauth-migration://") {
+            import.otps.extend(migration::parse_url(url)?);
+        } else {
+            import.otps.push(otpauth::parse_url(url)?);
+        }
+    }
 
     // (2)
-    let record = match &kind.to_ascii_lowercase()[..] {
-        "totp" => OtpRecord::Totp {
-            secret,
-            issuer,
-            algorithm,
-            digits,
-            period,
-        },
-        "hotp" => OtpRecord::Hotp {
-            secret,
-            issuer,
-            algorithm,
-            digits,
-        },
-        _ => unimplemented!(),
-    };
-
-    // (3)
-    // TODO These unwraps are due to the fact that the errors cannot be made
-    // into failure::Error's. Find a workaround
-    let mut vault = utils::read_vault(&vault_path, &mut ctx).unwrap();
-    vault.add_otp_record(record, record_id)?;
-    utils::write_vault(&vault_path, &vault, &mut ctx, &key).unwrap();
-
-    Ok(())
+    apply_import(vault_path, key, ctx, import, on_conflict, false)
 }
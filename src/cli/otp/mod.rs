@@ -5,12 +5,17 @@
 // file, You can obtain one at http://mozilla.org/MPL/2.0/.
 
 mod add;
+mod edit;
+mod export;
 mod import;
 mod remove;
 mod token;
 
 pub use self::add::add_record;
 pub use self::add::add_record_interactive;
+pub use self::edit::edit_record;
+pub use self::export::export_record;
+pub use self::import::import_image;
 pub use self::import::import_url;
 pub use self::remove::remove_record;
 pub use self::token::get_token;
@@ -4,8 +4,10 @@
 // License, v. 2.0. If a copy of the MPL was not distributed with this
 // file, You can obtain one at http://mozilla.org/MPL/2.0/.
 
+use chrono::Utc;
 use failure::Error;
-use gpgme::Context;
+use lib::backend::Crypto;
+use lib::search::{resolve, Kind};
 use lib::types::OtpRecord;
 use lib::utils;
 use std::path::PathBuf;
@@ -13,27 +15,73 @@ use std::path::PathBuf;
 /// Generates an OTP token
 /**
  * Blueprint
- *  1. `read_vault`, `vault::get_otp_record`, bail on error
- *  2. Generate a token, bail on error
+ *  1. `read_vault`, `resolve` the record ID, `vault::get_otp_record_mut`,
+ *     bail on error
+ *  2. Generate a token, which moves HOTP counters forward, bail on error
+ *  3. Save the counter of HOTP generators with `write_vault` before showing
+ *     the token, so that it is never reused, bail on error
+ *  4. If usage is tracked, record the use and `write_vault`, only warn on
+ *     error as the token was already shown
  */
 pub fn get_token(
     vault_path: &PathBuf,
-    mut ctx: Context,
+    key: Option<&str>,
+    mut ctx: Crypto,
     record_id: &str,
     counter: Option<u64>,
+    track_usage: bool,
 ) -> Result<(), Error> {
     tracepoint!();
 
     // (1)
-    let vault = utils::read_vault(&vault_path, &mut ctx).unwrap();
-    let record = vault.get_otp_record(&record_id)?;
+    let mut vault = utils::read_vault(vault_path, &mut ctx)
+        .map_err(|e| format_err!("Failed to read {} ({})", vault_path.display(), e))?;
+    let resolved = resolve(&vault, record_id, Kind::Otp)?.to_string();
+    if resolved != record_id {
+        // Keep stdout clean for scripts
+        eprintln!("Using {}", resolved);
+    }
+    let record = vault.get_otp_record_mut(&resolved)?;
 
     // (2)
     let (token, time) = record.generate_token(counter)?;
+    let is_hotp = match record {
+        OtpRecord::Hotp { .. } => true,
+        OtpRecord::Totp { .. } => false,
+    };
+    if track_usage {
+        record.timestamps_mut().mark_used(Utc::now());
+    }
+
+    // (3)
+    if is_hotp {
+        let key = key.ok_or_else(|| {
+            format_err!("A GPG key is needed to save the counter of HOTP generators")
+        })?;
+        utils::write_vault(vault_path, &vault, &mut ctx, key)
+            .map_err(|e| format_err!("Failed to write {} ({})", vault_path.display(), e))?;
+    }
+
     println!("Your token is {}", token);
-    if let OtpRecord::Totp { .. } = record {
+    if !is_hotp {
         println!("This token is valid for the next {} seconds", time)
     }
 
+    // (4)
+    if track_usage && !is_hotp {
+        let written = match key {
+            Some(key) => {
+                utils::write_vault(vault_path, &vault, &mut ctx, key).map_err(|e| e.to_string())
+            }
+            None => Err("no GPG key was provided".to_string()),
+        };
+        if let Err(err) = written {
+            eprintln!(
+                "Warning: failed to record the use of {} ({})",
+                resolved, err
+            );
+        }
+    }
+
     Ok(())
 }
@@ -5,8 +5,8 @@
 // file, You can obtain one at http://mozilla.org/MPL/2.0/.
 
 use failure::Error;
-use gpgme::Context;
-use lib::types::Record;
+use lib::backend::Crypto;
+use lib::types::{add_tags, Record};
 use lib::utils;
 use std::path::PathBuf;
 
@@ -18,7 +18,7 @@ use std::path::PathBuf;
 pub fn add_record(
     vault_path: &PathBuf,
     key: &str,
-    mut ctx: Context,
+    mut ctx: Crypto,
     record: Record,
     record_id: String,
 ) -> Result<(), Error> {
@@ -44,11 +44,12 @@ pub fn add_record(
  *      c) Account username
  *      d) Account email
  *      e) Account password: mandatory
+ *      f) Tags, comma-separated
  *  2. Construct a `Record`
  *  3. Get a record ID from the user, bail if not provided
  *  4. `add_record`
  */
-pub fn add_record_interactive(vault_path: &PathBuf, key: &str, ctx: Context) -> Result<(), Error> {
+pub fn add_record_interactive(vault_path: &PathBuf, key: &str, ctx: Crypto) -> Result<(), Error> {
     tracepoint!();
     println!("We are going to add a password to the vault.");
     println!("Once a password has been added, it will be safely stored and you'll be able to access it at any time.");
@@ -104,13 +105,17 @@ pub fn add_record_interactive(vault_path: &PathBuf, key: &str, ctx: Context) ->
         "What's the password? "
     )?;
 
+    // (1.f)
+    let tags = question!(
+        |s: String| -> Result<Vec<String>, Error> {
+            Ok(s.split(',').map(|t| t.to_string()).collect())
+        },
+        "What tags should this password have, separated by commas? [None] "
+    )?;
+
     // (2)
-    let record = Record {
-        username,
-        home,
-        email,
-        password: password.to_owned(),
-    };
+    let mut record = Record::new(password.to_owned(), username, email, home);
+    add_tags(&mut record.tags, tags);
 
     // (3)
     let record_id_default = record_id(&record, &service.to_owned());
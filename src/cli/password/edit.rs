@@ -0,0 +1,44 @@
+// Copyright (C) 2018 Alberto Coscia
+//
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at http://mozilla.org/MPL/2.0/.
+
+use failure::Error;
+use lib::backend::Crypto;
+use lib::types::Record;
+use lib::utils;
+use std::path::PathBuf;
+
+/// Edits a password record in a vault
+/**
+ * Blueprint
+ *  1. `read_vault`, bail on error
+ *  2. `vault.edit_record`, bail on error
+ *  3. `write_vault`, bail on error
+ */
+pub fn edit_record<F>(
+    vault_path: &PathBuf,
+    key: &str,
+    mut ctx: Crypto,
+    record_id: &str,
+    edit: F,
+) -> Result<(), Error>
+where
+    F: FnOnce(&mut Record),
+{
+    tracepoint!();
+
+    // (1)
+    // TODO These unwraps are due to the fact that the errors cannot be made
+    // into failure::Error's. Find a workaround
+    let mut vault = utils::read_vault(vault_path, &mut ctx).unwrap();
+
+    // (2)
+    vault.edit_record(record_id, edit)?;
+
+    // (3)
+    utils::write_vault(vault_path, &vault, &mut ctx, key).unwrap();
+
+    Ok(())
+}
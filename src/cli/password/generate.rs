@@ -8,10 +8,25 @@ use failure::Error;
 use rand::distributions::Alphanumeric;
 use rand::{thread_rng, Rng};
 
-/// Generates a random password
-pub fn generate_password(chars: usize) -> Result<(), Error> {
+/// The punctuation added to generated passwords with symbols
+const SYMBOLS: &[u8] = b"!#$%&()*+,-./:;<=>?@[]^_{|}~";
+
+/// Generates a random password, with punctuation if `symbols` is set
+pub fn generate_password(chars: usize, symbols: bool) -> Result<(), Error> {
     let mut random = thread_rng();
-    let pw: String = random.sample_iter(&Alphanumeric).take(chars).collect();
+    let pw: String = if symbols {
+        let charset: Vec<char> = (b'0'..=b'9')
+            .chain(b'A'..=b'Z')
+            .chain(b'a'..=b'z')
+            .chain(SYMBOLS.iter().cloned())
+            .map(char::from)
+            .collect();
+        (0..chars)
+            .map(|_| charset[random.gen_range(0, charset.len())])
+            .collect()
+    } else {
+        random.sample_iter(&Alphanumeric).take(chars).collect()
+    };
     println!("{}", pw);
 
     Ok(())
@@ -4,26 +4,58 @@
 // License, v. 2.0. If a copy of the MPL was not distributed with this
 // file, You can obtain one at http://mozilla.org/MPL/2.0/.
 
+use chrono::Utc;
 use failure::Error;
-use gpgme::Context;
+use lib::backend::Crypto;
+use lib::search::{resolve, Kind};
 use lib::utils;
 use std::path::PathBuf;
 
-/// Returns a password from a record
+/// Returns a password, or one of its custom fields, from a record
 /**
  * Blueprint
- *  1. `read_vault`, `vault.get_record`, bail on error
- *  2. Return the `password` field
+ *  1. `read_vault`, `resolve` the record ID, `vault.get_record`, bail on
+ *     error
+ *  2. Return the `password` field, or the custom `field` if provided, bail if
+ *     there is no such field
+ *  3. If a `usage_key` is provided, record the use and `write_vault`, only
+ *     warn on error as the password was already returned
  */
-pub fn get_password(vault_path: &PathBuf, mut ctx: Context, record_id: &str) -> Result<(), Error> {
+pub fn get_password(
+    vault_path: &PathBuf,
+    mut ctx: Crypto,
+    record_id: &str,
+    field: Option<&str>,
+    usage_key: Option<&str>,
+) -> Result<(), Error> {
     tracepoint!();
 
     // (1)
-    let vault = utils::read_vault(&vault_path, &mut ctx).unwrap();
-    let record = vault.get_record(&record_id)?;
+    let mut vault = utils::read_vault(vault_path, &mut ctx).unwrap();
+    let resolved = resolve(&vault, record_id, Kind::Password)?.to_string();
+    if resolved != record_id {
+        // Keep stdout clean for scripts
+        eprintln!("Using {}", resolved);
+    }
+    let record = vault.get_record(&resolved)?;
 
     // (2)
-    println!("{}", record.password);
+    match field {
+        Some(field) => println!("{}", record.get_field(field)?),
+        None => println!("{}", record.password),
+    }
+
+    // (3)
+    if let Some(key) = usage_key {
+        let record = vault.get_record_mut(&resolved)?;
+        record.timestamps.mark_used(Utc::now());
+        if let Err(err) = utils::write_vault(vault_path, &vault, &mut ctx, key) {
+            eprintln!(
+                "Warning: failed to record the use of {} ({})",
+                resolved, err
+            );
+        }
+    }
 
     Ok(())
 }
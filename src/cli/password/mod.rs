@@ -5,12 +5,14 @@
 // file, You can obtain one at http://mozilla.org/MPL/2.0/.
 
 mod add;
+mod edit;
 mod generate;
 mod get;
 mod remove;
 
 pub use self::add::add_record;
 pub use self::add::add_record_interactive;
+pub use self::edit::edit_record;
 pub use self::generate::generate_password;
 pub use self::get::get_password;
 pub use self::remove::remove_record;
@@ -5,7 +5,7 @@
 // file, You can obtain one at http://mozilla.org/MPL/2.0/.
 
 use failure::Error;
-use gpgme::Context;
+use lib::backend::Crypto;
 use lib::utils;
 use std::path::PathBuf;
 
@@ -17,7 +17,7 @@ use std::path::PathBuf;
 pub fn remove_record(
     vault_path: &PathBuf,
     key: &str,
-    mut ctx: Context,
+    mut ctx: Crypto,
     record_id: String,
 ) -> Result<(), Error> {
     tracepoint!();
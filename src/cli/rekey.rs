@@ -0,0 +1,92 @@
+// Copyright (C) 2018 Alberto Coscia
+//
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at http://mozilla.org/MPL/2.0/.
+
+use failure::Error;
+use lib::backend::{BackendKind, Crypto};
+use std::fs::{metadata, read, remove_file, rename, File};
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+
+/// Re-encrypts a vault to a new set of keys, using the backend that encrypted
+/// it
+/**
+ * Blueprint
+ *  1. Decrypt the vault with any available key, bail on error
+ *  2. Encrypt the vault to the new keys, which don't need to be pinned as
+ *     they are given explicitly, bail on error. age vaults decrypted with a
+ *     passphrase are encrypted to recipients instead
+ *  3. Report the keys, warning about GPG keys that are not pinned
+ *  4. Decrypt the result and check that it matches the vault, bail otherwise
+ *  5. Write the result next to the vault, with the same permissions, and
+ *     replace the vault with it. Remove the temporary file and bail on error
+ */
+pub fn rekey_vault(vault_path: &PathBuf, mut ctx: Crypto, to: &[String]) -> Result<(), Error> {
+    tracepoint!();
+    let key = to.join(",");
+
+    // (1)
+    let input = read(vault_path)?;
+    let kind = BackendKind::detect(&input);
+    let plaintext = ctx
+        .backend(kind)
+        .and_then(|backend| backend.decrypt(&input))
+        .map_err(|e| format_err!("Failed to decrypt the vault ({})", e))?;
+
+    // (2)
+    ctx.age().forget_passphrase();
+    ctx.allow_unpinned_keys();
+    // TODO Can we handle these failures more nicely?
+    let backend = ctx.backend(kind).unwrap();
+    let encrypted = backend
+        .encrypt(&plaintext, &key)
+        .map_err(|e| format_err!("Failed to encrypt the vault ({})", e))?;
+    let output = encrypted.ciphertext;
+
+    // (3)
+    for key in &encrypted.keys {
+        println!("Encrypting to {}", key);
+    }
+    for warning in &encrypted.warnings {
+        eprintln!("Warning: {}", warning);
+    }
+
+    // (4)
+    let decrypted = backend.decrypt(&output).map_err(|e| {
+        format_err!(
+            "The vault was left untouched, as it could not be decrypted once re-encrypted ({})",
+            e
+        )
+    })?;
+    ensure!(
+        decrypted == plaintext,
+        "The vault was left untouched, as it changed once re-encrypted"
+    );
+
+    // (5)
+    let temp_path = vault_path.with_extension("rekey");
+    if let Err(err) = replace_file(vault_path, &temp_path, &output) {
+        let _ = remove_file(&temp_path);
+        bail!(
+            "The vault was left untouched, as it could not be replaced ({})",
+            err
+        );
+    }
+    println!("Vault re-encrypted to {} key(s)", encrypted.keys.len());
+
+    Ok(())
+}
+
+/// Writes `contents` to `temp_path` with the permissions of `path`, then
+/// moves it over `path`
+fn replace_file(path: &Path, temp_path: &Path, contents: &[u8]) -> io::Result<()> {
+    let permissions = metadata(path)?.permissions();
+    let mut file = File::create(temp_path)?;
+    // Set before writing, so that the vault is never readable by anyone else
+    file.set_permissions(permissions)?;
+    file.write_all(contents)?;
+    file.sync_all()?;
+    rename(temp_path, path)
+}
@@ -0,0 +1,36 @@
+// Copyright (C) 2018 Alberto Coscia
+//
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at http://mozilla.org/MPL/2.0/.
+
+use failure::Error;
+use lib::backend::Crypto;
+use lib::utils;
+use std::path::PathBuf;
+
+/// Removes the passwords and OTP generators with a name or, if `recursive`,
+/// a whole folder
+/**
+ * Blueprint
+ *  1. `read_vault`, `vault.remove_records`, `write_vault`, bail on error
+ */
+pub fn remove_records(
+    vault_path: &PathBuf,
+    key: &str,
+    mut ctx: Crypto,
+    record_id: &str,
+    recursive: bool,
+) -> Result<(), Error> {
+    tracepoint!();
+
+    // (1)
+    // TODO These unwraps are due to the fact that the errors cannot be made
+    // into failure::Error's. Find a workaround
+    let mut vault = utils::read_vault(vault_path, &mut ctx).unwrap();
+    let removed = vault.remove_records(record_id, recursive)?;
+    utils::write_vault(vault_path, &vault, &mut ctx, key).unwrap();
+    println!("{} records removed", removed);
+
+    Ok(())
+}
@@ -0,0 +1,35 @@
+// Copyright (C) 2018 Alberto Coscia
+//
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at http://mozilla.org/MPL/2.0/.
+
+use failure::Error;
+use lib::backend::Crypto;
+use lib::utils;
+use std::path::PathBuf;
+
+/// Moves a record, or a folder and its contents, to a new name
+/**
+ * Blueprint
+ *  1. `read_vault`, `vault.move_records`, `write_vault`, bail on error
+ */
+pub fn move_records(
+    vault_path: &PathBuf,
+    key: &str,
+    mut ctx: Crypto,
+    from: &str,
+    to: &str,
+) -> Result<(), Error> {
+    tracepoint!();
+
+    // (1)
+    // TODO These unwraps are due to the fact that the errors cannot be made
+    // into failure::Error's. Find a workaround
+    let mut vault = utils::read_vault(vault_path, &mut ctx).unwrap();
+    let moved = vault.move_records(from, to)?;
+    utils::write_vault(vault_path, &vault, &mut ctx, key).unwrap();
+    println!("{} records moved", moved);
+
+    Ok(())
+}
@@ -4,10 +4,13 @@
 // License, v. 2.0. If a copy of the MPL was not distributed with this
 // file, You can obtain one at http://mozilla.org/MPL/2.0/.
 
+use age::secrecy::SecretString;
 use failure::Error;
+use lib::backend::{BackendKind, Crypto};
+use lib::error::BackendError;
 use lib::types::Vault;
 use lib::utils;
-use std::collections::HashMap;
+use rpassword;
 use std::path::PathBuf;
 
 /// Creates an empty vault file
@@ -15,14 +18,19 @@ use std::path::PathBuf;
  * Blueprint
  *  1. Check that we're not writing on an existing file, bail if true and not
  *     --force'ing. Check that existing file is not a directory, bail if true.
- *  2. Construct an empty `Vault`
- *  3. `write_vault`, bail on error
+ *  2. If a passphrase is used, ask for it twice and bail if they don't match
+ *  3. Construct an empty `Vault`
+ *  4. `write_vault_with` the chosen backend, bail on error
  */
-pub fn touch_vault(vault_path: &PathBuf, key: &str, force: bool) -> Result<(), Error> {
+pub fn touch_vault(
+    vault_path: &PathBuf,
+    key: &str,
+    mut ctx: Crypto,
+    backend: BackendKind,
+    passphrase: bool,
+    force: bool,
+) -> Result<(), Error> {
     tracepoint!();
-    // Acquire a GPGME context
-    // TODO Can we handle this failure more nicely?
-    let mut ctx = utils::create_context().unwrap();
 
     // (1)
     // Check if file exists
@@ -38,14 +46,23 @@ pub fn touch_vault(vault_path: &PathBuf, key: &str, force: bool) -> Result<(), E
     }
 
     // (2)
-    let vault = Vault {
-        passwords: HashMap::new(),
-        otps: HashMap::new(),
-    };
+    if passphrase {
+        let first = rpassword::prompt_password("Vault passphrase: ")?;
+        ensure!(!first.is_empty(), "Please provide a non-empty passphrase");
+        let second = rpassword::prompt_password("Repeat the passphrase: ")?;
+        if first != second {
+            return Err(BackendError::PassphraseMismatch.into());
+        }
+        ctx.age().set_passphrase(SecretString::from(first));
+    }
 
     // (3)
+    let vault = Vault::default();
+
+    // (4)
     // TODO Can we handle this failure more nicely?
-    utils::write_vault(&vault_path, &vault, &mut ctx, &key).unwrap();
+    let backend = ctx.backend(backend).unwrap();
+    utils::write_vault_with(vault_path, &vault, backend, key).unwrap();
 
     Ok(())
 }
@@ -0,0 +1,36 @@
+// Copyright (C) 2018 Alberto Coscia
+//
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at http://mozilla.org/MPL/2.0/.
+
+use failure::Error;
+use lib::config::Config;
+
+/// Lists the vault profiles of the configuration file, marking the selected
+/// one
+pub fn list_vaults(config: &Config, selected: Option<&str>) -> Result<(), Error> {
+    tracepoint!();
+    if config.profiles.is_empty() {
+        println!("No vault profiles, add one with `sigil config set profiles.<name>.vault <path>`");
+        return Ok(());
+    }
+
+    for (name, profile) in &config.profiles {
+        let marker = if Some(&name[..]) == selected {
+            "*"
+        } else {
+            " "
+        };
+        let vault = match profile.vault {
+            Some(ref vault) => vault.display().to_string(),
+            None => "no vault".to_string(),
+        };
+        match profile.key {
+            Some(ref key) => println!("{} {}: {} ({})", marker, name, vault, key),
+            None => println!("{} {}: {}", marker, name, vault),
+        }
+    }
+
+    Ok(())
+}
@@ -0,0 +1,185 @@
+// Copyright (C) 2018 Alberto Coscia
+//
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at http://mozilla.org/MPL/2.0/.
+
+use lib::audit::passwords::group_by_password;
+use lib::types::Vault;
+use ring::digest::{digest, SHA1};
+use std::cmp::{Ordering, Reverse};
+use std::io::{self, BufRead, BufReader, Read, Seek, SeekFrom};
+
+/// A password found in a breach
+#[derive(Debug, PartialEq)]
+pub struct Breached<'a> {
+    pub id: &'a str,
+    /// How many times the password was seen in breaches
+    pub count: u64,
+}
+
+/// Returns the uppercase hexadecimal SHA-1 hash of a password, as found in
+/// the Pwned Passwords files of Have I Been Pwned
+pub fn sha1_hex(password: &str) -> String {
+    digest(&SHA1, password.as_bytes())
+        .as_ref()
+        .iter()
+        .map(|b| format!("{:02X}", b))
+        .collect()
+}
+
+/// Looks up a hash in a Pwned Passwords file sorted by hash, returning how
+/// many times it was seen
+/**
+ * Blueprint
+ *  Binary search the lines starting between `lo` and `hi`, without reading the
+ *  whole file
+ *  1. Find the first line starting at or after the middle of the range
+ *  2. If there is none before `hi`, search the first half
+ *  3. Otherwise compare its hash, ignoring case, and search either the lines
+ *     after it or the first half
+ */
+pub fn find_hash<R: Read + Seek>(db: &mut BufReader<R>, hash: &str) -> io::Result<Option<u64>> {
+    let mut lo = 0;
+    let mut hi = db.seek(SeekFrom::End(0))?;
+    let mut line = Vec::new();
+    while lo < hi {
+        // (1)
+        let mid = lo + (hi - lo) / 2;
+        let mut start = mid;
+        if mid > 0 {
+            db.seek(SeekFrom::Start(mid - 1))?;
+            line.clear();
+            start = mid - 1 + db.read_until(b'\n', &mut line)? as u64;
+        } else {
+            db.seek(SeekFrom::Start(0))?;
+        }
+
+        // (2)
+        if start >= hi {
+            hi = mid;
+            continue;
+        }
+
+        // (3)
+        line.clear();
+        let len = db.read_until(b'\n', &mut line)? as u64;
+        let line = String::from_utf8_lossy(&line);
+        let mut parts = line.trim_end().splitn(2, ':');
+        let found = parts.next().unwrap_or("");
+        match found.to_uppercase()[..].cmp(hash) {
+            Ordering::Less => lo = start + len,
+            Ordering::Greater => hi = mid,
+            Ordering::Equal => {
+                let count = parts.next().unwrap_or("1").trim().parse().map_err(|_| {
+                    io::Error::new(
+                        io::ErrorKind::InvalidData,
+                        format!("Invalid line: {}", line),
+                    )
+                })?;
+                return Ok(Some(count));
+            }
+        }
+    }
+    Ok(None)
+}
+
+/// Lists the passwords found in a Pwned Passwords file, most seen first
+/**
+ * Blueprint
+ *  1. Group record IDs by password, so that every password is looked up once
+ *  2. Look up the SHA-1 hash of every password
+ *  3. Sort by how many times passwords were seen, then by ID
+ */
+pub fn breached_passwords<R: Read + Seek>(vault: &Vault, db: R) -> io::Result<Vec<Breached<'_>>> {
+    tracepoint!();
+    let mut db = BufReader::new(db);
+    // (1)
+    let mut breached = Vec::new();
+    for (password, ids) in group_by_password(vault) {
+        // (2)
+        if let Some(count) = find_hash(&mut db, &sha1_hex(password))? {
+            breached.extend(ids.into_iter().map(|id| Breached { id, count }));
+        }
+    }
+
+    // (3)
+    breached.sort_by_key(|b| (Reverse(b.count), b.id));
+    Ok(breached)
+}
+
+#[cfg(test)]
+mod tests {
+    use lib::audit::breached::{breached_passwords, find_hash, sha1_hex, Breached};
+    use lib::types::Vault;
+    use std::io::{BufReader, Cursor};
+
+    fn db(passwords: &[(&str, u64)]) -> Vec<u8> {
+        let mut lines: Vec<String> = passwords
+            .iter()
+            .map(|(password, count)| format!("{}:{}\r\n", sha1_hex(password), count))
+            .collect();
+        lines.sort();
+        lines.concat().into_bytes()
+    }
+
+    #[test]
+    fn hashes() {
+        assert_eq!(
+            sha1_hex("password"),
+            "5BAA61E4C9B93F3F0682250B6CF8331B7EE68FD8"
+        );
+    }
+
+    #[test]
+    fn lookup() {
+        let passwords: Vec<String> = (0..200).map(|i| format!("password{}", i)).collect();
+        let counts: Vec<(&str, u64)> = passwords
+            .iter()
+            .enumerate()
+            .map(|(i, p)| (&p[..], i as u64 + 1))
+            .collect();
+        let mut db = BufReader::new(Cursor::new(db(&counts)));
+        for (password, count) in &counts {
+            assert_eq!(
+                find_hash(&mut db, &sha1_hex(password)).unwrap(),
+                Some(*count)
+            );
+        }
+        assert_eq!(find_hash(&mut db, &sha1_hex("password")).unwrap(), None);
+        assert_eq!(find_hash(&mut db, &"0".repeat(40)).unwrap(), None);
+        assert_eq!(find_hash(&mut db, &"F".repeat(40)).unwrap(), None);
+
+        let mut empty = BufReader::new(Cursor::new(Vec::new()));
+        assert_eq!(find_hash(&mut empty, &sha1_hex("password")).unwrap(), None);
+    }
+
+    #[test]
+    fn breached() {
+        let vault = Vault::with_passwords(&[
+            ("bank", "wV8!pZ2#mQ5&tR"),
+            ("forum", "dragon"),
+            ("mail", "letmein"),
+            ("shop", "letmein"),
+        ]);
+
+        let db = Cursor::new(db(&[("123456", 100), ("dragon", 5), ("letmein", 10)]));
+        assert_eq!(
+            breached_passwords(&vault, db).unwrap(),
+            vec![
+                Breached {
+                    id: "mail",
+                    count: 10,
+                },
+                Breached {
+                    id: "shop",
+                    count: 10,
+                },
+                Breached {
+                    id: "forum",
+                    count: 5,
+                },
+            ]
+        );
+    }
+}
@@ -0,0 +1,163 @@
+// Copyright (C) 2018 Alberto Coscia
+//
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at http://mozilla.org/MPL/2.0/.
+
+use lib::audit::strength::{Estimator, Strength};
+use lib::types::Vault;
+use std::collections::{BTreeMap, BTreeSet};
+use std::io::{self, BufRead};
+
+/// A password scoring below the minimum strength
+#[derive(Debug, PartialEq)]
+pub struct Weak<'a> {
+    pub id: &'a str,
+    pub strength: Strength,
+}
+
+/// Groups the IDs of the passwords shared by several records
+/**
+ * Blueprint
+ *  1. Group record IDs by password, skipping empty ones
+ *  2. Keep the groups of more than one record, sorted by their first ID
+ */
+pub fn reused_passwords(vault: &Vault) -> Vec<Vec<&str>> {
+    tracepoint!();
+    // (1)
+    let groups = group_by_password(vault);
+
+    // (2)
+    let mut reused: Vec<Vec<&str>> = groups
+        .values()
+        .filter(|ids| ids.len() > 1)
+        .cloned()
+        .collect();
+    reused.sort();
+    reused
+}
+
+/// Lists the passwords scoring below `min_score`, weakest first
+pub fn weak_passwords<'a>(vault: &'a Vault, estimator: &Estimator, min_score: u8) -> Vec<Weak<'a>> {
+    tracepoint!();
+    let mut weak: Vec<Weak> = vault
+        .passwords
+        .iter()
+        .map(|(id, record)| Weak {
+            id,
+            strength: estimator.estimate(&record.password),
+        })
+        .filter(|weak| weak.strength.score < min_score)
+        .collect();
+    weak.sort_by(|a, b| {
+        a.strength
+            .guesses_log10
+            .partial_cmp(&b.strength.guesses_log10)
+            .unwrap()
+            .then(a.id.cmp(b.id))
+    });
+    weak
+}
+
+/// Lists the passwords found among the most common ones, ignoring their case
+pub fn common_passwords<'a>(vault: &'a Vault, estimator: &Estimator) -> BTreeSet<&'a str> {
+    tracepoint!();
+    vault
+        .passwords
+        .iter()
+        .filter(|(_, record)| !record.password.is_empty() && estimator.is_common(&record.password))
+        .map(|(id, _)| &id[..])
+        .collect()
+}
+
+/// Lists the passwords found in a word list, with one password per line
+/**
+ * Blueprint
+ *  1. Group record IDs by password, so that the word list is only read once
+ *  2. Stream the word list, collecting the records using every listed
+ *     password
+ */
+pub fn listed_passwords<R: BufRead>(vault: &Vault, wordlist: R) -> io::Result<BTreeSet<&str>> {
+    tracepoint!();
+    // (1)
+    let mut groups = group_by_password(vault);
+
+    // (2)
+    let mut listed = BTreeSet::new();
+    for line in wordlist.split(b'\n') {
+        let line = line?;
+        let line = String::from_utf8_lossy(&line);
+        if let Some(ids) = groups.remove(line.trim_end_matches('\r')) {
+            listed.extend(ids);
+        }
+    }
+    Ok(listed)
+}
+
+/// Groups record IDs by password, skipping empty passwords
+pub fn group_by_password(vault: &Vault) -> BTreeMap<&str, Vec<&str>> {
+    let mut groups: BTreeMap<&str, Vec<&str>> = BTreeMap::new();
+    for (id, record) in &vault.passwords {
+        if !record.password.is_empty() {
+            groups.entry(&record.password).or_default().push(id);
+        }
+    }
+    groups
+}
+
+#[cfg(test)]
+mod tests {
+    use lib::audit::passwords::{
+        common_passwords, listed_passwords, reused_passwords, weak_passwords,
+    };
+    use lib::audit::strength::Estimator;
+    use lib::types::Vault;
+
+    fn vault() -> Vault {
+        Vault::with_passwords(&[
+            ("bank", "wV8!pZ2#mQ5&tR"),
+            ("forum", "Dragon"),
+            ("mail", "k3#Rq9Lw"),
+            ("shop", "wV8!pZ2#mQ5&tR"),
+            ("work", "wV8!pZ2#mQ5&tR"),
+            ("legacy", ""),
+        ])
+    }
+
+    #[test]
+    fn reused() {
+        let vault = vault();
+        assert_eq!(reused_passwords(&vault), vec![vec!["bank", "shop", "work"]]);
+    }
+
+    #[test]
+    fn weak() {
+        let vault = vault();
+        let estimator = Estimator::new();
+        let weak: Vec<&str> = weak_passwords(&vault, &estimator, 3)
+            .iter()
+            .map(|w| w.id)
+            .collect();
+        assert_eq!(weak, vec!["legacy", "forum"]);
+        let weak: Vec<&str> = weak_passwords(&vault, &estimator, 4)
+            .iter()
+            .map(|w| w.id)
+            .collect();
+        assert_eq!(weak, vec!["legacy", "forum", "mail"]);
+    }
+
+    #[test]
+    fn common() {
+        let vault = vault();
+        let estimator = Estimator::new();
+        let common: Vec<&str> = common_passwords(&vault, &estimator).into_iter().collect();
+        assert_eq!(common, vec!["forum"]);
+
+        let wordlist = &b"123456\r\nk3#Rq9Lw\r\nwV8!pZ2#mQ5&tR\r\n"[..];
+        let listed: Vec<&str> = listed_passwords(&vault, wordlist)
+            .unwrap()
+            .into_iter()
+            .collect();
+        assert_eq!(listed, vec!["bank", "mail", "shop", "work"]);
+    }
+}
@@ -0,0 +1,172 @@
+// Copyright (C) 2018 Alberto Coscia
+//
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at http://mozilla.org/MPL/2.0/.
+
+use chrono::{DateTime, Duration, Utc};
+use lib::types::{Record, Vault};
+use std::cmp::Reverse;
+
+/// A password older than its maximum age
+#[derive(Debug, PartialEq)]
+pub struct Stale<'a> {
+    pub id: &'a str,
+    /// The maximum age of the password, in days
+    pub max_age: u32,
+    /// How long ago the password should have been changed, or `None` if it
+    /// has no timestamps
+    pub overdue: Option<Duration>,
+}
+
+/// Returns the maximum age, in days, of a password
+/**
+ * Blueprint
+ *  1. Use the maximum age of the record, if any
+ *  2. Otherwise, use the strictest policy among those of its tags, ignoring
+ *     their case
+ *  3. Otherwise, use `default`
+ */
+pub fn max_age(vault: &Vault, record: &Record, default: Option<u32>) -> Option<u32> {
+    // (1)
+    record
+        .max_age
+        // (2)
+        .or_else(|| {
+            vault
+                .max_age
+                .iter()
+                .filter(|(tag, _)| record.tags.iter().any(|t| t.eq_ignore_ascii_case(tag)))
+                .map(|(_, days)| *days)
+                .min()
+        })
+        // (3)
+        .or(default)
+}
+
+/// Lists the passwords older than their maximum age, most overdue first
+/**
+ * Blueprint
+ *  1. Skip passwords without a maximum age
+ *  2. Measure the age of a password since it was last changed or, failing
+ *     that, since its record was created. Passwords without timestamps are
+ *     always stale
+ *  3. Sort by how long ago passwords should have been changed, those without
+ *     timestamps first, then by ID
+ */
+pub fn stale_records<'a>(
+    vault: &'a Vault,
+    default: Option<u32>,
+    now: DateTime<Utc>,
+) -> Vec<Stale<'a>> {
+    tracepoint!();
+    let mut stale: Vec<Stale> = vault
+        .passwords
+        .iter()
+        .filter_map(|(id, record)| {
+            // (1)
+            let max_age = max_age(vault, record, default)?;
+
+            // (2)
+            let changed = record.password_changed.or(record.timestamps.created);
+            let overdue = changed.map(|c| now - (c + Duration::days(i64::from(max_age))));
+            match overdue {
+                Some(overdue) if overdue <= Duration::zero() => None,
+                overdue => Some(Stale {
+                    id,
+                    max_age,
+                    overdue,
+                }),
+            }
+        })
+        .collect();
+
+    // (3)
+    let key = |s: &Stale<'a>| (s.overdue.is_some(), Reverse(s.overdue), s.id);
+    stale.sort_by(|a, b| key(a).cmp(&key(b)));
+    stale
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::{DateTime, Duration, Utc};
+    use lib::audit::stale::{max_age, stale_records, Stale};
+    use lib::types::{Record, Vault};
+
+    fn time(s: &str) -> DateTime<Utc> {
+        s.parse().unwrap()
+    }
+
+    fn record(changed: Option<&str>, tags: &[&str], max_age: Option<u32>) -> Record {
+        let mut record = Record::new("hunter2".to_string(), None, None, None);
+        record.password_changed = changed.map(time);
+        record.tags = tags.iter().map(|t| t.to_string()).collect();
+        record.max_age = max_age;
+        record
+    }
+
+    #[test]
+    fn policies() {
+        let mut vault = Vault::default();
+        vault.max_age.insert("prod".to_string(), 30);
+        vault.max_age.insert("work".to_string(), 90);
+
+        assert_eq!(
+            max_age(&vault, &record(None, &[], Some(7)), Some(365)),
+            Some(7)
+        );
+        assert_eq!(
+            max_age(&vault, &record(None, &["Work", "PROD"], None), None),
+            Some(30)
+        );
+        assert_eq!(
+            max_age(&vault, &record(None, &["home"], None), Some(365)),
+            Some(365)
+        );
+        assert_eq!(max_age(&vault, &record(None, &["home"], None), None), None);
+    }
+
+    #[test]
+    fn stale() {
+        let mut vault = Vault::default();
+        vault.max_age.insert("prod".to_string(), 30);
+        for (id, record) in [
+            (
+                "fresh",
+                record(Some("2018-07-20T00:00:00Z"), &["prod"], None),
+            ),
+            (
+                "late",
+                record(Some("2018-06-01T00:00:00Z"), &["prod"], None),
+            ),
+            ("later", record(Some("2018-01-01T00:00:00Z"), &[], Some(30))),
+            ("unknown", record(None, &["prod"], None)),
+            ("untracked", record(Some("2000-01-01T00:00:00Z"), &[], None)),
+        ] {
+            vault.passwords.insert(id.to_string(), record);
+        }
+
+        let now = time("2018-07-22T00:00:00Z");
+        assert_eq!(
+            stale_records(&vault, None, now),
+            vec![
+                Stale {
+                    id: "unknown",
+                    max_age: 30,
+                    overdue: None,
+                },
+                Stale {
+                    id: "later",
+                    max_age: 30,
+                    overdue: Some(Duration::days(172)),
+                },
+                Stale {
+                    id: "late",
+                    max_age: 30,
+                    overdue: Some(Duration::days(21)),
+                },
+            ]
+        );
+        assert_eq!(stale_records(&vault, Some(365), now).len(), 4);
+    }
+}
@@ -0,0 +1,414 @@
+// Copyright (C) 2018 Alberto Coscia
+//
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at http://mozilla.org/MPL/2.0/.
+
+use chrono::{Datelike, Utc};
+use std::collections::HashMap;
+use std::fmt;
+
+/// The most common passwords, most common first
+pub const COMMON_PASSWORDS: &str = include_str!("common_passwords.txt");
+
+/// Guesses per character not covered by any pattern, as in zxcvbn
+const BRUTEFORCE_CARDINALITY: f64 = 10.0;
+/// Years closer than this to the current one are just as guessable
+const MIN_YEAR_SPACE: i32 = 20;
+/// Keyboard patterns shorter than this are not detected
+const MIN_KEYBOARD_LENGTH: usize = 4;
+
+const KEYBOARD_ROWS: [&str; 5] = [
+    "`1234567890-=",
+    "~!@#$%^&*()_+",
+    "qwertyuiop[]\\",
+    "asdfghjkl;'",
+    "zxcvbnm,./",
+];
+
+const LEET: [(char, char); 10] = [
+    ('4', 'a'),
+    ('@', 'a'),
+    ('3', 'e'),
+    ('1', 'i'),
+    ('!', 'i'),
+    ('0', 'o'),
+    ('$', 's'),
+    ('5', 's'),
+    ('7', 't'),
+    ('+', 't'),
+];
+
+/// A guessable pattern found in a password
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Pattern {
+    Common,
+    Sequence,
+    Repeat,
+    Keyboard,
+    Year,
+}
+
+impl fmt::Display for Pattern {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let name = match self {
+            Pattern::Common => "common password",
+            Pattern::Sequence => "sequence",
+            Pattern::Repeat => "repetition",
+            Pattern::Keyboard => "keyboard pattern",
+            Pattern::Year => "year",
+        };
+        write!(f, "{}", name)
+    }
+}
+
+/// How hard a password is to guess
+#[derive(Debug, PartialEq)]
+pub struct Strength {
+    /// The base 10 logarithm of the guesses needed to find the password
+    pub guesses_log10: f64,
+    /// From 0, too guessable, to 4, very unguessable, as in zxcvbn
+    pub score: u8,
+    /// The pattern covering most of the password, if any
+    pub pattern: Option<Pattern>,
+}
+
+impl Strength {
+    pub fn describe(&self) -> &'static str {
+        match self.score {
+            0 => "very weak",
+            1 => "weak",
+            2 => "fair",
+            3 => "good",
+            _ => "strong",
+        }
+    }
+}
+
+/// A pattern spanning the characters from `start` to `end`, excluded
+struct Match {
+    start: usize,
+    end: usize,
+    guesses_log10: f64,
+    pattern: Pattern,
+}
+
+/// Estimates the strength of passwords following the approach of zxcvbn:
+/// passwords are split into the guessable patterns that minimize the guesses
+/// needed to find them, the remaining characters are bruteforced
+pub struct Estimator {
+    /// The rank of each common password
+    dictionary: HashMap<&'static str, usize>,
+    year: i32,
+}
+
+impl Estimator {
+    pub fn new() -> Self {
+        let dictionary = COMMON_PASSWORDS
+            .lines()
+            .enumerate()
+            .map(|(rank, word)| (word, rank + 1))
+            .collect();
+        Estimator {
+            dictionary,
+            year: Utc::now().year(),
+        }
+    }
+
+    /// Returns whether a password is one of the most common ones
+    pub fn is_common(&self, password: &str) -> bool {
+        self.dictionary.contains_key(&password.to_lowercase()[..])
+    }
+
+    /// Estimates the strength of a password
+    /**
+     * Blueprint
+     *  1. Find the guessable patterns in the password
+     *  2. For every prefix, find the cheapest way to guess it, either by
+     *     bruteforcing its last character or by guessing a pattern ending
+     *     there
+     *  3. Report the pattern covering most of the password
+     *  4. Score the password using the thresholds of zxcvbn
+     */
+    pub fn estimate(&self, password: &str) -> Strength {
+        let chars: Vec<char> = password.chars().collect();
+        let (guesses_log10, pattern) = self.estimate_chars(&chars);
+
+        // (4)
+        let score = match guesses_log10 {
+            g if g < 3.0 => 0,
+            g if g < 6.0 => 1,
+            g if g < 8.0 => 2,
+            g if g < 10.0 => 3,
+            _ => 4,
+        };
+        Strength {
+            guesses_log10,
+            score,
+            pattern,
+        }
+    }
+
+    fn estimate_chars(&self, chars: &[char]) -> (f64, Option<Pattern>) {
+        // (1)
+        let mut matches = self.common_matches(chars);
+        matches.extend(sequence_matches(chars));
+        matches.extend(self.repeat_matches(chars));
+        matches.extend(keyboard_matches(chars));
+        matches.extend(self.year_matches(chars));
+
+        // (2)
+        // The guesses needed for each prefix, and the match it ends with
+        let mut best: Vec<(f64, Option<usize>)> = vec![(0.0, None); chars.len() + 1];
+        for end in 1..=chars.len() {
+            best[end] = (best[end - 1].0 + BRUTEFORCE_CARDINALITY.log10(), None);
+            for (i, m) in matches.iter().enumerate().filter(|(_, m)| m.end == end) {
+                let guesses_log10 = best[m.start].0 + m.guesses_log10;
+                if guesses_log10 < best[end].0 {
+                    best[end] = (guesses_log10, Some(i));
+                }
+            }
+        }
+
+        // (3)
+        let mut pattern = None;
+        let mut longest = 0;
+        let mut end = chars.len();
+        while end > 0 {
+            match best[end].1 {
+                Some(i) => {
+                    let m = &matches[i];
+                    if m.end - m.start > longest {
+                        longest = m.end - m.start;
+                        pattern = Some(m.pattern);
+                    }
+                    end = m.start;
+                }
+                None => end -= 1,
+            }
+        }
+
+        (best[chars.len()].0, pattern)
+    }
+
+    /// Finds common passwords, ignoring their case and l33t substitutions
+    fn common_matches(&self, chars: &[char]) -> Vec<Match> {
+        let mut matches = Vec::new();
+        for start in 0..chars.len() {
+            for end in start + 3..=chars.len() {
+                let word = &chars[start..end];
+                let lowercase: String = word.iter().flat_map(|c| c.to_lowercase()).collect();
+                let unleet: String = lowercase
+                    .chars()
+                    .map(|c| LEET.iter().find(|(l, _)| *l == c).map_or(c, |(_, u)| *u))
+                    .collect();
+                let guesses = match (
+                    self.dictionary.get(&lowercase[..]),
+                    self.dictionary.get(&unleet[..]),
+                ) {
+                    (Some(rank), _) => *rank as f64,
+                    (None, Some(rank)) => *rank as f64 * 2.0,
+                    (None, None) => continue,
+                };
+                matches.push(Match {
+                    start,
+                    end,
+                    guesses_log10: guesses.log10() + uppercase_variations(word).log10(),
+                    pattern: Pattern::Common,
+                });
+            }
+        }
+        matches
+    }
+
+    /// Finds characters or blocks of characters repeated at least twice
+    fn repeat_matches(&self, chars: &[char]) -> Vec<Match> {
+        let mut matches = Vec::new();
+        for start in 0..chars.len() {
+            for len in 1..=(chars.len() - start) / 2 {
+                let block = &chars[start..start + len];
+                let count = chars[start..]
+                    .chunks(len)
+                    .take_while(|chunk| *chunk == block)
+                    .count();
+                if count < 2 {
+                    continue;
+                }
+                matches.push(Match {
+                    start,
+                    end: start + count * len,
+                    guesses_log10: self.estimate_chars(block).0 + (count as f64).log10(),
+                    pattern: Pattern::Repeat,
+                });
+            }
+        }
+        matches
+    }
+
+    /// Finds years between 1900 and 2099
+    fn year_matches(&self, chars: &[char]) -> Vec<Match> {
+        (0..chars.len().saturating_sub(3))
+            .filter_map(|start| {
+                let digits: String = chars[start..start + 4].iter().collect();
+                match digits.parse::<i32>() {
+                    Ok(year) if digits.starts_with("19") || digits.starts_with("20") => {
+                        let space = (year - self.year).abs().max(MIN_YEAR_SPACE);
+                        Some(Match {
+                            start,
+                            end: start + 4,
+                            guesses_log10: f64::from(space).log10(),
+                            pattern: Pattern::Year,
+                        })
+                    }
+                    _ => None,
+                }
+            })
+            .collect()
+    }
+}
+
+/// Returns the number of ways a word could have been capitalized
+fn uppercase_variations(word: &[char]) -> f64 {
+    let upper = word.iter().filter(|c| c.is_uppercase()).count();
+    let lower = word.iter().filter(|c| c.is_lowercase()).count();
+    if upper == 0 {
+        1.0
+    } else if lower == 0 || (upper == 1 && word[0].is_uppercase()) {
+        2.0
+    } else {
+        2f64.powi(upper.min(lower) as i32)
+    }
+}
+
+/// Finds runs of at least three letters or digits following each other,
+/// such as `abc` or `987`
+fn sequence_matches(chars: &[char]) -> Vec<Match> {
+    let class = |c: char| {
+        if c.is_ascii_lowercase() {
+            Some(0)
+        } else if c.is_ascii_uppercase() {
+            Some(1)
+        } else if c.is_ascii_digit() {
+            Some(2)
+        } else {
+            None
+        }
+    };
+
+    let mut matches = Vec::new();
+    let mut start = 0;
+    while start + 2 < chars.len() {
+        let delta = chars[start + 1] as i32 - chars[start] as i32;
+        let mut end = start + 1;
+        while end < chars.len()
+            && (delta == 1 || delta == -1)
+            && class(chars[end]).is_some()
+            && class(chars[end]) == class(chars[start])
+            && chars[end] as i32 - chars[end - 1] as i32 == delta
+        {
+            end += 1;
+        }
+        if end - start < 3 {
+            start += 1;
+            continue;
+        }
+        let base = match chars[start] {
+            'a' | 'A' | 'z' | 'Z' | '0' | '1' | '9' => 4.0,
+            c if c.is_ascii_digit() => 10.0,
+            _ => 26.0,
+        };
+        let direction: f64 = if delta < 0 { 2.0 } else { 1.0 };
+        matches.push(Match {
+            start,
+            end,
+            guesses_log10: (base * (end - start) as f64 * direction).log10(),
+            pattern: Pattern::Sequence,
+        });
+        start = end;
+    }
+    matches
+}
+
+/// Finds runs of adjacent keys on a row of a QWERTY keyboard, such as `asdf`
+/// or `!@#$`
+fn keyboard_matches(chars: &[char]) -> Vec<Match> {
+    let position = |row: &str, c: char| row.chars().position(|k| k == c.to_ascii_lowercase());
+    let adjacent =
+        |row: &str, a: char, b: char, delta: i32| match (position(row, a), position(row, b)) {
+            (Some(a), Some(b)) => b as i32 - a as i32 == delta,
+            _ => false,
+        };
+
+    let mut matches = Vec::new();
+    for row in &KEYBOARD_ROWS {
+        for &delta in &[1, -1] {
+            let mut start = 0;
+            while start < chars.len() {
+                let mut end = start + 1;
+                while end < chars.len() && adjacent(row, chars[end - 1], chars[end], delta) {
+                    end += 1;
+                }
+                if end - start < MIN_KEYBOARD_LENGTH {
+                    start += 1;
+                    continue;
+                }
+                let direction: f64 = if delta < 0 { 2.0 } else { 1.0 };
+                let keys = row.len() as f64;
+                matches.push(Match {
+                    start,
+                    end,
+                    guesses_log10: (keys * (end - start) as f64 * direction).log10(),
+                    pattern: Pattern::Keyboard,
+                });
+                start = end;
+            }
+        }
+    }
+    matches
+}
+
+#[cfg(test)]
+mod tests {
+    use lib::audit::strength::{Estimator, Pattern};
+
+    #[test]
+    fn common_passwords() {
+        let estimator = Estimator::new();
+        assert!(estimator.is_common("password"));
+        assert!(estimator.is_common("PassWord"));
+        assert!(!estimator.is_common("correct horse battery staple"));
+
+        let strength = estimator.estimate("P@ssw0rd");
+        assert_eq!(strength.score, 0);
+        assert_eq!(strength.pattern, Some(Pattern::Common));
+    }
+
+    #[test]
+    fn patterns() {
+        let estimator = Estimator::new();
+        let cases = [
+            ("abcdefgh", Pattern::Sequence),
+            ("98765432", Pattern::Sequence),
+            ("xyzxyzxyzxyz", Pattern::Repeat),
+            ("zzzzzzzzzz", Pattern::Repeat),
+            ("asdfghjkl", Pattern::Keyboard),
+            ("1987", Pattern::Year),
+        ];
+        for (password, pattern) in cases.iter() {
+            let strength = estimator.estimate(password);
+            assert_eq!(strength.pattern, Some(*pattern), "{}", password);
+            assert!(strength.score < 2, "{}", password);
+        }
+    }
+
+    #[test]
+    fn scores() {
+        let estimator = Estimator::new();
+        assert_eq!(estimator.estimate("").score, 0);
+        assert_eq!(estimator.estimate("x7#q").score, 1);
+        assert_eq!(estimator.estimate("monkey1987").score, 0);
+        assert_eq!(estimator.estimate("k3#Rq9Lw").score, 3);
+        assert_eq!(estimator.estimate("wV8!pZ2#mQ5&tR").score, 4);
+        assert_eq!(estimator.estimate("x7#q").pattern, None);
+    }
+}
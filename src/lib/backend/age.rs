@@ -0,0 +1,203 @@
+// Copyright (C) 2018 Alberto Coscia
+//
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at http://mozilla.org/MPL/2.0/.
+
+use age::armor::{ArmoredReader, ArmoredWriter, Format};
+use age::secrecy::SecretString;
+use age::{scrypt, x25519, Decryptor, Encryptor, Identity, Recipient};
+use lib::backend::{Backend, Encrypted};
+use lib::error::BackendError;
+use rpassword;
+use std::error::Error;
+use std::fs::read_to_string;
+use std::io::{Read, Write};
+use std::iter;
+use std::path::PathBuf;
+
+/// Encrypts vaults as armored age files, either to X25519 recipients or with
+/// a passphrase. Keys are comma-separated `age1...` recipients
+pub struct Age {
+    /// The file holding the X25519 identities that decrypt vaults
+    identity_file: Option<PathBuf>,
+    /// The passphrase of the vault, once known. Vaults are written back with
+    /// it instead of being encrypted to recipients
+    passphrase: Option<SecretString>,
+}
+
+impl Age {
+    pub fn new(identity_file: Option<PathBuf>) -> Age {
+        Age {
+            identity_file,
+            passphrase: None,
+        }
+    }
+
+    /// Encrypts vaults with a passphrase instead of recipients
+    pub fn set_passphrase(&mut self, passphrase: SecretString) {
+        self.passphrase = Some(passphrase);
+    }
+
+    /// Encrypts vaults to recipients again, after decrypting one with a
+    /// passphrase
+    pub fn forget_passphrase(&mut self) {
+        self.passphrase = None;
+    }
+
+    /// Parses comma-separated recipients, or uses those of the identity file
+    /// if there are none
+    fn recipients(&self, key: &str) -> Result<Vec<x25519::Recipient>, Box<Error>> {
+        if key.trim().is_empty() {
+            return Ok(self.identities()?.iter().map(|i| i.to_public()).collect());
+        }
+        let recipients = key
+            .split(',')
+            .map(|r| {
+                r.trim().parse().map_err(|_| {
+                    BackendError::InvalidAgeRecipient(r.trim().to_string()).to_string()
+                })
+            })
+            .collect::<Result<_, _>>()?;
+
+        Ok(recipients)
+    }
+
+    /// Parses the identity file, ignoring empty lines and comments as
+    /// `age-keygen` writes them
+    fn identities(&self) -> Result<Vec<x25519::Identity>, Box<Error>> {
+        let path = self
+            .identity_file
+            .as_ref()
+            .ok_or_else(|| BackendError::NoAgeIdentity.to_string())?;
+        let contents = read_to_string(path)?;
+        let identities = contents
+            .lines()
+            .map(|l| l.trim())
+            .filter(|l| !l.is_empty() && !l.starts_with('#'))
+            .map(|l| {
+                l.parse::<x25519::Identity>().map_err(|_| {
+                    BackendError::InvalidAgeIdentity(path.display().to_string()).to_string()
+                })
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+        if identities.is_empty() {
+            return Err(From::from(BackendError::NoAgeIdentity.to_string()));
+        }
+
+        Ok(identities)
+    }
+}
+
+impl Backend for Age {
+    /// Decrypts an age file, asking for its passphrase if it was encrypted
+    /// with one and it is not known yet
+    fn decrypt(&mut self, ciphertext: &[u8]) -> Result<Vec<u8>, Box<Error>> {
+        let decryptor = Decryptor::new_buffered(ArmoredReader::new(ciphertext))?;
+        let mut reader = if decryptor.is_scrypt() {
+            if self.passphrase.is_none() {
+                let passphrase = rpassword::prompt_password("Vault passphrase: ")?;
+                self.passphrase = Some(SecretString::from(passphrase));
+            }
+            // Safe unwrap, it was just set
+            let identity = scrypt::Identity::new(self.passphrase.clone().unwrap());
+            decryptor.decrypt(iter::once(&identity as &Identity))?
+        } else {
+            let identities = self.identities()?;
+            decryptor.decrypt(identities.iter().map(|i| i as &Identity))?
+        };
+
+        let mut output: Vec<u8> = Vec::new();
+        reader.read_to_end(&mut output)?;
+
+        Ok(output)
+    }
+
+    /// Encrypts data with the known passphrase or, failing that, to the
+    /// recipients in `key` or those of the identity file
+    fn encrypt(&mut self, plaintext: &[u8], key: &str) -> Result<Encrypted, Box<Error>> {
+        let (encryptor, keys) = match self.passphrase {
+            Some(ref passphrase) => (
+                Encryptor::with_user_passphrase(passphrase.clone()),
+                vec!["a passphrase".to_string()],
+            ),
+            None => {
+                let recipients = self.recipients(key)?;
+                (
+                    Encryptor::with_recipients(recipients.iter().map(|r| r as &Recipient))?,
+                    recipients.iter().map(|r| r.to_string()).collect(),
+                )
+            }
+        };
+
+        let mut output: Vec<u8> = Vec::new();
+        {
+            let armored = ArmoredWriter::wrap_output(&mut output, Format::AsciiArmor)?;
+            let mut writer = encryptor.wrap_output(armored)?;
+            writer.write_all(plaintext)?;
+            writer.finish()?.finish()?;
+        }
+
+        Ok(Encrypted {
+            ciphertext: output,
+            keys,
+            warnings: Vec::new(),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use age::secrecy::{ExposeSecret, SecretString};
+    use age::x25519;
+    use lib::backend::age::Age;
+    use lib::backend::{Backend, BackendKind};
+    use std::env;
+    use std::fs::{remove_file, write};
+
+    #[test]
+    fn recipients() {
+        let identity = x25519::Identity::generate();
+        let path = env::temp_dir().join(format!("sigil-age-{}.txt", identity.to_public()));
+        let contents = format!(
+            "# public key: {}\n{}\n",
+            identity.to_public(),
+            identity.to_string().expose_secret()
+        );
+        write(&path, contents).unwrap();
+
+        let mut age = Age::new(Some(path.clone()));
+        let ciphertext = age
+            .encrypt(b"[passwords]", &identity.to_public().to_string())
+            .unwrap()
+            .ciphertext;
+        assert_eq!(BackendKind::detect(&ciphertext), BackendKind::Age);
+        assert_eq!(age.decrypt(&ciphertext).unwrap(), b"[passwords]");
+
+        // Vaults are written back to the identity file when no recipient is
+        // provided
+        let ciphertext = age.encrypt(b"[otps]", "").unwrap().ciphertext;
+        assert_eq!(age.decrypt(&ciphertext).unwrap(), b"[otps]");
+
+        assert!(age.encrypt(b"[otps]", "0xDEADBEEF").is_err());
+        assert!(Age::new(None).decrypt(&ciphertext).is_err());
+        remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn passphrase() {
+        let mut age = Age::new(None);
+        age.set_passphrase(SecretString::from(
+            "correct horse battery staple".to_string(),
+        ));
+        let encrypted = age.encrypt(b"[passwords]", "").unwrap();
+        assert_eq!(encrypted.keys, vec!["a passphrase"]);
+        let ciphertext = encrypted.ciphertext;
+        assert_eq!(BackendKind::detect(&ciphertext), BackendKind::Age);
+        assert_eq!(age.decrypt(&ciphertext).unwrap(), b"[passwords]");
+
+        let mut wrong = Age::new(None);
+        wrong.set_passphrase(SecretString::from("hunter2".to_string()));
+        assert!(wrong.decrypt(&ciphertext).is_err());
+    }
+}
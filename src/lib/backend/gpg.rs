@@ -0,0 +1,182 @@
+// Copyright (C) 2018 Alberto Coscia
+//
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at http://mozilla.org/MPL/2.0/.
+
+use chrono::{DateTime, Utc};
+use gpgme::{Context, Key, Validity};
+use lib::backend::{Backend, Encrypted};
+use lib::config::normalize_fingerprint;
+use lib::error::BackendError;
+use lib::utils;
+use std::error::Error;
+
+/// Encrypts vaults as armored OpenPGP messages using GPGME. Keys are
+/// comma-separated GPG key IDs, fingerprints or UIDs
+pub struct Gpg {
+    pub ctx: Context,
+    /// The fingerprints of the only keys data may be encrypted to
+    pinned: Vec<String>,
+    /// Whether keys that are not pinned are refused, rather than only warned
+    /// about
+    pub require_pinned: bool,
+}
+
+impl Gpg {
+    pub fn new(pinned: Vec<String>, require_pinned: bool) -> Result<Gpg, Box<Error>> {
+        Ok(Gpg {
+            ctx: utils::create_context()?,
+            pinned,
+            require_pinned,
+        })
+    }
+}
+
+impl Backend for Gpg {
+    fn decrypt(&mut self, ciphertext: &[u8]) -> Result<Vec<u8>, Box<Error>> {
+        let mut output: Vec<u8> = Vec::new();
+        self.ctx.decrypt(ciphertext, &mut output)?;
+
+        Ok(output)
+    }
+
+    fn encrypt(&mut self, plaintext: &[u8], key: &str) -> Result<Encrypted, Box<Error>> {
+        let pinned = if self.require_pinned {
+            Some(&self.pinned[..])
+        } else {
+            None
+        };
+        let keys = select_keys(&mut self.ctx, key, pinned)?;
+
+        let mut output: Vec<u8> = Vec::new();
+        self.ctx.encrypt(&keys, plaintext, &mut output)?;
+
+        let warnings = keys
+            .iter()
+            .filter(|k| !is_pinned(k, &self.pinned))
+            .map(|k| {
+                format!(
+                    "{} is not pinned, so vaults encrypted to it cannot be changed \
+                     until you run `sigil config pin {}`",
+                    describe_key(k),
+                    k.fingerprint().unwrap_or("<fingerprint>")
+                )
+            })
+            .collect();
+        Ok(Encrypted {
+            ciphertext: output,
+            keys: keys.iter().map(describe_key).collect(),
+            warnings,
+        })
+    }
+}
+
+/// Finds the keys matching comma-separated patterns
+/**
+ * Blueprint
+ *  1. Bail if no pattern is provided, an empty one would match every key
+ *  2. For every pattern, keep the matching keys that can encrypt, are not
+ *     expired, revoked, disabled or invalid, are fully trusted and whose
+ *     fingerprint is pinned, if `pinned` is given
+ *  3. Bail if a pattern has no such key, explaining why its keys were refused
+ */
+pub fn select_keys(
+    ctx: &mut Context,
+    key: &str,
+    pinned: Option<&[String]>,
+) -> Result<Vec<Key>, Box<Error>> {
+    // (1)
+    let patterns: Vec<&str> = key
+        .split(',')
+        .map(|p| p.trim())
+        .filter(|p| !p.is_empty())
+        .collect();
+    if patterns.is_empty() {
+        return Err(From::from(BackendError::NoKey.to_string()));
+    }
+
+    let mut selected: Vec<Key> = Vec::new();
+    for pattern in patterns {
+        // (2)
+        let keys: Vec<Key> = ctx
+            .find_keys(vec![pattern])?
+            .filter_map(|k| k.ok())
+            .collect();
+        let (usable, refused): (Vec<Key>, Vec<Key>) =
+            keys.into_iter().partition(|k| refusal(k, pinned).is_none());
+
+        // (3)
+        if usable.is_empty() {
+            if refused.is_empty() {
+                return Err(From::from(
+                    BackendError::UnknownKey(pattern.to_string()).to_string(),
+                ));
+            }
+            let reasons: Vec<String> = refused
+                .iter()
+                .map(|k| format!("{} is {}", describe_key(k), refusal(k, pinned).unwrap()))
+                .collect();
+            return Err(From::from(
+                BackendError::UnusableKey(pattern.to_string(), reasons.join(", ")).to_string(),
+            ));
+        }
+        for key in usable {
+            if !selected
+                .iter()
+                .any(|k| k.fingerprint_raw() == key.fingerprint_raw())
+            {
+                selected.push(key);
+            }
+        }
+    }
+
+    Ok(selected)
+}
+
+/// Returns why a key cannot be used for encryption, if it cannot
+fn refusal(key: &Key, pinned: Option<&[String]>) -> Option<&'static str> {
+    if key.is_revoked() {
+        Some("revoked")
+    } else if key.is_expired() {
+        Some("expired")
+    } else if key.is_disabled() {
+        Some("disabled")
+    } else if key.is_invalid() {
+        Some("invalid")
+    } else if !key.can_encrypt() {
+        Some("not an encryption key")
+    } else if !key
+        .user_ids()
+        .any(|u| u.validity() == Validity::Full || u.validity() == Validity::Ultimate)
+    {
+        Some("not fully trusted")
+    } else if pinned.map_or(false, |p| !is_pinned(key, p)) {
+        Some("not pinned, pin it with `sigil config pin <fingerprint>`")
+    } else {
+        None
+    }
+}
+
+fn is_pinned(key: &Key, pinned: &[String]) -> bool {
+    key.fingerprint()
+        .map(|fpr| pinned.contains(&normalize_fingerprint(fpr)))
+        .unwrap_or(false)
+}
+
+/// Describes a key by its fingerprint, first user ID and expiration date
+pub fn describe_key(key: &Key) -> String {
+    let fingerprint = key.fingerprint().unwrap_or("unknown fingerprint");
+    let expiry = match key.primary_key().and_then(|k| k.expiration_time()) {
+        Some(time) => format!("expires {}", DateTime::<Utc>::from(time).format("%Y-%m-%d")),
+        None => "never expires".to_string(),
+    };
+    match key
+        .user_ids()
+        .next()
+        .and_then(|u| u.id().ok().map(String::from))
+    {
+        Some(uid) => format!("{} ({}), {}", fingerprint, uid, expiry),
+        None => format!("{}, {}", fingerprint, expiry),
+    }
+}
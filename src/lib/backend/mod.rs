@@ -0,0 +1,188 @@
+// Copyright (C) 2018 Alberto Coscia
+//
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at http://mozilla.org/MPL/2.0/.
+
+pub mod age;
+pub mod gpg;
+#[cfg(any(test, feature = "test-plaintext"))]
+pub mod plaintext;
+
+use self::age::Age;
+use self::gpg::Gpg;
+#[cfg(any(test, feature = "test-plaintext"))]
+use self::plaintext::Plaintext;
+use gpgme::Context;
+use lib::error::BackendError;
+use std::error::Error;
+use std::fs::File;
+use std::io::Read;
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+
+/// Encrypts and decrypts vaults
+pub trait Backend {
+    /// Decrypts the contents of an encrypted file
+    fn decrypt(&mut self, ciphertext: &[u8]) -> Result<Vec<u8>, Box<Error>>;
+    /// Encrypts data to a key, whose format depends on the backend
+    fn encrypt(&mut self, plaintext: &[u8], key: &str) -> Result<Encrypted, Box<Error>>;
+}
+
+/// Data encrypted by a backend, and the keys it selected to do so
+pub struct Encrypted {
+    pub ciphertext: Vec<u8>,
+    /// Describes every key the data was encrypted to
+    pub keys: Vec<String>,
+    /// Why some of the keys may not be the intended ones
+    pub warnings: Vec<String>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum BackendKind {
+    Gpg,
+    Age,
+}
+
+impl FromStr for BackendKind {
+    type Err = BackendError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match &s.to_ascii_lowercase()[..] {
+            "gpg" => Ok(BackendKind::Gpg),
+            "age" => Ok(BackendKind::Age),
+            _ => Err(BackendError::UnknownBackend),
+        }
+    }
+}
+
+impl BackendKind {
+    /// Detects the backend that encrypted some data from its header. Anything
+    /// that is not an age file is assumed to be an OpenPGP message
+    pub fn detect(contents: &[u8]) -> BackendKind {
+        let start = contents
+            .iter()
+            .position(|b| !b.is_ascii_whitespace())
+            .unwrap_or(0);
+        let contents = &contents[start..];
+        if contents.starts_with(b"age-encryption.org/")
+            || contents.starts_with(b"-----BEGIN AGE ENCRYPTED FILE-----")
+        {
+            BackendKind::Age
+        } else {
+            BackendKind::Gpg
+        }
+    }
+
+    /// Detects the backend that encrypted a file, if it can be read
+    pub fn detect_file(path: &Path) -> Option<BackendKind> {
+        let mut header = Vec::new();
+        File::open(path)
+            .and_then(|file| file.take(64).read_to_end(&mut header))
+            .ok()?;
+        Some(BackendKind::detect(&header))
+    }
+}
+
+/// The backends used to read and write vaults, each created when first needed
+pub struct Crypto {
+    gpg: Option<Gpg>,
+    /// The fingerprints of the only GPG keys data may be encrypted to
+    pinned_keys: Vec<String>,
+    /// Whether GPG keys that are not pinned are refused
+    require_pinned: bool,
+    age: Age,
+    /// Replaces every other backend when set
+    #[cfg(any(test, feature = "test-plaintext"))]
+    plaintext: Option<Plaintext>,
+}
+
+impl Crypto {
+    /// Creates the backends, age ones decrypt vaults using the X25519
+    /// identities in `age_identity` and GPG ones only encrypt to the keys in
+    /// `pinned_keys`
+    pub fn new(age_identity: Option<PathBuf>, pinned_keys: Vec<String>) -> Crypto {
+        Crypto {
+            gpg: None,
+            pinned_keys,
+            require_pinned: true,
+            age: Age::new(age_identity),
+            #[cfg(any(test, feature = "test-plaintext"))]
+            plaintext: None,
+        }
+    }
+
+    /// Creates backends that read and write every vault unencrypted, for
+    /// testing purposes
+    #[cfg(any(test, feature = "test-plaintext"))]
+    pub fn plaintext() -> Crypto {
+        Crypto {
+            plaintext: Some(Plaintext),
+            ..Crypto::new(None, Vec::new())
+        }
+    }
+
+    /// Lets GPG backends encrypt to keys that are not pinned, for recipients
+    /// chosen explicitly on the command line
+    pub fn allow_unpinned_keys(&mut self) {
+        self.require_pinned = false;
+        if let Some(ref mut gpg) = self.gpg {
+            gpg.require_pinned = false;
+        }
+    }
+
+    /// Returns the GPGME context, which is also used for `pass` stores and
+    /// encrypted exports
+    pub fn gpg(&mut self) -> Result<&mut Context, Box<Error>> {
+        if self.gpg.is_none() {
+            self.gpg = Some(Gpg::new(self.pinned_keys.clone(), self.require_pinned)?);
+        }
+        // Safe unwrap, it was just created
+        Ok(&mut self.gpg.as_mut().unwrap().ctx)
+    }
+
+    pub fn age(&mut self) -> &mut Age {
+        &mut self.age
+    }
+
+    /// Returns a backend, creating it if needed
+    pub fn backend(&mut self, kind: BackendKind) -> Result<&mut Backend, Box<Error>> {
+        #[cfg(any(test, feature = "test-plaintext"))]
+        {
+            if let Some(ref mut plaintext) = self.plaintext {
+                return Ok(plaintext);
+            }
+        }
+        match kind {
+            BackendKind::Gpg => {
+                self.gpg()?;
+                // Safe unwrap, it was just created
+                Ok(self.gpg.as_mut().unwrap())
+            }
+            BackendKind::Age => Ok(&mut self.age),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use lib::backend::BackendKind;
+
+    #[test]
+    fn detection() {
+        assert_eq!(
+            BackendKind::detect(b"-----BEGIN AGE ENCRYPTED FILE-----\nYWdl"),
+            BackendKind::Age
+        );
+        assert_eq!(
+            BackendKind::detect(b"age-encryption.org/v1\n-> X25519"),
+            BackendKind::Age
+        );
+        assert_eq!(
+            BackendKind::detect(b"\n-----BEGIN PGP MESSAGE-----\n"),
+            BackendKind::Gpg
+        );
+        assert_eq!(BackendKind::detect(b"\x85\x02\x0c"), BackendKind::Gpg);
+        assert_eq!(BackendKind::detect(b""), BackendKind::Gpg);
+    }
+}
@@ -0,0 +1,26 @@
+// Copyright (C) 2018 Alberto Coscia
+//
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at http://mozilla.org/MPL/2.0/.
+
+use lib::backend::{Backend, Encrypted};
+use std::error::Error;
+
+/// Leaves vaults unencrypted, so that vault logic can be tested without a
+/// keyring. Only available with the `test-plaintext` feature
+pub struct Plaintext;
+
+impl Backend for Plaintext {
+    fn decrypt(&mut self, ciphertext: &[u8]) -> Result<Vec<u8>, Box<Error>> {
+        Ok(ciphertext.to_vec())
+    }
+
+    fn encrypt(&mut self, plaintext: &[u8], _key: &str) -> Result<Encrypted, Box<Error>> {
+        Ok(Encrypted {
+            ciphertext: plaintext.to_vec(),
+            keys: Vec::new(),
+            warnings: Vec::new(),
+        })
+    }
+}
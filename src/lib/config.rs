@@ -0,0 +1,394 @@
+// Copyright (C) 2018 Alberto Coscia
+//
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at http://mozilla.org/MPL/2.0/.
+
+use lib::error::ConfigError;
+use lib::types::HmacAlgorithm;
+use std::collections::BTreeMap;
+use std::env;
+use std::error::Error;
+use std::fs::read_to_string;
+use std::io;
+use std::path::PathBuf;
+use std::str::FromStr;
+use toml::value::Table;
+use toml::{from_str, Value};
+
+/// The keys a configuration file can set, tables and their keys are separated
+/// by dots. Profiles are set as `profiles.<name>.vault` and
+/// `profiles.<name>.key`
+pub const KEYS: &[&str] = &[
+    "profile",
+    "vault",
+    "key",
+    "age_identity",
+    "pinned_keys",
+    "track_usage",
+    "output",
+    "generator.length",
+    "generator.symbols",
+    "otp.algorithm",
+    "otp.digits",
+    "otp.period",
+];
+
+/// The settings read from the configuration file. Command line arguments and
+/// environment variables take precedence over them
+#[derive(Debug, Default, Deserialize, PartialEq)]
+#[serde(deny_unknown_fields)]
+pub struct Config {
+    /// Path to the vault
+    pub vault: Option<PathBuf>,
+    /// The key to use for encryption
+    pub key: Option<String>,
+    /// The profile used when none is selected
+    pub profile: Option<String>,
+    /// Named vaults, such as "personal" or "work"
+    #[serde(default)]
+    pub profiles: BTreeMap<String, Profile>,
+    /// The identity file used to decrypt age vaults
+    pub age_identity: Option<PathBuf>,
+    /// Fingerprints of the GPG keys that vaults may be encrypted to
+    #[serde(default)]
+    pub pinned_keys: Vec<String>,
+    /// Whether to record when records are last used
+    pub track_usage: Option<bool>,
+    /// How `sigil ls` prints records
+    pub output: Option<OutputFormat>,
+    #[serde(default)]
+    pub generator: GeneratorConfig,
+    #[serde(default)]
+    pub otp: OtpConfig,
+}
+
+/// A named vault and the key it is encrypted to
+#[derive(Debug, Default, Deserialize, PartialEq)]
+#[serde(deny_unknown_fields)]
+pub struct Profile {
+    pub vault: Option<PathBuf>,
+    pub key: Option<String>,
+}
+
+/// The policy of generated passwords
+#[derive(Debug, Default, Deserialize, PartialEq)]
+#[serde(deny_unknown_fields)]
+pub struct GeneratorConfig {
+    /// How many characters a password has when no length is given
+    pub length: Option<usize>,
+    /// Whether passwords include punctuation
+    pub symbols: Option<bool>,
+}
+
+/// The settings of new OTP generators
+#[derive(Debug, Default, Deserialize, PartialEq)]
+#[serde(deny_unknown_fields)]
+pub struct OtpConfig {
+    pub algorithm: Option<HmacAlgorithm>,
+    pub digits: Option<u32>,
+    pub period: Option<u64>,
+}
+
+#[derive(Debug, Clone, Copy, Deserialize, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum OutputFormat {
+    Text,
+    Json,
+}
+
+impl Config {
+    /// Returns the location of the configuration file,
+    /// `$XDG_CONFIG_HOME/sigil/config.toml` or
+    /// `$HOME/.config/sigil/config.toml`
+    pub fn path() -> Option<PathBuf> {
+        let base = match env::var_os("XDG_CONFIG_HOME") {
+            Some(ref dir) if !dir.is_empty() => PathBuf::from(dir),
+            _ => PathBuf::from(env::var_os("HOME")?).join(".config"),
+        };
+        Some(base.join("sigil").join("config.toml"))
+    }
+
+    /// Reads a configuration file, a missing one holds the default settings
+    pub fn load(path: &PathBuf) -> Result<Config, Box<Error>> {
+        match read_to_string(path) {
+            Ok(contents) => Ok(from_str(&contents)?),
+            Err(ref e) if e.kind() == io::ErrorKind::NotFound => Ok(Config::default()),
+            Err(e) => Err(From::from(e)),
+        }
+    }
+
+    /// Returns a profile by name, bails if it does not exist or has no vault
+    pub fn profile(&self, name: &str) -> Result<&Profile, ConfigError> {
+        match self.profiles.get(name) {
+            Some(profile) if profile.vault.is_some() => Ok(profile),
+            Some(_) => Err(ConfigError::NoProfileVault(name.to_string())),
+            None => Err(ConfigError::UnknownProfile(name.to_string())),
+        }
+    }
+
+    /// Returns the pinned fingerprints, uppercase and without spaces or `0x`
+    /// prefixes
+    pub fn pinned_fingerprints(&self) -> Vec<String> {
+        self.pinned_keys
+            .iter()
+            .map(|fpr| normalize_fingerprint(fpr))
+            .collect()
+    }
+}
+
+/// Returns the value of a dotted key in a configuration file, if it is set
+pub fn get_value<'a>(config: &'a Table, key: &str) -> Result<Option<&'a Value>, ConfigError> {
+    if !is_known_key(key) {
+        return Err(ConfigError::UnknownKey(key.to_string()));
+    }
+    let mut value = None;
+    let mut table = Some(config);
+    for part in key.split('.') {
+        value = table.and_then(|t| t.get(part));
+        table = value.and_then(|v| v.as_table());
+    }
+    Ok(value)
+}
+
+/// Sets a dotted key in a configuration file
+/**
+ * Blueprint
+ *  1. Bail if the key is unknown
+ *  2. Parse the value as a TOML value, such as `6`, `true` or `["a", "b"]`,
+ *     or as a string if it is none or the configuration rejects it
+ *  3. Set the value, creating its table if needed
+ *  4. Bail if the configuration rejects both values
+ */
+pub fn set_value(config: &mut Table, key: &str, value: &str) -> Result<(), ConfigError> {
+    // (1)
+    if !is_known_key(key) {
+        return Err(ConfigError::UnknownKey(key.to_string()));
+    }
+
+    // (2)
+    let mut candidates = Vec::new();
+    if let Ok(Value::Table(mut literal)) = Value::from_str(&format!("value = {}", value)) {
+        // Safe unwrap, the key was just parsed
+        candidates.push(literal.remove("value").unwrap());
+    }
+    candidates.push(Value::String(value.to_string()));
+
+    for candidate in candidates {
+        // (3)
+        let mut updated = config.clone();
+        let mut parts: Vec<&str> = key.split('.').collect();
+        // Safe unwrap, split always returns at least one part
+        let last = parts.pop().unwrap();
+        let mut table = &mut updated;
+        for part in parts {
+            let entry = table
+                .entry(part.to_string())
+                .or_insert_with(|| Value::Table(Table::new()));
+            table = match entry.as_table_mut() {
+                Some(table) => table,
+                None => {
+                    return Err(ConfigError::InvalidValue(
+                        key.to_string(),
+                        value.to_string(),
+                    ))
+                }
+            };
+        }
+        table.insert(last.to_string(), candidate);
+
+        if Value::Table(updated.clone()).try_into::<Config>().is_ok() {
+            *config = updated;
+            return Ok(());
+        }
+    }
+
+    // (4)
+    Err(ConfigError::InvalidValue(
+        key.to_string(),
+        value.to_string(),
+    ))
+}
+
+fn is_known_key(key: &str) -> bool {
+    let parts: Vec<&str> = key.split('.').collect();
+    match &parts[..] {
+        ["profiles", name, "vault"] | ["profiles", name, "key"] => !name.is_empty(),
+        _ => KEYS.contains(&key),
+    }
+}
+
+/// Formats a configuration value, without quoting strings
+pub fn display_value(value: &Value) -> String {
+    match value {
+        Value::String(s) => s.to_string(),
+        Value::Array(values) => {
+            let values: Vec<String> = values.iter().map(display_value).collect();
+            values.join(",")
+        }
+        Value::Integer(i) => i.to_string(),
+        Value::Float(f) => f.to_string(),
+        Value::Boolean(b) => b.to_string(),
+        Value::Datetime(d) => d.to_string(),
+        Value::Table(_) => String::new(),
+    }
+}
+
+/// Adds a GPG fingerprint to the pinned keys of a configuration file
+///
+/// Returns whether it was not pinned yet
+pub fn pin_key(config: &mut Table, fingerprint: &str) -> Result<bool, ConfigError> {
+    let fingerprint = normalize_fingerprint(fingerprint);
+    let invalid = || ConfigError::InvalidValue("pinned_keys".to_string(), fingerprint.clone());
+    // Full fingerprints only, as short key IDs can be forged
+    if !(fingerprint.len() == 40 || fingerprint.len() == 64)
+        || !fingerprint.chars().all(|c| c.is_ascii_hexdigit())
+    {
+        return Err(invalid());
+    }
+
+    let pinned = config
+        .entry("pinned_keys".to_string())
+        .or_insert_with(|| Value::Array(Vec::new()))
+        .as_array_mut()
+        .ok_or_else(invalid)?;
+    if pinned
+        .iter()
+        .any(|v| v.as_str().map(normalize_fingerprint).as_ref() == Some(&fingerprint))
+    {
+        return Ok(false);
+    }
+    pinned.push(Value::String(fingerprint.clone()));
+    Ok(true)
+}
+
+/// Normalizes a fingerprint, so that `0xabcd ef01` matches `ABCDEF01`
+pub fn normalize_fingerprint(fingerprint: &str) -> String {
+    let fingerprint: String = fingerprint
+        .chars()
+        .filter(|c| !c.is_whitespace())
+        .collect::<String>()
+        .to_ascii_uppercase();
+    match fingerprint.get(..2) {
+        Some("0X") => fingerprint[2..].to_string(),
+        _ => fingerprint,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use lib::config::{
+        display_value, get_value, normalize_fingerprint, pin_key, set_value, Config, OutputFormat,
+    };
+    use lib::types::HmacAlgorithm;
+    use std::env;
+    use std::fs::{remove_file, write};
+    use toml::value::Table;
+    use toml::{from_str, to_string, Value};
+
+    #[test]
+    fn pinned_keys() {
+        let config: Config =
+            from_str("pinned_keys = [\"0x0123 4567 89ab cdef 0123  4567 89ab cdef 0123 4567\"]")
+                .unwrap();
+        assert_eq!(
+            config.pinned_fingerprints(),
+            vec!["0123456789ABCDEF0123456789ABCDEF01234567"]
+        );
+        assert_eq!(normalize_fingerprint("ABCD"), "ABCD");
+
+        let config: Config = from_str("").unwrap();
+        assert!(config.pinned_keys.is_empty());
+    }
+
+    #[test]
+    fn pin() {
+        let mut config = Table::new();
+        let fingerprint = "0x0123 4567 89ab cdef 0123  4567 89ab cdef 0123 4567";
+        assert!(pin_key(&mut config, fingerprint).unwrap());
+        assert!(!pin_key(&mut config, "0123456789ABCDEF0123456789ABCDEF01234567").unwrap());
+        // Short key IDs and user IDs are not fingerprints
+        assert!(pin_key(&mut config, "89ABCDEF01234567").is_err());
+        assert!(pin_key(&mut config, "me@example.com").is_err());
+
+        let config: Config = from_str(&to_string(&Value::Table(config)).unwrap()).unwrap();
+        assert_eq!(
+            config.pinned_fingerprints(),
+            vec!["0123456789ABCDEF0123456789ABCDEF01234567"]
+        );
+
+        let mut config = Table::new();
+        config.insert("pinned_keys".to_string(), Value::from("ABCD"));
+        assert!(pin_key(&mut config, fingerprint).is_err());
+    }
+
+    #[test]
+    fn load() {
+        let path = env::temp_dir().join("sigil-config-load.toml");
+        let _ = remove_file(&path);
+        assert_eq!(Config::load(&path).unwrap(), Config::default());
+
+        write(
+            &path,
+            "key = \"me@example.com\"\noutput = \"json\"\n\n[otp]\nalgorithm = \"SHA256\"\ndigits = 8\n",
+        )
+        .unwrap();
+        let config = Config::load(&path).unwrap();
+        assert_eq!(config.key, Some("me@example.com".to_string()));
+        assert_eq!(config.output, Some(OutputFormat::Json));
+        assert_eq!(config.otp.algorithm, Some(HmacAlgorithm::SHA256));
+        assert_eq!(config.otp.digits, Some(8));
+        assert_eq!(config.otp.period, None);
+
+        write(&path, "pinned_keys = \"ABCD\"").unwrap();
+        assert!(Config::load(&path).is_err());
+        write(&path, "editor = \"vim\"").unwrap();
+        assert!(Config::load(&path).is_err());
+        remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn set_and_get() {
+        let mut config = Table::new();
+        set_value(&mut config, "otp.digits", "8").unwrap();
+        set_value(&mut config, "key", "me@example.com").unwrap();
+        // Strings that look like other TOML values are kept as strings
+        set_value(&mut config, "key", "1234").unwrap();
+        set_value(&mut config, "pinned_keys", "[\"ABCD\", \"EF01\"]").unwrap();
+        set_value(&mut config, "track_usage", "true").unwrap();
+        set_value(&mut config, "profiles.work.key", "team@example.com").unwrap();
+        set_value(&mut config, "profiles.work.vault", "/tmp/work.vault").unwrap();
+
+        assert!(set_value(&mut config, "otp.digits", "eight").is_err());
+        assert!(set_value(&mut config, "output", "yaml").is_err());
+        assert!(set_value(&mut config, "editor", "vim").is_err());
+        assert!(get_value(&config, "editor").is_err());
+        assert!(set_value(&mut config, "profiles.work.editor", "vim").is_err());
+
+        let value = |key| get_value(&config, key).unwrap().map(display_value);
+        assert_eq!(value("otp.digits"), Some("8".to_string()));
+        assert_eq!(value("key"), Some("1234".to_string()));
+        assert_eq!(value("pinned_keys"), Some("ABCD,EF01".to_string()));
+        assert_eq!(value("otp.period"), None);
+        assert_eq!(value("vault"), None);
+
+        let config: Config = from_str(&to_string(&Value::Table(config)).unwrap()).unwrap();
+        assert_eq!(config.otp.digits, Some(8));
+        assert_eq!(config.track_usage, Some(true));
+        assert_eq!(
+            config.profile("work").unwrap().key,
+            Some("team@example.com".to_string())
+        );
+    }
+
+    #[test]
+    fn profiles() {
+        let config: Config = from_str(
+            "profile = \"personal\"\n\n[profiles.personal]\nvault = \"/tmp/personal.vault\"\n\n[profiles.draft]\nkey = \"me\"\n",
+        )
+        .unwrap();
+        assert!(config.profile("personal").is_ok());
+        assert!(config.profile("draft").is_err());
+        assert!(config.profile("work").is_err());
+    }
+}
@@ -10,12 +10,101 @@ pub enum VaultError {
     ShouldUpdate,
     #[fail(display = "Failed to find a matching record")]
     UnknownRecord,
+    #[fail(display = "Failed to find a matching record, did you mean {}?", _0)]
+    AmbiguousRecord(String),
+    #[fail(display = "Unknown conflict policy, expected skip, rename or overwrite")]
+    UnknownConflictPolicy,
+    #[fail(display = "Unknown sort order, expected name, created, modified or last-used")]
+    UnknownSortOrder,
+    #[fail(display = "A record named {} already exists", _0)]
+    AlreadyExists(String),
+    #[fail(display = "{} is a folder, use -r to remove it and its contents", _0)]
+    IsFolder(String),
+    #[fail(display = "Invalid record name")]
+    InvalidRecordId,
+    #[fail(display = "Record has no field named {}", _0)]
+    UnknownField(String),
+    #[fail(display = "Invalid field ({}), expected key=value", _0)]
+    InvalidField(String),
 }
 
 #[derive(Debug, Fail)]
 pub enum OtpError {
-    #[fail(display = "No counter was provided")]
-    NoCounterProvided,
     #[fail(display = "Unknown HMAC algorithm")]
     UnknownHmacAlgorithm,
+    #[fail(display = "Secret is not a valid base32 string")]
+    InvalidSecret,
+    #[fail(display = "The HOTP counter cannot move past {}", _0)]
+    CounterOverflow(u64),
+    #[fail(display = "Unsupported token length ({}), expected 6 to 8 digits", _0)]
+    UnsupportedDigits(u32),
+    #[fail(display = "Unsupported period ({}), expected at least 1 second", _0)]
+    UnsupportedPeriod(u64),
+}
+
+#[derive(Debug, Fail)]
+pub enum UrlError {
+    #[fail(display = "Invalid URL scheme, expected otpauth://")]
+    InvalidScheme,
+    #[fail(display = "Unknown OTP type ({})", _0)]
+    UnknownOtpType(String),
+    #[fail(display = "URL has no label")]
+    MissingLabel,
+    #[fail(display = "URL has no {} parameter", _0)]
+    MissingParameter(&'static str),
+    #[fail(display = "URL has an invalid {} parameter", _0)]
+    InvalidParameter(&'static str),
+}
+
+#[derive(Debug, Fail)]
+pub enum ImportError {
+    #[fail(
+        display = "Unknown CSV format, expected firefox, chrome, bitwarden, 1password or lastpass"
+    )]
+    UnknownCsvFormat,
+    #[fail(
+        display = "Invalid column mapping ({}), expected field=column pairs such as name=Title",
+        _0
+    )]
+    InvalidMapping(String),
+    #[fail(display = "CSV file has no {} column", _0)]
+    MissingColumn(String),
+}
+
+#[derive(Debug, Fail)]
+pub enum ExportError {
+    #[fail(display = "Unknown export format, expected csv, json, keepass-xml or pass")]
+    UnknownFormat,
+}
+
+#[derive(Debug, Fail)]
+pub enum BackendError {
+    #[fail(display = "Unknown backend, expected gpg or age")]
+    UnknownBackend,
+    #[fail(display = "No age identity was provided, set SIGIL_AGE_IDENTITY to an identity file")]
+    NoAgeIdentity,
+    #[fail(display = "Invalid age identity in {}", _0)]
+    InvalidAgeIdentity(String),
+    #[fail(display = "Invalid age recipient ({}), expected age1...", _0)]
+    InvalidAgeRecipient(String),
+    #[fail(display = "Passphrases do not match")]
+    PassphraseMismatch,
+    #[fail(display = "No key was provided")]
+    NoKey,
+    #[fail(display = "No GPG key matches {}", _0)]
+    UnknownKey(String),
+    #[fail(display = "No usable GPG key matches {}: {}", _0, _1)]
+    UnusableKey(String, String),
+}
+
+#[derive(Debug, Fail)]
+pub enum ConfigError {
+    #[fail(display = "Unknown configuration key {}", _0)]
+    UnknownKey(String),
+    #[fail(display = "Invalid value for {}: {}", _0, _1)]
+    InvalidValue(String, String),
+    #[fail(display = "Unknown profile {}, list them with `sigil vaults ls`", _0)]
+    UnknownProfile(String),
+    #[fail(display = "Profile {} has no vault, set profiles.{}.vault", _0, _0)]
+    NoProfileVault(String),
 }
@@ -0,0 +1,118 @@
+// Copyright (C) 2018 Alberto Coscia
+//
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at http://mozilla.org/MPL/2.0/.
+
+use csv::Writer;
+use failure::Error;
+use lib::export::entries;
+use lib::otpauth;
+use lib::types::Vault;
+
+/// Serializes a vault to a Bitwarden CSV export
+/**
+ * Blueprint
+ *  1. Write the Bitwarden header row
+ *  2. Write a login row for every entry, storing the email and the custom
+ *     fields as `key: value` lines of the fields column and the OTP
+ *     generator, if any, as an `otpauth://` URL. Whether custom fields are
+ *     secret is lost, as Bitwarden CSV exports don't record it
+ */
+pub fn to_csv(vault: &Vault) -> Result<String, Error> {
+    tracepoint!();
+    let mut writer = Writer::from_writer(Vec::new());
+
+    // (1)
+    writer.write_record([
+        "folder",
+        "favorite",
+        "type",
+        "name",
+        "notes",
+        "fields",
+        "reprompt",
+        "login_uri",
+        "login_username",
+        "login_password",
+        "login_totp",
+    ])?;
+
+    // (2)
+    for entry in entries(vault) {
+        let record = entry.record;
+        let fields = record
+            .and_then(|r| r.email.as_ref())
+            .map(|email| ("email", email))
+            .into_iter()
+            .chain(
+                record
+                    .into_iter()
+                    .flat_map(|r| &r.fields)
+                    .map(|(key, field)| (&key[..], &field.value)),
+            )
+            .map(|(key, value)| format!("{}: {}", key, value))
+            .collect::<Vec<_>>()
+            .join("\n");
+        let totp = entry
+            .otp
+            .map(|otp| otpauth::to_url(entry.id, otp))
+            .unwrap_or_default();
+        writer.write_record([
+            "",
+            "",
+            "login",
+            entry.id,
+            text(record.and_then(|r| r.notes.as_ref())),
+            &fields,
+            "0",
+            text(record.and_then(|r| r.home.as_ref())),
+            text(record.and_then(|r| r.username.as_ref())),
+            text(record.map(|r| &r.password)),
+            &totp,
+        ])?;
+    }
+
+    Ok(String::from_utf8(writer.into_inner()?)?)
+}
+
+fn text(value: Option<&String>) -> &str {
+    value.map_or("", |v| &v[..])
+}
+
+#[cfg(test)]
+mod tests {
+    use lib::export::csv::to_csv;
+    use lib::import::csv::{read_csv, CsvFormat, Mapping};
+    use lib::types::{Field, Record, Vault};
+
+    #[test]
+    fn round_trip() {
+        let mut vault = Vault::default();
+        let mut record = Record::new(
+            "hunter, \"2\"".to_string(),
+            Some("bob".to_string()),
+            Some("bob@example.com".to_string()),
+            None,
+        );
+        record.notes = Some("Line 1\nLine 2".to_string());
+        record.fields.insert(
+            "PIN".to_string(),
+            Field {
+                value: "1234".to_string(),
+                secret: true,
+            },
+        );
+        vault.passwords.insert("service".to_string(), record);
+
+        let csv = to_csv(&vault).unwrap();
+        assert!(csv.contains("\"email: bob@example.com\nPIN: 1234\""));
+
+        let import = read_csv(&mut csv.as_bytes(), &Mapping::preset(CsvFormat::Bitwarden)).unwrap();
+        let (ref record_id, ref record) = import.passwords[0];
+        assert_eq!(record_id, "service");
+        assert_eq!(record.password, "hunter, \"2\"");
+        assert_eq!(record.notes.as_ref().unwrap(), "Line 1\nLine 2");
+        assert!(import.otps.is_empty());
+    }
+}
@@ -0,0 +1,200 @@
+// Copyright (C) 2018 Alberto Coscia
+//
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at http://mozilla.org/MPL/2.0/.
+
+use chrono::{DateTime, Utc};
+use failure::Error;
+use lib::export::entries;
+use lib::import::bitwarden::{HIDDEN, TEXT};
+use lib::otpauth;
+use lib::types::Vault;
+use serde_json;
+
+#[derive(Serialize)]
+struct Export<'a> {
+    encrypted: bool,
+    folders: Vec<()>,
+    items: Vec<Item<'a>>,
+}
+
+#[derive(Serialize)]
+struct Item<'a> {
+    #[serde(rename = "type")]
+    kind: u8,
+    name: &'a str,
+    notes: Option<&'a str>,
+    favorite: bool,
+    fields: Vec<Field<'a>>,
+    login: Login<'a>,
+    #[serde(rename = "creationDate", skip_serializing_if = "Option::is_none")]
+    creation_date: Option<DateTime<Utc>>,
+    #[serde(rename = "revisionDate", skip_serializing_if = "Option::is_none")]
+    revision_date: Option<DateTime<Utc>>,
+}
+
+#[derive(Serialize)]
+struct Field<'a> {
+    name: &'a str,
+    value: &'a str,
+    #[serde(rename = "type")]
+    kind: u8,
+}
+
+#[derive(Serialize)]
+struct Login<'a> {
+    uris: Vec<Uri<'a>>,
+    username: Option<&'a str>,
+    password: Option<&'a str>,
+    totp: Option<String>,
+}
+
+#[derive(Serialize)]
+struct Uri<'a> {
+    #[serde(rename = "match")]
+    kind: Option<u8>,
+    uri: &'a str,
+}
+
+/// Serializes a vault to an unencrypted Bitwarden JSON export
+/**
+ * Blueprint
+ *  1. Construct a login item for every entry, storing the email and the
+ *     custom fields as custom text or hidden fields and the OTP generator, if
+ *     any, as an `otpauth://` URL. Dates are those of the password, if any
+ *  2. Serialize the export, bail on error
+ */
+pub fn to_json(vault: &Vault) -> Result<String, Error> {
+    tracepoint!();
+
+    // (1)
+    let items = entries(vault)
+        .into_iter()
+        .map(|entry| {
+            let record = entry.record;
+            let timestamps = record
+                .map(|r| r.timestamps)
+                .or_else(|| entry.otp.map(|otp| *otp.timestamps()))
+                .unwrap_or_default();
+            Item {
+                // Logins
+                kind: 1,
+                name: entry.id,
+                notes: record.and_then(|r| r.notes.as_ref()).map(|n| &n[..]),
+                favorite: false,
+                fields: record
+                    .and_then(|r| r.email.as_ref())
+                    .map(|email| Field {
+                        name: "email",
+                        value: email,
+                        kind: TEXT,
+                    })
+                    .into_iter()
+                    .chain(
+                        record
+                            .into_iter()
+                            .flat_map(|r| &r.fields)
+                            .map(|(key, field)| Field {
+                                name: key,
+                                value: &field.value,
+                                kind: if field.secret { HIDDEN } else { TEXT },
+                            }),
+                    )
+                    .collect(),
+                login: Login {
+                    uris: record
+                        .and_then(|r| r.home.as_ref())
+                        .map(|home| Uri {
+                            kind: None,
+                            uri: home,
+                        })
+                        .into_iter()
+                        .collect(),
+                    username: record.and_then(|r| r.username.as_ref()).map(|u| &u[..]),
+                    password: record.map(|r| &r.password[..]),
+                    totp: entry.otp.map(|otp| otpauth::to_url(entry.id, otp)),
+                },
+                creation_date: timestamps.created,
+                revision_date: timestamps.modified,
+            }
+        })
+        .collect();
+
+    // (2)
+    Ok(serde_json::to_string_pretty(&Export {
+        encrypted: false,
+        folders: Vec::new(),
+        items,
+    })?)
+}
+
+#[cfg(test)]
+mod tests {
+    use lib::export::json::to_json;
+    use lib::import::bitwarden::read_export;
+    use lib::types::{Field, HmacAlgorithm, OtpRecord, Record, Vault};
+
+    #[test]
+    fn round_trip() {
+        let mut vault = Vault::default();
+        let mut record = Record::new(
+            "hunter2".to_string(),
+            Some("bob".to_string()),
+            Some("bob@example.com".to_string()),
+            Some("https://service.tld".to_string()),
+        );
+        record
+            .fields
+            .insert("PIN".to_string(), Field::parse("PIN=1234", true).unwrap().1);
+        vault.passwords.insert("service".to_string(), record);
+        vault.otps.insert(
+            "service".to_string(),
+            OtpRecord::new_totp(
+                "GEZDGNBVGY3TQOJQGEZDGNBVGY3TQOJQ".to_string(),
+                None,
+                HmacAlgorithm::SHA256,
+                8,
+                60,
+            ),
+        );
+        vault.otps.insert(
+            "other".to_string(),
+            OtpRecord::new_hotp(
+                "GEZDGNBVGY3TQOJQGEZDGNBVGY3TQOJQ".to_string(),
+                None,
+                HmacAlgorithm::SHA1,
+                6,
+                4,
+            ),
+        );
+
+        let json = to_json(&vault).unwrap();
+        assert!(json.contains("bob@example.com"));
+
+        let import = read_export(&mut json.as_bytes()).unwrap();
+        assert_eq!(import.passwords.len(), 1);
+        assert_eq!(import.passwords[0].0, "service");
+        assert_eq!(import.passwords[0].1.password, "hunter2");
+        assert_eq!(
+            import.passwords[0].1.email.as_ref().unwrap(),
+            "bob@example.com"
+        );
+        assert!(import.passwords[0].1.fields["PIN"].secret);
+        assert_eq!(import.otps.len(), 2);
+        // Entries are sorted by record ID
+        match import.otps[0] {
+            (ref id, OtpRecord::Hotp { counter: 4, .. }) => assert_eq!(id, "other"),
+            _ => panic!("Expected a HOTP record"),
+        }
+        match import.otps[1].1 {
+            OtpRecord::Totp {
+                algorithm: HmacAlgorithm::SHA256,
+                digits: 8,
+                period: 60,
+                ..
+            } => {}
+            _ => panic!("Expected a TOTP record"),
+        }
+    }
+}
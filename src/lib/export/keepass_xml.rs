@@ -0,0 +1,138 @@
+// Copyright (C) 2018 Alberto Coscia
+//
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at http://mozilla.org/MPL/2.0/.
+
+use base64;
+use lib::export::entries;
+use lib::otpauth;
+use lib::types::Vault;
+use rand::{thread_rng, Rng};
+
+/// The fields of an entry written by Sigil, which KeePass doesn't allow twice
+const RESERVED: &[&str] = &[
+    "Title", "UserName", "Password", "URL", "Notes", "Email", "otp",
+];
+
+/// Serializes a vault to a KeePass 2 XML file, which KeePass and KeePassXC
+/// can import
+/**
+ * Blueprint
+ *  1. Open a single "Sigil" group
+ *  2. Write an entry with a random UUID for every record, storing the email
+ *     and the custom fields as custom, protected if secret, fields and the
+ *     OTP generator, if any, as an `otpauth://` URL in the `otp` field used
+ *     by KeePassXC. Custom fields named after one of those Sigil writes are
+ *     renamed, such as "Title (custom)"
+ */
+pub fn to_keepass_xml(vault: &Vault) -> String {
+    tracepoint!();
+    let mut random = thread_rng();
+
+    // (1)
+    let mut buf = String::from(
+        "<?xml version=\"1.0\" encoding=\"utf-8\" standalone=\"yes\"?>\n\
+         <KeePassFile>\n\
+         \t<Meta>\n\t\t<Generator>Sigil</Generator>\n\t</Meta>\n\
+         \t<Root>\n\t\t<Group>\n",
+    );
+    buf += &format!("\t\t\t<UUID>{}</UUID>\n", uuid(&mut random));
+    buf += "\t\t\t<Name>Sigil</Name>\n";
+
+    // (2)
+    for entry in entries(vault) {
+        buf += "\t\t\t<Entry>\n";
+        buf += &format!("\t\t\t\t<UUID>{}</UUID>\n", uuid(&mut random));
+        add_string(&mut buf, "Title", Some(entry.id), false);
+        if let Some(record) = entry.record {
+            add_string(&mut buf, "UserName", record.username.as_ref(), false);
+            add_string(&mut buf, "Password", Some(&record.password), true);
+            add_string(&mut buf, "URL", record.home.as_ref(), false);
+            add_string(&mut buf, "Notes", record.notes.as_ref(), false);
+            add_string(&mut buf, "Email", record.email.as_ref(), false);
+            for (key, field) in &record.fields {
+                let key = if RESERVED.contains(&&key[..]) {
+                    format!("{} (custom)", key)
+                } else {
+                    key.to_string()
+                };
+                add_string(&mut buf, &key, Some(&field.value), field.secret);
+            }
+        }
+        if let Some(otp) = entry.otp {
+            add_string(&mut buf, "otp", Some(&otpauth::to_url(entry.id, otp)), true);
+        }
+        buf += "\t\t\t</Entry>\n";
+    }
+
+    buf += "\t\t</Group>\n\t</Root>\n</KeePassFile>\n";
+    buf
+}
+
+fn add_string<S: AsRef<str>>(buf: &mut String, key: &str, value: Option<S>, protect: bool) {
+    if let Some(value) = value {
+        buf.push_str(&format!(
+            "\t\t\t\t<String>\n\t\t\t\t\t<Key>{}</Key>\n\t\t\t\t\t<Value{}>{}</Value>\n\t\t\t\t</String>\n",
+            escape(key),
+            if protect {
+                " ProtectInMemory=\"True\""
+            } else {
+                ""
+            },
+            escape(value.as_ref())
+        ));
+    }
+}
+
+/// A random UUID, base64-encoded as KeePass expects
+fn uuid<R: Rng>(random: &mut R) -> String {
+    let bytes: [u8; 16] = random.gen();
+    base64::encode(&bytes)
+}
+
+fn escape(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+        .replace('\'', "&apos;")
+}
+
+#[cfg(test)]
+mod tests {
+    use lib::export::keepass_xml::to_keepass_xml;
+    use lib::types::{Field, Record, Vault};
+
+    #[test]
+    fn escaping() {
+        let mut vault = Vault::default();
+        vault.passwords.insert(
+            "Tom & Jerry".to_string(),
+            Record::new("<\"hunter2\">".to_string(), None, None, None),
+        );
+
+        let xml = to_keepass_xml(&vault);
+        assert!(xml.contains("<Value>Tom &amp; Jerry</Value>"));
+        assert!(xml.contains("<Value ProtectInMemory=\"True\">&lt;&quot;hunter2&quot;&gt;</Value>"));
+        assert!(!xml.contains("UserName"));
+    }
+
+    #[test]
+    fn reserved_fields() {
+        let mut vault = Vault::default();
+        let mut record = Record::new("hunter2".to_string(), None, None, None);
+        record.fields.insert(
+            "Password".to_string(),
+            Field {
+                value: "old".to_string(),
+                secret: true,
+            },
+        );
+        vault.passwords.insert("service".to_string(), record);
+
+        let xml = to_keepass_xml(&vault);
+        assert_eq!(xml.matches("<Key>Password</Key>").count(), 1);
+        assert!(xml.contains("<Key>Password (custom)</Key>"));
+    }
+}
@@ -0,0 +1,62 @@
+// Copyright (C) 2018 Alberto Coscia
+//
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at http://mozilla.org/MPL/2.0/.
+
+pub mod csv;
+pub mod json;
+pub mod keepass_xml;
+pub mod pass;
+
+use lib::error::ExportError;
+use lib::types::{OtpRecord, Record, Vault};
+use std::collections::BTreeSet;
+use std::str::FromStr;
+
+/// The formats a vault can be exported to
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ExportFormat {
+    /// Bitwarden CSV
+    Csv,
+    /// Unencrypted Bitwarden JSON
+    Json,
+    /// KeePass 2 XML
+    KeepassXml,
+    /// A pass password store
+    Pass,
+}
+
+impl FromStr for ExportFormat {
+    type Err = ExportError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match &s.to_ascii_lowercase()[..] {
+            "csv" => Ok(ExportFormat::Csv),
+            "json" => Ok(ExportFormat::Json),
+            "keepass-xml" => Ok(ExportFormat::KeepassXml),
+            "pass" => Ok(ExportFormat::Pass),
+            _ => Err(ExportError::UnknownFormat),
+        }
+    }
+}
+
+/// A password and/or an OTP generator sharing the same record ID
+pub struct Entry<'a> {
+    pub id: &'a str,
+    pub record: Option<&'a Record>,
+    pub otp: Option<&'a OtpRecord>,
+}
+
+/// Pairs the passwords and OTP generators of a vault by record ID, sorted by
+/// record ID
+pub fn entries<'a>(vault: &'a Vault) -> Vec<Entry<'a>> {
+    let ids: BTreeSet<&String> = vault.passwords.keys().chain(vault.otps.keys()).collect();
+    ids.into_iter()
+        .map(|id| Entry {
+            id,
+            record: vault.passwords.get(id),
+            otp: vault.otps.get(id),
+        })
+        .collect()
+}
@@ -0,0 +1,130 @@
+// Copyright (C) 2018 Alberto Coscia
+//
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at http://mozilla.org/MPL/2.0/.
+
+use lib::export::Entry;
+use lib::otpauth;
+use std::path::{Component, Path, PathBuf};
+
+/// Serializes an entry to the contents of a
+/// [pass](https://www.passwordstore.org/) entry
+/**
+ * Blueprint
+ *  1. Write the password on the first line
+ *  2. Write the username, email, homepage and custom fields as `key: value`
+ *     lines
+ *  3. Write the OTP generator, if any, as an `otpauth://` URL as pass-otp
+ *     does
+ *  4. Append the notes
+ */
+pub fn to_entry(entry: &Entry) -> String {
+    let mut buf = String::new();
+
+    if let Some(record) = entry.record {
+        // (1)
+        buf += &record.password;
+        buf += "\n";
+
+        // (2)
+        for (key, value) in &[
+            ("login", &record.username),
+            ("email", &record.email),
+            ("url", &record.home),
+        ] {
+            if let Some(value) = value {
+                buf += &format!("{}: {}\n", key, value);
+            }
+        }
+        for (key, field) in &record.fields {
+            buf += &format!("{}: {}\n", key, field.value);
+        }
+    }
+
+    // (3)
+    if let Some(otp) = entry.otp {
+        buf += &otpauth::to_url(entry.id, otp);
+        buf += "\n";
+    }
+
+    // (4)
+    if let Some(notes) = entry.record.and_then(|r| r.notes.as_ref()) {
+        buf += "\n";
+        buf += notes;
+        buf += "\n";
+    }
+
+    buf
+}
+
+/// Returns the path of an entry in a password store, such as
+/// `work/aws/root.gpg` for "work/aws/root", or `None` if the record ID would
+/// escape the store
+pub fn entry_path(record_id: &str) -> Option<PathBuf> {
+    let path = Path::new(record_id);
+    let safe = path.components().all(|c| matches!(c, Component::Normal(_)));
+    if !safe || record_id.ends_with('/') {
+        return None;
+    }
+
+    Some(PathBuf::from(format!("{}.gpg", record_id)))
+}
+
+#[cfg(test)]
+mod tests {
+    use lib::export::pass::{entry_path, to_entry};
+    use lib::export::Entry;
+    use lib::import::pass::parse_entry;
+    use lib::types::{Field, HmacAlgorithm, OtpRecord, Record};
+    use std::path::PathBuf;
+
+    #[test]
+    fn round_trip() {
+        let mut record = Record::new(
+            "hunter2".to_string(),
+            Some("bob".to_string()),
+            None,
+            Some("https://service.tld".to_string()),
+        );
+        record.notes = Some("Recovery codes".to_string());
+        record.fields.insert(
+            "PIN".to_string(),
+            Field {
+                value: "1234".to_string(),
+                secret: true,
+            },
+        );
+        let otp = OtpRecord::new_totp(
+            "GEZDGNBVGY3TQOJQGEZDGNBVGY3TQOJQ".to_string(),
+            None,
+            HmacAlgorithm::SHA1,
+            6,
+            30,
+        );
+        let contents = to_entry(&Entry {
+            id: "service",
+            record: Some(&record),
+            otp: Some(&otp),
+        });
+        assert!(contents.contains("\nPIN: 1234\n"));
+
+        let (imported, otp) = parse_entry(&contents);
+        let imported = imported.unwrap();
+        assert_eq!(imported.password, "hunter2");
+        assert_eq!(imported.username.unwrap(), "bob");
+        assert_eq!(imported.home.unwrap(), "https://service.tld");
+        assert!(otp.unwrap().is_some());
+    }
+
+    #[test]
+    fn paths() {
+        assert_eq!(
+            entry_path("work/aws/root").unwrap(),
+            PathBuf::from("work/aws/root.gpg")
+        );
+        assert!(entry_path("../escape").is_none());
+        assert!(entry_path("/etc/passwd").is_none());
+        assert!(entry_path("folder/").is_none());
+    }
+}
@@ -0,0 +1,170 @@
+// Copyright (C) 2018 Alberto Coscia
+//
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at http://mozilla.org/MPL/2.0/.
+
+use failure::Error;
+use lib::import::{default_algorithm, default_digits, default_period, otp_record, Import};
+use lib::otpauth;
+use serde::de::IgnoredAny;
+use serde_json;
+use std::io::Read;
+
+#[derive(Deserialize)]
+struct Export {
+    db: Database,
+}
+
+/// Encrypted vaults store the database as a base64 string
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum Database {
+    Plain { entries: Vec<Entry> },
+    Encrypted(IgnoredAny),
+}
+
+#[derive(Deserialize)]
+struct Entry {
+    #[serde(rename = "type")]
+    kind: String,
+    #[serde(default)]
+    name: String,
+    #[serde(default)]
+    issuer: String,
+    info: Info,
+}
+
+#[derive(Deserialize)]
+struct Info {
+    secret: String,
+    #[serde(default = "default_algorithm")]
+    algo: String,
+    #[serde(default = "default_digits")]
+    digits: u32,
+    #[serde(default = "default_period")]
+    period: u64,
+    #[serde(default)]
+    counter: u64,
+}
+
+/// Reads the OTP generators of an unencrypted
+/// [Aegis](https://getaegis.app/) JSON export
+/**
+ * Blueprint
+ *  1. Deserialize the export, bail on error or if it is encrypted
+ *  2. Name every entry after its issuer and name, such as "issuer:account",
+ *     skip it if it has neither
+ *  3. Construct a `OtpRecord` from every entry, skip it on error or if its
+ *     type is not supported (e.g. Steam)
+ */
+pub fn read_export(source: &mut dyn Read) -> Result<Import, Error> {
+    tracepoint!();
+
+    // (1)
+    let export: Export = serde_json::from_reader(source)?;
+    let entries = match export.db {
+        Database::Plain { entries } => entries,
+        Database::Encrypted(_) => {
+            bail!("Encrypted Aegis exports are not supported, please export an unencrypted vault")
+        }
+    };
+
+    let mut import = Import::default();
+    for (i, entry) in entries.into_iter().enumerate() {
+        // (2)
+        let issuer = Some(entry.issuer.trim()).filter(|i| !i.is_empty());
+        if issuer.is_none() && entry.name.trim().is_empty() {
+            import.skipped.push((
+                format!("entry {}", i + 1),
+                "Entry has no name or issuer".to_string(),
+            ));
+            continue;
+        }
+        let record_id = otpauth::record_id(issuer, entry.name.trim());
+
+        // (3)
+        match otp_record(
+            &entry.kind,
+            &entry.info.secret,
+            issuer.map(|i| i.to_string()),
+            &entry.info.algo,
+            entry.info.digits,
+            entry.info.period,
+            entry.info.counter,
+        ) {
+            Ok(record) => import.otps.push((record_id, record)),
+            Err(err) => import.skipped.push((record_id, err.to_string())),
+        }
+    }
+
+    Ok(import)
+}
+
+#[cfg(test)]
+mod tests {
+    use lib::import::aegis::read_export;
+    use lib::types::{HmacAlgorithm, OtpRecord};
+
+    #[test]
+    fn export() {
+        let json = r#"{
+            "version": 1,
+            "header": { "slots": null, "params": null },
+            "db": {
+                "version": 2,
+                "entries": [
+                    {
+                        "type": "totp", "uuid": "1", "name": "bob@example.com",
+                        "issuer": "Service", "note": "", "icon": null,
+                        "info": {
+                            "secret": "GEZDGNBVGY3TQOJQGEZDGNBVGY3TQOJQ",
+                            "algo": "SHA256", "digits": 8, "period": 60
+                        }
+                    },
+                    {
+                        "type": "hotp", "uuid": "2", "name": "alice", "issuer": "",
+                        "info": {
+                            "secret": "GEZDGNBVGY3TQOJQGEZDGNBVGY3TQOJQ",
+                            "algo": "SHA1", "digits": 6, "counter": 7
+                        }
+                    },
+                    {
+                        "type": "steam", "uuid": "3", "name": "gamer", "issuer": "Steam",
+                        "info": {
+                            "secret": "GEZDGNBVGY3TQOJQGEZDGNBVGY3TQOJQ",
+                            "algo": "SHA1", "digits": 5, "period": 30
+                        }
+                    },
+                    {
+                        "type": "totp", "uuid": "4", "name": "", "issuer": "",
+                        "info": { "secret": "GEZDGNBVGY3TQOJQGEZDGNBVGY3TQOJQ" }
+                    }
+                ]
+            }
+        }"#;
+        let import = read_export(&mut json.as_bytes()).unwrap();
+
+        assert_eq!(import.otps.len(), 2);
+        assert_eq!(import.otps[0].0, "Service:bob@example.com");
+        match import.otps[0].1 {
+            OtpRecord::Totp {
+                algorithm: HmacAlgorithm::SHA256,
+                digits: 8,
+                period: 60,
+                ..
+            } => {}
+            _ => panic!("Expected a TOTP record"),
+        }
+        assert_eq!(import.otps[1].0, "alice");
+        match import.otps[1].1 {
+            OtpRecord::Hotp { counter: 7, .. } => {}
+            _ => panic!("Expected a HOTP record"),
+        }
+        assert_eq!(import.skipped[0].0, "Steam:gamer");
+        assert_eq!(import.skipped[1].0, "entry 4");
+
+        let encrypted = r#"{ "version": 1, "header": {}, "db": "c2VjcmV0" }"#;
+        assert!(read_export(&mut encrypted.as_bytes()).is_err());
+    }
+}
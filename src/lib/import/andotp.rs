@@ -0,0 +1,134 @@
+// Copyright (C) 2018 Alberto Coscia
+//
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at http://mozilla.org/MPL/2.0/.
+
+use failure::Error;
+use lib::import::{default_algorithm, default_digits, default_period, otp_record, Import};
+use lib::otpauth;
+use serde_json;
+use std::io::Read;
+
+#[derive(Deserialize)]
+struct Entry {
+    #[serde(rename = "type")]
+    kind: String,
+    secret: String,
+    #[serde(default)]
+    label: String,
+    #[serde(default)]
+    issuer: String,
+    #[serde(default = "default_algorithm")]
+    algorithm: String,
+    #[serde(default = "default_digits")]
+    digits: u32,
+    #[serde(default = "default_period")]
+    period: u64,
+    #[serde(default)]
+    counter: u64,
+}
+
+/// Reads the OTP generators of a plain-text
+/// [andOTP](https://github.com/andOTP/andOTP) JSON backup
+/**
+ * Blueprint
+ *  1. Deserialize the backup, bail on error
+ *  2. Name every entry after its issuer and label, such as "issuer:account",
+ *     skip it if it has neither
+ *  3. Construct a `OtpRecord` from every entry, skip it on error or if its
+ *     type is not supported (e.g. Steam)
+ */
+pub fn read_backup(source: &mut dyn Read) -> Result<Import, Error> {
+    tracepoint!();
+
+    // (1)
+    let entries: Vec<Entry> = serde_json::from_reader(source).map_err(|e| {
+        format_err!(
+            "Failed to read the andOTP backup, encrypted backups are not supported ({})",
+            e
+        )
+    })?;
+
+    let mut import = Import::default();
+    for (i, entry) in entries.into_iter().enumerate() {
+        // (2)
+        let issuer = Some(entry.issuer.trim()).filter(|i| !i.is_empty());
+        if issuer.is_none() && entry.label.trim().is_empty() {
+            import.skipped.push((
+                format!("entry {}", i + 1),
+                "Entry has no name or issuer".to_string(),
+            ));
+            continue;
+        }
+        let record_id = otpauth::record_id(issuer, entry.label.trim());
+
+        // (3)
+        match otp_record(
+            &entry.kind,
+            &entry.secret,
+            issuer.map(|i| i.to_string()),
+            &entry.algorithm,
+            entry.digits,
+            entry.period,
+            entry.counter,
+        ) {
+            Ok(record) => import.otps.push((record_id, record)),
+            Err(err) => import.skipped.push((record_id, err.to_string())),
+        }
+    }
+
+    Ok(import)
+}
+
+#[cfg(test)]
+mod tests {
+    use lib::import::andotp::read_backup;
+    use lib::types::{HmacAlgorithm, OtpRecord};
+
+    #[test]
+    fn backup() {
+        let json = r#"[
+            {
+                "secret": "GEZDGNBVGY3TQOJQGEZDGNBVGY3TQOJQ", "issuer": "Service",
+                "label": "bob", "digits": 6, "type": "TOTP", "algorithm": "SHA512",
+                "thumbnail": "Default", "last_used": 0, "used_frequency": 0,
+                "period": 30, "tags": []
+            },
+            {
+                "secret": "GEZDGNBVGY3TQOJQGEZDGNBVGY3TQOJQ", "issuer": "",
+                "label": "alice", "digits": 8, "type": "HOTP", "algorithm": "SHA1",
+                "counter": 3, "tags": []
+            },
+            {
+                "secret": "GEZDGNBVGY3TQOJQGEZDGNBVGY3TQOJQ", "issuer": "Service",
+                "label": "md5", "digits": 6, "type": "TOTP", "algorithm": "MD5",
+                "period": 30, "tags": []
+            },
+            { "secret": "GEZDGNBVGY3TQOJQGEZDGNBVGY3TQOJQ", "type": "TOTP", "tags": [] }
+        ]"#;
+        let import = read_backup(&mut json.as_bytes()).unwrap();
+
+        assert_eq!(import.otps.len(), 2);
+        assert_eq!(import.otps[0].0, "Service:bob");
+        match import.otps[0].1 {
+            OtpRecord::Totp {
+                algorithm: HmacAlgorithm::SHA512,
+                ..
+            } => {}
+            _ => panic!("Expected a TOTP record"),
+        }
+        match import.otps[1].1 {
+            OtpRecord::Hotp {
+                digits: 8,
+                counter: 3,
+                ..
+            } => {}
+            _ => panic!("Expected a HOTP record"),
+        }
+        assert_eq!(import.skipped[0].0, "Service:md5");
+        assert_eq!(import.skipped[1].0, "entry 4");
+
+        assert!(read_backup(&mut "not json".as_bytes()).is_err());
+    }
+}
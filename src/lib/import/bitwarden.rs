@@ -0,0 +1,250 @@
+// Copyright (C) 2018 Alberto Coscia
+//
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at http://mozilla.org/MPL/2.0/.
+
+use chrono::{DateTime, Utc};
+use failure::Error;
+use lib::import::{parse_totp, Import};
+use lib::types::{Field, Record};
+use serde_json;
+use std::io::Read;
+
+#[derive(Deserialize)]
+struct Export {
+    #[serde(default)]
+    encrypted: bool,
+    #[serde(default)]
+    items: Vec<Item>,
+}
+
+#[derive(Deserialize)]
+struct Item {
+    #[serde(rename = "type")]
+    kind: u8,
+    name: Option<String>,
+    notes: Option<String>,
+    #[serde(default)]
+    fields: Option<Vec<ItemField>>,
+    login: Option<Login>,
+    #[serde(rename = "creationDate")]
+    creation_date: Option<DateTime<Utc>>,
+    #[serde(rename = "revisionDate")]
+    revision_date: Option<DateTime<Utc>>,
+}
+
+#[derive(Deserialize)]
+struct ItemField {
+    name: Option<String>,
+    value: Option<String>,
+    #[serde(rename = "type")]
+    kind: u8,
+}
+
+#[derive(Deserialize)]
+struct Login {
+    #[serde(default)]
+    uris: Option<Vec<Uri>>,
+    username: Option<String>,
+    password: Option<String>,
+    totp: Option<String>,
+}
+
+#[derive(Deserialize)]
+struct Uri {
+    uri: Option<String>,
+}
+
+/// The Bitwarden item type of logins, as opposed to secure notes, cards and
+/// identities
+const LOGIN: u8 = 1;
+
+/// The Bitwarden custom field types of plain text and hidden fields
+pub const TEXT: u8 = 0;
+pub const HIDDEN: u8 = 1;
+/// Boolean fields are imported as plain text ones, linked ones are skipped
+const BOOLEAN: u8 = 2;
+
+/// Reads the logins of an unencrypted Bitwarden JSON export
+/**
+ * Blueprint
+ *  1. Deserialize the export, bail on error or if it is encrypted
+ *  2. Skip items that are not logins or have no name
+ *  3. Construct a `Record` from the password, the first URI, the username,
+ *     the notes and the creation and revision dates, unless there is no
+ *     password
+ *      i) Custom fields: an "email" text field is used as the email, hidden
+ *         fields are secret
+ *  4. Construct a `OtpRecord` from the TOTP field and the creation and
+ *     revision dates, skip only the TOTP on error
+ */
+pub fn read_export(source: &mut dyn Read) -> Result<Import, Error> {
+    tracepoint!();
+
+    // (1)
+    let export: Export = serde_json::from_reader(source)?;
+    ensure!(
+        !export.encrypted,
+        "Encrypted Bitwarden exports are not supported, please export as unencrypted JSON"
+    );
+
+    let mut import = Import::default();
+    for (i, item) in export.items.into_iter().enumerate() {
+        // (2)
+        let record_id = match item.name.as_ref().map(|n| n.trim()) {
+            Some(name) if !name.is_empty() => name.to_string(),
+            _ => {
+                import
+                    .skipped
+                    .push((format!("item {}", i + 1), "Item has no name".to_string()));
+                continue;
+            }
+        };
+        let login = match item.login {
+            Some(ref login) if item.kind == LOGIN => login,
+            _ => {
+                import.skipped.push((record_id, "Not a login".to_string()));
+                continue;
+            }
+        };
+
+        // (3)
+        let record = login.password.as_ref().filter(|p| !p.is_empty()).map(|p| {
+            let home = login
+                .uris
+                .iter()
+                .flatten()
+                .filter_map(|u| u.uri.clone())
+                .next();
+            let mut record = Record::new(p.to_string(), login.username.clone(), None, home);
+            record.notes = item.notes.clone().filter(|n| !n.is_empty());
+            record.timestamps.created = item.creation_date;
+            record.timestamps.modified = item.revision_date;
+
+            // (3.i)
+            for field in item.fields.iter().flatten() {
+                let (key, value) = match (&field.name, &field.value) {
+                    (Some(key), Some(value)) if !key.trim().is_empty() => (key.trim(), value),
+                    _ => continue,
+                };
+                match field.kind {
+                    TEXT if key == "email" && record.email.is_none() => {
+                        record.email = Some(value.clone())
+                    }
+                    TEXT | HIDDEN | BOOLEAN => {
+                        record.fields.insert(
+                            key.to_string(),
+                            Field {
+                                value: value.clone(),
+                                secret: field.kind == HIDDEN,
+                            },
+                        );
+                    }
+                    _ => {}
+                }
+            }
+            record
+        });
+
+        // (4)
+        let totp = login.totp.as_ref().filter(|t| !t.trim().is_empty());
+        let otp = match totp {
+            Some(totp) => match parse_totp(totp) {
+                Ok(mut otp) => {
+                    otp.timestamps_mut().created = item.creation_date;
+                    otp.timestamps_mut().modified = item.revision_date;
+                    Some(otp)
+                }
+                Err(err) => {
+                    import.skip_totp(&record_id, &err);
+                    None
+                }
+            },
+            None => None,
+        };
+
+        match (record, otp) {
+            (None, None) if totp.is_none() => import
+                .skipped
+                .push((record_id, "No password or TOTP".to_string())),
+            (record, otp) => {
+                if let Some(record) = record {
+                    import.passwords.push((record_id.clone(), record));
+                }
+                if let Some(otp) = otp {
+                    import.otps.push((record_id, otp));
+                }
+            }
+        }
+    }
+
+    Ok(import)
+}
+
+#[cfg(test)]
+mod tests {
+    use lib::import::bitwarden::read_export;
+    use lib::types::OtpRecord;
+
+    #[test]
+    fn export() {
+        let json = r#"{
+            "encrypted": false,
+            "folders": [],
+            "items": [
+                {
+                    "id": "1", "folderId": null, "type": 1, "name": "Service",
+                    "creationDate": "2018-07-22T10:00:00.000Z",
+                    "revisionDate": "2018-08-01T10:00:00.000Z",
+                    "notes": "Recovery codes", "favorite": false,
+                    "fields": [
+                        { "name": "PIN", "value": "1234", "type": 1 },
+                        { "name": "Key ID", "value": "AKIA", "type": 0 },
+                        { "name": "Linked", "value": null, "type": 3, "linkedId": 100 }
+                    ],
+                    "login": {
+                        "uris": [{ "match": null, "uri": "https://service.tld" }],
+                        "username": "bob", "password": "hunter2",
+                        "totp": "otpauth://totp/bob?secret=GEZDGNBVGY3TQOJQGEZDGNBVGY3TQOJQ&digits=8"
+                    }
+                },
+                { "id": "2", "type": 2, "name": "Note", "secureNote": { "type": 0 } },
+                {
+                    "id": "3", "type": 1, "name": "Broken",
+                    "login": { "password": "hunter2", "totp": "not base32" }
+                }
+            ]
+        }"#;
+        let import = read_export(&mut json.as_bytes()).unwrap();
+
+        assert_eq!(import.passwords.len(), 2);
+        let (ref record_id, ref record) = import.passwords[0];
+        assert_eq!(record_id, "Service");
+        assert_eq!(record.username.as_ref().unwrap(), "bob");
+        assert_eq!(record.home.as_ref().unwrap(), "https://service.tld");
+        assert_eq!(record.notes.as_ref().unwrap(), "Recovery codes");
+        assert_eq!(
+            record.timestamps.created.unwrap().to_rfc3339(),
+            "2018-07-22T10:00:00+00:00"
+        );
+        assert_eq!(
+            record.timestamps.modified.unwrap().to_rfc3339(),
+            "2018-08-01T10:00:00+00:00"
+        );
+        assert_eq!(record.fields.len(), 2);
+        assert!(record.fields["PIN"].secret);
+        assert!(!record.fields["Key ID"].secret);
+        match import.otps[0].1 {
+            OtpRecord::Totp { digits: 8, .. } => {}
+            _ => panic!("Expected a TOTP record"),
+        }
+        assert_eq!(import.otps.len(), 1);
+        // The password of an item with an invalid TOTP is still imported
+        assert_eq!(import.passwords[1].0, "Broken");
+        assert_eq!(import.skipped.len(), 2);
+        assert_eq!(import.skipped[1].0, "TOTP of Broken");
+
+        assert!(read_export(&mut r#"{ "encrypted": true, "items": [] }"#.as_bytes()).is_err());
+    }
+}
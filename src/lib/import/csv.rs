@@ -0,0 +1,327 @@
+// Copyright (C) 2018 Alberto Coscia
+//
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at http://mozilla.org/MPL/2.0/.
+
+use csv::ReaderBuilder;
+use failure::Error;
+use lib::error::ImportError;
+use lib::import::{parse_totp, Import};
+use lib::types::Record;
+use std::io::Read;
+use std::str::FromStr;
+use url::Url;
+
+/// The password managers whose CSV exports have a built-in column mapping
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum CsvFormat {
+    Firefox,
+    Chrome,
+    Bitwarden,
+    OnePassword,
+    Lastpass,
+}
+
+impl FromStr for CsvFormat {
+    type Err = ImportError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match &s.to_ascii_lowercase()[..] {
+            "firefox" => Ok(CsvFormat::Firefox),
+            "chrome" => Ok(CsvFormat::Chrome),
+            "bitwarden" => Ok(CsvFormat::Bitwarden),
+            "1password" => Ok(CsvFormat::OnePassword),
+            "lastpass" => Ok(CsvFormat::Lastpass),
+            _ => Err(ImportError::UnknownCsvFormat),
+        }
+    }
+}
+
+/// Which CSV column, if any, holds each field of a record
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct Mapping {
+    /// The record ID. Defaults to the host of the URL
+    pub name: Option<String>,
+    pub url: Option<String>,
+    pub username: Option<String>,
+    pub email: Option<String>,
+    pub password: Option<String>,
+    pub notes: Option<String>,
+    /// An `otpauth://` URL or a base32 TOTP secret
+    pub totp: Option<String>,
+    /// Rows are only imported if this column, when present, reads "login"
+    pub kind: Option<String>,
+}
+
+impl Mapping {
+    /// Returns the column mapping of a known CSV export
+    pub fn preset(format: CsvFormat) -> Mapping {
+        let column = |c: &str| Some(c.to_string());
+        match format {
+            CsvFormat::Firefox => Mapping {
+                url: column("url"),
+                username: column("username"),
+                password: column("password"),
+                ..Mapping::default()
+            },
+            CsvFormat::Chrome => Mapping {
+                name: column("name"),
+                url: column("url"),
+                username: column("username"),
+                password: column("password"),
+                notes: column("note"),
+                ..Mapping::default()
+            },
+            CsvFormat::Bitwarden => Mapping {
+                name: column("name"),
+                url: column("login_uri"),
+                username: column("login_username"),
+                password: column("login_password"),
+                notes: column("notes"),
+                totp: column("login_totp"),
+                kind: column("type"),
+                ..Mapping::default()
+            },
+            CsvFormat::OnePassword => Mapping {
+                name: column("title"),
+                url: column("url"),
+                username: column("username"),
+                password: column("password"),
+                notes: column("notes"),
+                totp: column("otpauth"),
+                ..Mapping::default()
+            },
+            CsvFormat::Lastpass => Mapping {
+                name: column("name"),
+                url: column("url"),
+                username: column("username"),
+                password: column("password"),
+                notes: column("extra"),
+                totp: column("totp"),
+                ..Mapping::default()
+            },
+        }
+    }
+
+    /// Overrides columns using a "field=column,..." specification, such as
+    /// "name=Title,password=Secret"
+    pub fn apply_spec(&mut self, spec: &str) -> Result<(), ImportError> {
+        for pair in spec.split(',').filter(|p| !p.trim().is_empty()) {
+            let invalid = || ImportError::InvalidMapping(pair.trim().to_string());
+            let i = pair.find('=').ok_or_else(invalid)?;
+            let column = pair[i + 1..].trim();
+            if column.is_empty() {
+                return Err(invalid());
+            }
+            let column = Some(column.to_string());
+            match &pair[..i].trim().to_ascii_lowercase()[..] {
+                "name" => self.name = column,
+                "url" => self.url = column,
+                "username" => self.username = column,
+                "email" => self.email = column,
+                "password" => self.password = column,
+                "notes" => self.notes = column,
+                "totp" => self.totp = column,
+                _ => return Err(invalid()),
+            }
+        }
+
+        Ok(())
+    }
+}
+
+/// Reads the rows of a CSV file with a header row according to a `Mapping`
+/**
+ * Blueprint
+ *  1. Read the header row and find the mapped columns, ignoring their case.
+ *     Bail if there is no password column or no way to name records
+ *  2. Read every row, bail on malformed CSV
+ *      i) Skip rows that are not logins
+ *      ii) Name the record after the name column or the host of its URL
+ *          and its username, such as "example.com/bob", skip the row if
+ *          neither is available
+ *      iii) Construct a `Record` and/or a TOTP `OtpRecord`, skip the row if
+ *           it's empty and only the TOTP if it's invalid
+ */
+pub fn read_csv(source: &mut dyn Read, mapping: &Mapping) -> Result<Import, Error> {
+    tracepoint!();
+    let mut reader = ReaderBuilder::new().flexible(true).from_reader(source);
+
+    // (1)
+    let headers = reader.headers()?.clone();
+    let find = |column: &Option<String>| {
+        column.as_ref().and_then(|c| {
+            headers
+                .iter()
+                .position(|h| h.trim().eq_ignore_ascii_case(c.trim()))
+        })
+    };
+    let password = find(&mapping.password).ok_or_else(|| {
+        ImportError::MissingColumn(
+            mapping
+                .password
+                .clone()
+                .unwrap_or_else(|| "password".into()),
+        )
+    })?;
+    let name = find(&mapping.name);
+    let url = find(&mapping.url);
+    ensure!(
+        name.is_some() || url.is_some(),
+        ImportError::MissingColumn(mapping.name.clone().unwrap_or_else(|| "name".into()))
+    );
+    let username = find(&mapping.username);
+    let email = find(&mapping.email);
+    let notes = find(&mapping.notes);
+    let totp = find(&mapping.totp);
+    let kind = find(&mapping.kind);
+
+    // (2)
+    let mut import = Import::default();
+    for (i, row) in reader.records().enumerate() {
+        let row = row?;
+        let field = |column: Option<usize>| {
+            column
+                .and_then(|c| row.get(c))
+                .map(|v| v.trim())
+                .filter(|v| !v.is_empty())
+                .map(|v| v.to_string())
+        };
+        // Header is line 1
+        let line = format!("line {}", i + 2);
+
+        // (2.i)
+        if let Some(kind) = field(kind) {
+            if kind != "login" {
+                import
+                    .skipped
+                    .push((line, format!("Not a login ({})", kind)));
+                continue;
+            }
+        }
+
+        // (2.ii)
+        let home = field(url);
+        // Browsers store one login per site and username
+        let from_url = || {
+            let host = home.as_ref().and_then(|u| host(u))?;
+            Some(match field(username) {
+                Some(username) => format!("{}/{}", host, username),
+                None => host,
+            })
+        };
+        let record_id = match field(name).or_else(from_url) {
+            Some(record_id) => record_id,
+            None => {
+                import.skipped.push((line, "No name or URL".to_string()));
+                continue;
+            }
+        };
+
+        // (2.iii)
+        // Passwords are not trimmed, whitespace may be part of them
+        let record = row.get(password).filter(|p| !p.is_empty()).map(|p| {
+            let mut record = Record::new(p.to_string(), field(username), field(email), home);
+            record.notes = field(notes);
+            record
+        });
+        let otp = match field(totp).map(|totp| parse_totp(&totp)) {
+            Some(Ok(otp)) => Some(otp),
+            Some(Err(err)) => {
+                import.skip_totp(&record_id, &err);
+                None
+            }
+            None => None,
+        };
+        match (record, otp) {
+            (None, None) if field(totp).is_none() => import
+                .skipped
+                .push((record_id, "No password or TOTP".to_string())),
+            (record, otp) => {
+                if let Some(record) = record {
+                    import.passwords.push((record_id.clone(), record));
+                }
+                if let Some(otp) = otp {
+                    import.otps.push((record_id, otp));
+                }
+            }
+        }
+    }
+
+    Ok(import)
+}
+
+/// Returns the host of a URL, such as "example.com" for
+/// "https://www.example.com/login"
+fn host(url: &str) -> Option<String> {
+    let host = Url::parse(url).ok()?.host_str()?.to_ascii_lowercase();
+    Some(host.trim_start_matches("www.").to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use lib::import::csv::{read_csv, CsvFormat, Mapping};
+    use lib::types::OtpRecord;
+
+    #[test]
+    fn firefox() {
+        let csv = "\"url\",\"username\",\"password\",\"httpRealm\",\"formActionOrigin\",\"guid\"\n\
+                   \"https://www.example.com\",\"bob\",\"hunter2\",,\"\",\"{1}\"\n\
+                   \"https://service.tld\",\"alice\",\"\",,\"\",\"{2}\"\n";
+        let import = read_csv(&mut csv.as_bytes(), &Mapping::preset(CsvFormat::Firefox)).unwrap();
+
+        assert_eq!(import.passwords.len(), 1);
+        let (ref record_id, ref record) = import.passwords[0];
+        assert_eq!(record_id, "example.com/bob");
+        assert_eq!(record.password, "hunter2");
+        assert_eq!(record.username.as_ref().unwrap(), "bob");
+        assert_eq!(record.home.as_ref().unwrap(), "https://www.example.com");
+        assert_eq!(import.skipped[0].0, "service.tld/alice");
+    }
+
+    #[test]
+    fn bitwarden() {
+        let csv = "folder,favorite,type,name,notes,fields,reprompt,login_uri,login_username,login_password,login_totp\n\
+                   ,,login,Service,\"Line 1\nLine 2\",,0,https://service.tld,bob,hunter2,GEZDGNBVGY3TQOJQGEZDGNBVGY3TQOJQ\n\
+                   ,,note,Recovery codes,12345,,0,,,,\n\
+                   ,,login,Broken,,,0,,alice,hunter3,not base32!\n";
+        let import = read_csv(&mut csv.as_bytes(), &Mapping::preset(CsvFormat::Bitwarden)).unwrap();
+
+        assert_eq!(import.passwords[0].0, "Service");
+        assert_eq!(
+            import.passwords[0].1.notes.as_ref().unwrap(),
+            "Line 1\nLine 2"
+        );
+        match import.otps[0] {
+            (ref record_id, OtpRecord::Totp { period: 30, .. }) => assert_eq!(record_id, "Service"),
+            _ => panic!("Expected a TOTP record"),
+        }
+        // Only the invalid TOTP is skipped
+        assert_eq!(import.passwords[1].0, "Broken");
+        assert_eq!(import.otps.len(), 1);
+        assert_eq!(import.skipped.len(), 2);
+        assert_eq!(import.skipped[1].0, "TOTP of Broken");
+    }
+
+    #[test]
+    fn custom_mapping() {
+        let mut mapping = Mapping::default();
+        mapping
+            .apply_spec("name=Account, password=Secret, email=Mail")
+            .unwrap();
+        let csv = "Account,Mail,Secret\nservice,bob@example.com,hunter2\n";
+        let import = read_csv(&mut csv.as_bytes(), &mapping).unwrap();
+        assert_eq!(import.passwords[0].0, "service");
+        assert_eq!(
+            import.passwords[0].1.email.as_ref().unwrap(),
+            "bob@example.com"
+        );
+
+        assert!(mapping.apply_spec("colour=Red").is_err());
+        assert!(mapping.apply_spec("name").is_err());
+        assert!(mapping.apply_spec("name=").is_err());
+        // No password column
+        assert!(read_csv(&mut "Account,Mail\n".as_bytes(), &mapping).is_err());
+    }
+}
@@ -0,0 +1,310 @@
+// Copyright (C) 2018 Alberto Coscia
+//
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at http://mozilla.org/MPL/2.0/.
+
+use base32;
+use base64;
+use failure::Error;
+use keepass::{Database, NodeRef};
+use lib::import::{self, Import};
+use lib::otp;
+use lib::otpauth;
+use lib::types::{OtpRecord, Record};
+use std::io::Read;
+use std::str::FromStr;
+
+/// Reads every entry of a KDBX 3.1 or 4 database
+/**
+ * Blueprint
+ *  1. Open the database using its master password and/or key file, bail on
+ *     error
+ *  2. Walk every group and entry of the database
+ *  3. Parse every entry to a `Record` and/or an `OtpRecord` named after its
+ *     title, skip it if it's untitled or empty and only skip its OTP
+ *     settings if they are invalid
+ */
+pub fn read_database(
+    source: &mut dyn Read,
+    password: Option<&str>,
+    keyfile: Option<&mut dyn Read>,
+) -> Result<Import, Error> {
+    tracepoint!();
+
+    // (1)
+    let db = Database::open(source, password, keyfile)
+        .map_err(|e| format_err!("Failed to open the KeePass database ({})", e))?;
+
+    // (2)
+    let mut import = Import::default();
+    for node in &db.root {
+        if let NodeRef::Entry(entry) = node {
+            // (3)
+            let title = match entry.get_title().map(str::trim) {
+                Some(title) if !title.is_empty() => title.to_string(),
+                _ => {
+                    import
+                        .skipped
+                        .push(("(untitled)".to_string(), "Entry has no title".to_string()));
+                    continue;
+                }
+            };
+
+            let (record, otp) = parse_entry(|key| entry.get(key).map(|v| v.to_string()));
+            let otp = match otp {
+                Ok(otp) => otp,
+                Err(err) => {
+                    import.skip_totp(&title, &err);
+                    if record.is_none() {
+                        continue;
+                    }
+                    None
+                }
+            };
+            match (record, otp) {
+                (None, None) => import
+                    .skipped
+                    .push((title, "No password or TOTP".to_string())),
+                (record, otp) => {
+                    if let Some(record) = record {
+                        import.passwords.push((title.clone(), record));
+                    }
+                    if let Some(otp) = otp {
+                        import.otps.push((title, otp));
+                    }
+                }
+            }
+        }
+    }
+
+    Ok(import)
+}
+
+/// Parses the fields of a KeePass entry to a `Record` and/or an `OtpRecord`
+/**
+ * Blueprint
+ *  1. Construct a `Record` from the standard fields, unless there is no
+ *     password
+ *      i) URL: `home`
+ *      ii) UserName: `username`
+ *      iii) Notes: `notes`
+ *  2. Construct a `OtpRecord` from the first supported OTP field set,
+ *     return the error alongside the `Record` if it's invalid
+ *      i) `otp`: an `otpauth://` URL, as written by KeePassXC
+ *      ii) `TimeOtp-*`: TOTP settings, as written by KeePass 2.47+
+ *      iii) `HmacOtp-*`: HOTP settings, as written by KeePass 2.47+
+ */
+pub fn parse_entry<F>(get: F) -> (Option<Record>, Result<Option<OtpRecord>, Error>)
+where
+    F: Fn(&str) -> Option<String>,
+{
+    tracepoint!();
+    let field = |key: &str| get(key).filter(|v| !v.trim().is_empty());
+
+    // (1)
+    let record = field("Password").map(|password| {
+        let mut record = Record::new(password, field("UserName"), None, field("URL"));
+        record.notes = field("Notes");
+        record
+    });
+
+    // (2)
+    (record, parse_otp(&field))
+}
+
+fn parse_otp<F>(field: &F) -> Result<Option<OtpRecord>, Error>
+where
+    F: Fn(&str) -> Option<String>,
+{
+    let otp = if let Some(url) = field("otp") {
+        // (2.i)
+        Some(otpauth::parse_url(url.trim())?.1)
+    } else if let Some(secret) = otp_secret(field, "TimeOtp")? {
+        // (2.ii)
+        let algorithm = match field("TimeOtp-Algorithm") {
+            Some(a) => parse_algorithm(&a),
+            None => import::default_algorithm(),
+        };
+        let digits = parse_number(field("TimeOtp-Length"), 6, "TimeOtp-Length")?;
+        let period = parse_number(field("TimeOtp-Period"), 30, "TimeOtp-Period")?;
+        Some(import::otp_record(
+            "totp", &secret, None, &algorithm, digits, period, 0,
+        )?)
+    } else if let Some(secret) = otp_secret(field, "HmacOtp")? {
+        // (2.iii)
+        let counter = parse_number(field("HmacOtp-Counter"), 0, "HmacOtp-Counter")?;
+        Some(import::otp_record(
+            "hotp",
+            &secret,
+            None,
+            &import::default_algorithm(),
+            import::default_digits(),
+            import::default_period(),
+            counter,
+        )?)
+    } else {
+        None
+    };
+
+    Ok(otp)
+}
+
+/// Reads a KeePass OTP secret, which may be stored as base32, base64 or hex,
+/// to a base32 string
+fn otp_secret<F>(field: &F, prefix: &str) -> Result<Option<String>, Error>
+where
+    F: Fn(&str) -> Option<String>,
+{
+    let invalid = || format_err!("{}-Secret is not valid", prefix);
+    let bytes = if let Some(secret) = field(&format!("{}-Secret-Base32", prefix)) {
+        return Ok(Some(otp::normalize_secret(&secret)?));
+    } else if let Some(secret) = field(&format!("{}-Secret-Base64", prefix)) {
+        base64::decode(secret.trim()).map_err(|_| invalid())?
+    } else if let Some(secret) = field(&format!("{}-Secret-Hex", prefix)) {
+        let secret: String = secret.split_whitespace().collect();
+        ensure!(secret.len() % 2 == 0, invalid());
+        (0..secret.len())
+            .step_by(2)
+            .map(|i| u8::from_str_radix(&secret[i..i + 2], 16))
+            .collect::<Result<Vec<u8>, _>>()
+            .map_err(|_| invalid())?
+    } else if let Some(secret) = field(&format!("{}-Secret", prefix)) {
+        secret.into_bytes()
+    } else {
+        return Ok(None);
+    };
+    ensure!(!bytes.is_empty(), invalid());
+
+    Ok(Some(base32::encode(
+        base32::Alphabet::RFC4648 { padding: false },
+        &bytes,
+    )))
+}
+
+/// Converts a KeePass HMAC algorithm name, such as "HMAC-SHA-256", to the
+/// name `otp_record` expects
+fn parse_algorithm(algorithm: &str) -> String {
+    let algorithm = algorithm.trim().to_ascii_uppercase().replace('-', "");
+    algorithm.trim_start_matches("HMAC").to_string()
+}
+
+fn parse_number<T: FromStr>(value: Option<String>, default: T, name: &str) -> Result<T, Error> {
+    match value {
+        Some(v) => v
+            .trim()
+            .parse()
+            .map_err(|_| format_err!("{} is not a valid number", name)),
+        None => Ok(default),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use lib::import::keepass::parse_entry;
+    use lib::types::{HmacAlgorithm, OtpRecord};
+    use std::collections::HashMap;
+
+    fn entry(fields: &[(&str, &str)]) -> HashMap<String, String> {
+        fields
+            .iter()
+            .map(|(k, v)| (k.to_string(), v.to_string()))
+            .collect()
+    }
+
+    #[test]
+    fn entry_fields() {
+        let fields = entry(&[
+            ("Title", "service"),
+            ("UserName", "bob"),
+            ("Password", "hunter2"),
+            ("URL", "https://service.tld"),
+            ("Notes", "Security questions\nPet: none"),
+        ]);
+        let (record, otp) = parse_entry(|k| fields.get(k).cloned());
+        let record = record.unwrap();
+        assert_eq!(record.password, "hunter2");
+        assert_eq!(record.username.unwrap(), "bob");
+        assert_eq!(record.home.unwrap(), "https://service.tld");
+        assert_eq!(record.notes.unwrap(), "Security questions\nPet: none");
+        assert!(otp.unwrap().is_none());
+
+        let fields = entry(&[("Title", "service"), ("Password", "")]);
+        let (record, otp) = parse_entry(|k| fields.get(k).cloned());
+        assert!(record.is_none() && otp.unwrap().is_none());
+    }
+
+    #[test]
+    fn entry_otp() {
+        // KeePassXC
+        let fields = entry(&[(
+            "otp",
+            "otpauth://totp/bob?secret=GEZDGNBVGY3TQOJQGEZDGNBVGY3TQOJQ&issuer=service",
+        )]);
+        let (record, otp) = parse_entry(|k| fields.get(k).cloned());
+        assert!(record.is_none());
+        match otp.unwrap().unwrap() {
+            OtpRecord::Totp { issuer, .. } => assert_eq!(issuer.unwrap(), "service"),
+            _ => panic!("Expected a TOTP record"),
+        }
+
+        // KeePass 2.47+
+        let fields = entry(&[
+            ("Password", "hunter2"),
+            (
+                "TimeOtp-Secret-Hex",
+                "3132333435363738393031323334353637383930",
+            ),
+            ("TimeOtp-Algorithm", "HMAC-SHA-256"),
+            ("TimeOtp-Length", "8"),
+            ("TimeOtp-Period", "60"),
+        ]);
+        match parse_entry(|k| fields.get(k).cloned()).1.unwrap().unwrap() {
+            OtpRecord::Totp {
+                secret,
+                algorithm: HmacAlgorithm::SHA256,
+                digits: 8,
+                period: 60,
+                ..
+            } => assert_eq!(secret, "GEZDGNBVGY3TQOJQGEZDGNBVGY3TQOJQ"),
+            _ => panic!("Expected a TOTP record"),
+        }
+
+        let fields = entry(&[
+            (
+                "HmacOtp-Secret-Base32",
+                "gezd gnbv gy3t qojq gezd gnbv gy3t qojq",
+            ),
+            ("HmacOtp-Counter", "3"),
+        ]);
+        match parse_entry(|k| fields.get(k).cloned()).1.unwrap().unwrap() {
+            OtpRecord::Hotp { counter: 3, .. } => {}
+            _ => panic!("Expected a HOTP record"),
+        }
+
+        // Invalid OTP settings don't prevent importing the password
+        let fields = entry(&[("Password", "hunter2"), ("TimeOtp-Secret-Base32", "0")]);
+        let (record, otp) = parse_entry(|k| fields.get(k).cloned());
+        assert!(record.is_some() && otp.is_err());
+        let fields = entry(&[
+            ("TimeOtp-Secret", "12345678901234567890"),
+            ("TimeOtp-Algorithm", "HMAC-MD5"),
+        ]);
+        assert!(parse_entry(|k| fields.get(k).cloned()).1.is_err());
+
+        // Unsupported or out of range settings
+        for (key, value) in &[
+            ("TimeOtp-Length", "10"),
+            ("TimeOtp-Length", "4294967302"),
+            ("TimeOtp-Period", "0"),
+        ] {
+            let fields = entry(&[
+                ("Password", "hunter2"),
+                ("TimeOtp-Secret-Base32", "GEZDGNBVGY3TQOJQGEZDGNBVGY3TQOJQ"),
+                (key, value),
+            ]);
+            let (record, otp) = parse_entry(|k| fields.get(k).cloned());
+            assert!(record.is_some() && otp.is_err());
+        }
+    }
+}
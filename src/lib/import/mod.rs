@@ -0,0 +1,105 @@
+// Copyright (C) 2018 Alberto Coscia
+//
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at http://mozilla.org/MPL/2.0/.
+
+pub mod aegis;
+pub mod andotp;
+pub mod bitwarden;
+pub mod csv;
+pub mod keepass;
+pub mod pass;
+
+use failure::Error;
+use lib::error::UrlError;
+use lib::types::{HmacAlgorithm, OtpRecord, Record};
+use lib::{otp, otpauth};
+
+/// Records read from another password manager, ready to be added to a vault
+#[derive(Default)]
+pub struct Import {
+    pub passwords: Vec<(String, Record)>,
+    pub otps: Vec<(String, OtpRecord)>,
+    /// Entries that could not be imported, alongside the reason why
+    pub skipped: Vec<(String, String)>,
+}
+
+impl Import {
+    /// Reports a TOTP field that could not be parsed, while the password of
+    /// the same entry is still imported
+    pub fn skip_totp(&mut self, record_id: &str, err: &Error) {
+        self.skipped
+            .push((format!("TOTP of {}", record_id), err.to_string()));
+    }
+}
+
+/// Parses a TOTP field as found in password manager exports, which is either
+/// an `otpauth://` URL or a bare base32 secret using the default settings
+pub fn parse_totp(totp: &str) -> Result<OtpRecord, Error> {
+    let totp = totp.trim();
+    if totp.starts_with("otpauth://") {
+        Ok(otpauth::parse_url(totp)?.1)
+    } else {
+        Ok(OtpRecord::new_totp(
+            otp::normalize_secret(totp)?,
+            None,
+            HmacAlgorithm::SHA1,
+            6,
+            30,
+        ))
+    }
+}
+
+/// The defaults of the OTP settings that authenticator app exports may leave
+/// out
+pub fn default_algorithm() -> String {
+    "SHA1".to_string()
+}
+
+pub fn default_digits() -> u32 {
+    6
+}
+
+pub fn default_period() -> u64 {
+    30
+}
+
+/// Constructs a `OtpRecord` from the settings found in authenticator app
+/// exports
+/**
+ * Blueprint
+ *  1. Normalize the secret and parse the HMAC algorithm, bail on error
+ *  2. Bail if the token length or the period are not supported
+ *  3. Construct a `OtpRecord`, bail if the OTP type is neither TOTP nor HOTP
+ */
+pub fn otp_record(
+    kind: &str,
+    secret: &str,
+    issuer: Option<String>,
+    algorithm: &str,
+    digits: u32,
+    period: u64,
+    counter: u64,
+) -> Result<OtpRecord, Error> {
+    // (1)
+    let secret = otp::normalize_secret(secret)?;
+    let algorithm = algorithm.parse::<HmacAlgorithm>()?;
+
+    // (2)
+    otp::check_digits(digits)?;
+
+    // (3)
+    match &kind.to_ascii_lowercase()[..] {
+        "totp" => {
+            otp::check_period(period)?;
+            Ok(OtpRecord::new_totp(
+                secret, issuer, algorithm, digits, period,
+            ))
+        }
+        "hotp" => Ok(OtpRecord::new_hotp(
+            secret, issuer, algorithm, digits, counter,
+        )),
+        _ => bail!(UrlError::UnknownOtpType(kind.to_string())),
+    }
+}
@@ -0,0 +1,152 @@
+// Copyright (C) 2018 Alberto Coscia
+//
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at http://mozilla.org/MPL/2.0/.
+
+use failure::Error;
+use lib::otpauth;
+use lib::types::{OtpRecord, Record};
+use std::path::{Component, Path};
+
+/// Parses the decrypted contents of a [pass](https://www.passwordstore.org/)
+/// entry to a `Record` and/or an `OtpRecord`
+/**
+ * Blueprint
+ *  1. Use the first line as the password, unless it is an `otpauth://` URL
+ *     as written by pass-otp
+ *  2. Read metadata from the other `key: value` lines
+ *      i) Username: `login`, `user` or `username`
+ *      ii) Email: `email`
+ *      iii) Home: `url`
+ *  3. Parse the first `otpauth://` line to a `OtpRecord`, returning the error
+ *     alongside the `Record` so that the password is still imported
+ *  4. Construct a `Record`, unless there is no password
+ */
+pub fn parse_entry(contents: &str) -> (Option<Record>, Result<Option<OtpRecord>, Error>) {
+    tracepoint!();
+    let mut lines = contents.lines();
+
+    // (1)
+    let first = lines.next().unwrap_or("");
+    let (password, rest) = if first.trim().starts_with("otpauth://") {
+        (None, contents.lines())
+    } else {
+        (Some(first.to_string()), lines)
+    };
+
+    // (2)
+    let mut username = None;
+    let mut email = None;
+    let mut home = None;
+    let mut otp = None;
+    for line in rest {
+        let line = line.trim();
+        if line.starts_with("otpauth://") {
+            // (3)
+            if otp.is_none() {
+                otp = Some(otpauth::parse_url(line).map(|(_, otp)| otp));
+            }
+            continue;
+        }
+
+        if let Some(i) = line.find(':') {
+            let value = line[i + 1..].trim().to_string();
+            if value.is_empty() {
+                continue;
+            }
+            match &line[..i].trim().to_ascii_lowercase()[..] {
+                // (2.i)
+                "login" | "user" | "username" => username = Some(value),
+                // (2.ii)
+                "email" => email = Some(value),
+                // (2.iii)
+                "url" => home = Some(value),
+                _ => {}
+            }
+        }
+    }
+
+    // (4)
+    let record = password
+        .filter(|p| !p.is_empty())
+        .map(|p| Record::new(p, username, email, home));
+
+    (record, otp.map_or(Ok(None), |otp| Ok(Some(otp?))))
+}
+
+/// Creates a record ID from the path of a pass entry, such as "work/aws/root"
+/// for `<store>/work/aws/root.gpg`
+pub fn record_id(store: &Path, entry: &Path) -> Option<String> {
+    let relative = entry.strip_prefix(store).ok()?.with_extension("");
+    let components: Vec<String> = relative
+        .components()
+        .filter_map(|c| match c {
+            Component::Normal(c) => Some(c.to_string_lossy().into_owned()),
+            _ => None,
+        })
+        .collect();
+
+    if components.is_empty() {
+        None
+    } else {
+        Some(components.join("/"))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use lib::import::pass::{parse_entry, record_id};
+    use lib::types::OtpRecord;
+    use std::path::Path;
+
+    #[test]
+    fn entry_metadata() {
+        let (record, otp) = parse_entry(
+            "hunter2\nlogin: bob\nEmail: bob@example.com\nurl: https://service.tld\nnotes: none\n",
+        );
+        let record = record.unwrap();
+        assert_eq!(record.password, "hunter2");
+        assert_eq!(record.username.unwrap(), "bob");
+        assert_eq!(record.email.unwrap(), "bob@example.com");
+        assert_eq!(record.home.unwrap(), "https://service.tld");
+        assert!(otp.unwrap().is_none());
+    }
+
+    #[test]
+    fn entry_otp() {
+        let (record, otp) = parse_entry(
+            "hunter2\notpauth://totp/bob?secret=GEZDGNBVGY3TQOJQGEZDGNBVGY3TQOJQ&issuer=service\n",
+        );
+        assert_eq!(record.unwrap().password, "hunter2");
+        match otp.unwrap().unwrap() {
+            OtpRecord::Totp { issuer, .. } => assert_eq!(issuer.unwrap(), "service"),
+            _ => panic!("Expected a TOTP record"),
+        }
+
+        // pass-otp may store the URL alone
+        let (record, otp) =
+            parse_entry("otpauth://totp/bob?secret=GEZDGNBVGY3TQOJQGEZDGNBVGY3TQOJQ");
+        assert!(record.is_none());
+        assert!(otp.unwrap().is_some());
+
+        // An invalid URL doesn't lose the password
+        let (record, otp) = parse_entry("hunter2\notpauth://totp/bob?secret=0");
+        assert_eq!(record.unwrap().password, "hunter2");
+        assert!(otp.is_err());
+    }
+
+    #[test]
+    fn entry_record_id() {
+        let store = Path::new("/home/bob/.password-store");
+        assert_eq!(
+            record_id(store, &store.join("work/aws/root.gpg")).unwrap(),
+            "work/aws/root"
+        );
+        assert_eq!(
+            record_id(store, &store.join("example.com.gpg")).unwrap(),
+            "example.com"
+        );
+        assert!(record_id(store, Path::new("/tmp/foo.gpg")).is_none());
+    }
+}
@@ -0,0 +1,324 @@
+// Copyright (C) 2018 Alberto Coscia
+//
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at http://mozilla.org/MPL/2.0/.
+
+use base32;
+use base64;
+use failure::Error;
+use lib::error::{OtpError, UrlError};
+use lib::otpauth;
+use lib::types::{HmacAlgorithm, OtpRecord};
+use url::Url;
+
+/// Parses a Google Authenticator `otpauth-migration://` URL to the
+/// `OtpRecord`s it contains and their record IDs
+///
+/// The `data` parameter is a base64-encoded `MigrationPayload` protobuf
+/// message, see
+/// [the Google Authenticator source](https://github.com/google/google-authenticator-android/blob/master/java/com/google/android/apps/authenticator/otp/proto/migration.proto)
+/**
+ * Blueprint
+ *  1. Parse the URL, bail if it is not an `otpauth-migration://` URL
+ *  2. Decode the base64 `data` parameter, bail on error
+ *  3. Read every `OtpParameters` message (field 1) of the payload, skip
+ *     other fields, bail on malformed messages
+ *  4. Construct a `OtpRecord` from every `OtpParameters`, bail on error
+ */
+pub fn parse_url(url: &str) -> Result<Vec<(String, OtpRecord)>, Error> {
+    tracepoint!();
+
+    // (1)
+    let url = Url::parse(url)?;
+    ensure!(
+        url.scheme().eq_ignore_ascii_case("otpauth-migration"),
+        UrlError::InvalidScheme
+    );
+
+    // (2)
+    let data = url
+        .query_pairs()
+        .find(|(k, _)| k == "data")
+        .map(|(_, v)| v.into_owned())
+        .ok_or(UrlError::MissingParameter("data"))?;
+    // An unescaped '+' is decoded to a space by the query string parser
+    let data =
+        base64::decode(&data.replace(' ', "+")).map_err(|_| UrlError::InvalidParameter("data"))?;
+
+    // (3)
+    let mut payload = Message::new(&data);
+    let mut records = Vec::new();
+    while let Some((field, value)) = payload.next_field()? {
+        if let (1, Value::Bytes(parameters)) = (field, value) {
+            // (4)
+            records.push(parse_parameters(parameters)?);
+        }
+    }
+
+    Ok(records)
+}
+
+/// Constructs a `OtpRecord` from an `OtpParameters` protobuf message
+/**
+ * Blueprint
+ *  1. Read every known field, skip the others
+ *      i) Secret (1): raw bytes, base32-encode them
+ *      ii) Name (2): a label such as "issuer:account"
+ *      iii) Issuer (3): defaults to the label prefix
+ *      iv) Algorithm (4): 1 is SHA1, 2 is SHA256, 3 is SHA512
+ *      v) Digits (5): 1 is 6 digits, 2 is 8 digits
+ *      vi) Type (6): 1 is HOTP, 2 is TOTP
+ *      vii) Counter (7): HOTP only
+ *  2. Bail if there is no secret or name
+ *  3. Construct a `OtpRecord`, bail if the OTP type is unknown
+ *  4. Use the label, such as "issuer:account", as the record ID
+ */
+fn parse_parameters(message: &[u8]) -> Result<(String, OtpRecord), Error> {
+    tracepoint!();
+    let mut secret = None;
+    let mut name = String::new();
+    let mut issuer = String::new();
+    let mut algorithm = HmacAlgorithm::SHA1;
+    let mut digits = 6;
+    let mut kind = 0;
+    let mut counter = 0;
+
+    // (1)
+    let mut message = Message::new(message);
+    while let Some((field, value)) = message.next_field()? {
+        match (field, value) {
+            // (1.i)
+            (1, Value::Bytes(b)) => {
+                secret = Some(base32::encode(
+                    base32::Alphabet::RFC4648 { padding: false },
+                    b,
+                ))
+            }
+            // (1.ii)
+            (2, Value::Bytes(b)) => name = String::from_utf8(b.to_vec())?,
+            // (1.iii)
+            (3, Value::Bytes(b)) => issuer = String::from_utf8(b.to_vec())?,
+            // (1.iv)
+            // Unspecified algorithms default to SHA1
+            (4, Value::Varint(a)) => {
+                algorithm = match a {
+                    0 | 1 => HmacAlgorithm::SHA1,
+                    2 => HmacAlgorithm::SHA256,
+                    3 => HmacAlgorithm::SHA512,
+                    _ => bail!(OtpError::UnknownHmacAlgorithm),
+                }
+            }
+            // (1.v)
+            (5, Value::Varint(d)) => digits = if d == 2 { 8 } else { 6 },
+            // (1.vi)
+            (6, Value::Varint(t)) => kind = t,
+            // (1.vii)
+            (7, Value::Varint(c)) => counter = c,
+            _ => {}
+        }
+    }
+
+    // (2)
+    let secret = secret
+        .filter(|s| !s.is_empty())
+        .ok_or(UrlError::MissingParameter("secret"))?;
+    let (label_issuer, account) = otpauth::split_label(&name);
+    ensure!(!account.is_empty(), UrlError::MissingLabel);
+    let issuer = Some(issuer.trim())
+        .filter(|i| !i.is_empty())
+        .or(label_issuer)
+        .map(|i| i.to_string());
+
+    // (3)
+    let record = match kind {
+        1 => OtpRecord::new_hotp(secret, issuer, algorithm, digits, counter),
+        // Unspecified types default to TOTP
+        0 | 2 => OtpRecord::new_totp(secret, issuer, algorithm, digits, 30),
+        _ => bail!(UrlError::UnknownOtpType(kind.to_string())),
+    };
+
+    // (4)
+    Ok((otpauth::record_id(label_issuer, account), record))
+}
+
+/// A protobuf field value, see the
+/// [encoding reference](https://developers.google.com/protocol-buffers/docs/encoding)
+enum Value<'a> {
+    Varint(u64),
+    Bytes(&'a [u8]),
+    Fixed,
+}
+
+/// A reader for the fields of an encoded protobuf message
+struct Message<'a> {
+    buf: &'a [u8],
+    pos: usize,
+}
+
+impl<'a> Message<'a> {
+    fn new(buf: &'a [u8]) -> Message<'a> {
+        Message { buf, pos: 0 }
+    }
+
+    /// Reads the next field number and its value, if any
+    fn next_field(&mut self) -> Result<Option<(u64, Value<'a>)>, UrlError> {
+        if self.pos >= self.buf.len() {
+            return Ok(None);
+        }
+
+        // The key holds the field number and the wire type in its 3 LSBs
+        let key = self.varint()?;
+        let value = match key & 0x7 {
+            0 => Value::Varint(self.varint()?),
+            1 => self.fixed(8)?,
+            2 => {
+                let len = self.varint()? as usize;
+                Value::Bytes(self.take(len)?)
+            }
+            5 => self.fixed(4)?,
+            _ => return Err(UrlError::InvalidParameter("data")),
+        };
+
+        Ok(Some((key >> 3, value)))
+    }
+
+    fn varint(&mut self) -> Result<u64, UrlError> {
+        let mut value = 0u64;
+        // Every byte holds 7 bits, the MSB is set on all bytes but the last
+        for shift in (0..64).step_by(7) {
+            let byte = self.take(1)?[0];
+            value |= u64::from(byte & 0x7f) << shift;
+            if byte & 0x80 == 0 {
+                return Ok(value);
+            }
+        }
+
+        Err(UrlError::InvalidParameter("data"))
+    }
+
+    fn fixed(&mut self, len: usize) -> Result<Value<'a>, UrlError> {
+        self.take(len)?;
+        Ok(Value::Fixed)
+    }
+
+    fn take(&mut self, len: usize) -> Result<&'a [u8], UrlError> {
+        let end = self
+            .pos
+            .checked_add(len)
+            .filter(|end| *end <= self.buf.len())
+            .ok_or(UrlError::InvalidParameter("data"))?;
+        let bytes = &self.buf[self.pos..end];
+        self.pos = end;
+
+        Ok(bytes)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use base64;
+    use lib::migration::parse_url;
+    use lib::types::{HmacAlgorithm, OtpRecord};
+
+    /// Encodes a length-delimited protobuf field
+    fn field(number: u8, bytes: &[u8]) -> Vec<u8> {
+        let mut buf = vec![number << 3 | 2, bytes.len() as u8];
+        buf.extend_from_slice(bytes);
+        buf
+    }
+
+    /// Encodes a varint protobuf field
+    fn varint(number: u8, value: u8) -> Vec<u8> {
+        vec![number << 3, value]
+    }
+
+    fn migration_url(parameters: &[Vec<u8>]) -> String {
+        let mut payload = Vec::new();
+        for p in parameters {
+            payload.extend(field(1, p));
+        }
+        // Version, batch size, batch index and batch ID
+        payload.extend(varint(2, 1));
+        payload.extend(varint(3, 1));
+        payload.extend(varint(4, 0));
+        payload.extend(&[5 << 3, 0xd2, 0x09]);
+
+        format!(
+            "otpauth-migration://offline?data={}",
+            base64::encode(&payload)
+                .replace('+', "%2B")
+                .replace('/', "%2F")
+                .replace('=', "%3D")
+        )
+    }
+
+    #[test]
+    fn batch() {
+        let totp = [
+            field(1, b"12345678901234567890"),
+            field(2, b"Example:alice@example.com"),
+            field(3, b"Example"),
+            varint(4, 2),
+            varint(5, 2),
+            varint(6, 2),
+        ]
+        .concat();
+        let hotp = [
+            field(1, b"12345678901234567890"),
+            field(2, b"bob"),
+            varint(4, 1),
+            varint(5, 1),
+            varint(6, 1),
+            varint(7, 42),
+        ]
+        .concat();
+
+        let records = parse_url(&migration_url(&[totp, hotp])).unwrap();
+        assert_eq!(records.len(), 2);
+
+        assert_eq!(records[0].0, "Example:alice@example.com");
+        match &records[0].1 {
+            OtpRecord::Totp {
+                secret,
+                issuer,
+                algorithm: HmacAlgorithm::SHA256,
+                digits: 8,
+                period: 30,
+                ..
+            } => {
+                assert_eq!(secret, "GEZDGNBVGY3TQOJQGEZDGNBVGY3TQOJQ");
+                assert_eq!(issuer.as_ref().unwrap(), "Example");
+            }
+            _ => panic!("Expected a TOTP record"),
+        }
+
+        assert_eq!(records[1].0, "bob");
+        match &records[1].1 {
+            OtpRecord::Hotp {
+                issuer: None,
+                algorithm: HmacAlgorithm::SHA1,
+                digits: 6,
+                counter: 42,
+                ..
+            } => {}
+            _ => panic!("Expected a HOTP record"),
+        }
+    }
+
+    #[test]
+    fn invalid_payloads() {
+        // Wrong scheme
+        assert!(parse_url("otpauth://offline?data=AAAA").is_err());
+        // Missing or malformed data
+        assert!(parse_url("otpauth-migration://offline").is_err());
+        assert!(parse_url("otpauth-migration://offline?data=!!!").is_err());
+        // Truncated message
+        assert!(parse_url("otpauth-migration://offline?data=ChQ%3D").is_err());
+        // Missing secret
+        assert!(parse_url(&migration_url(&[field(2, b"alice")])).is_err());
+        // MD5 is not supported
+        let md5 = [field(1, b"1234567890"), field(2, b"alice"), varint(4, 4)].concat();
+        assert!(parse_url(&migration_url(&[md5])).is_err());
+    }
+}
@@ -4,8 +4,16 @@
 // License, v. 2.0. If a copy of the MPL was not distributed with this
 // file, You can obtain one at http://mozilla.org/MPL/2.0/.
 
+pub mod audit;
+pub mod backend;
+pub mod config;
 pub mod error;
+pub mod export;
+pub mod import;
+pub mod migration;
 #[allow(non_snake_case)] // We allow it as we try to have variable names conform to the RFCs
 pub mod otp;
+pub mod otpauth;
+pub mod search;
 pub mod types;
 pub mod utils;
@@ -5,6 +5,7 @@
 // file, You can obtain one at http://mozilla.org/MPL/2.0/.
 
 use base32;
+use lib::error::OtpError;
 use lib::types::HmacAlgorithm;
 use ring::{digest, hmac};
 use std::mem::transmute;
@@ -15,7 +16,8 @@ pub type Algorithm = &'static digest::Algorithm;
 /// Computes an N-digits OTP using the TOTP algorithm as laid out in
 /// [IETF RFC 6238](https://tools.ietf.org/html/rfc6238).
 ///
-/// Returns the remaining token validity time as u64.
+/// Returns the remaining token validity time as u64, fails if `K` is not
+/// valid base32.
 /**
  * Blueprint
  *  0. Let `T0` be a Unix timestamp and `TI` a period, both expressed in the same
@@ -25,7 +27,13 @@ pub type Algorithm = &'static digest::Algorithm;
  *  1. C := floor((now - T0)/TI) as u64
  *  2. Return HOTP(K, C)
  */
-pub fn totp(T0: u64, TI: u64, K: &str, N: u32, algorithm: &HmacAlgorithm) -> (u32, u64) {
+pub fn totp(
+    T0: u64,
+    TI: u64,
+    K: &str,
+    N: u32,
+    algorithm: &HmacAlgorithm,
+) -> Result<(u32, u64), OtpError> {
     tracepoint!();
     let now = SystemTime::now()
         .duration_since(UNIX_EPOCH)
@@ -39,7 +47,8 @@ pub fn totp(T0: u64, TI: u64, K: &str, N: u32, algorithm: &HmacAlgorithm) -> (u3
 /// [IETF RFC 6238](https://tools.ietf.org/html/rfc6238).
 /// Helper method that allows testing
 ///
-/// Returns the remaining token validity time as u64.
+/// Returns the remaining token validity time as u64, fails if `K` is not
+/// valid base32 or `TI` is 0.
 pub fn totp_with_now(
     T0: u64,
     TI: u64,
@@ -47,8 +56,9 @@ pub fn totp_with_now(
     N: u32,
     now: u64,
     algorithm: &HmacAlgorithm,
-) -> (u32, u64) {
+) -> Result<(u32, u64), OtpError> {
     tracepoint!();
+    check_period(TI)?;
     // (1)
     // Cast to f64 so that we can have the precision necessary to use floor as
     // specified in RFC 6238 §4.2
@@ -56,7 +66,7 @@ pub fn totp_with_now(
 
     // (2)
     tracepoint!();
-    (hotp(K, C, N, &algorithm), TI - ((now - T0) % TI))
+    Ok((hotp(K, C, N, algorithm)?, TI - ((now - T0) % TI)))
 }
 
 /// Computes an N-digits OTP using the HOTP algorithm as laid out in
@@ -64,11 +74,14 @@ pub fn totp_with_now(
 ///
 /// Note that RFC 4226 only allows for SHA-1 to be used, but SHA-2 is allowed by
 /// RFC 6238
+///
+/// Fails if `K` is not valid base32 or `N` is not a supported length.
 /**
  * Blueprint
  *  0. Let `K` be a base32-encoded secret. Let `C` be a counter. Let `N` be the
  *     token length.
- *  1. Decode `K` to a Vec<u8>, bail if it is not valid base32
+ *  1. Bail if `N` is not supported, decode `K` to a Vec<u8>, bail if it is not
+ *     valid base32
  *  2. H := HMAC(K, C) using `algorithm`
  *  3. O := least 4 significant bits of H
  *  4. Take 4 bytes from `H` starting at the O'th most significant byte, discard
@@ -76,11 +89,14 @@ pub fn totp_with_now(
  *     store the rest as u32
  *  5. Return only `N` digits
  * */
-pub fn hotp(K: &str, C: u64, N: u32, algorithm: &HmacAlgorithm) -> u32 {
+pub fn hotp(K: &str, C: u64, N: u32, algorithm: &HmacAlgorithm) -> Result<u32, OtpError> {
     tracepoint!();
     // (1)
-    // Failing here is not recoverable -- the user did something wrong
-    let K = base32::decode(base32::Alphabet::RFC4648 { padding: false }, K).unwrap();
+    check_digits(N)?;
+    // Secrets stored by older versions may not have been normalized
+    let K = normalize_secret(K)?;
+    let K = base32::decode(base32::Alphabet::RFC4648 { padding: false }, &K)
+        .ok_or(OtpError::InvalidSecret)?;
 
     // (2)
     tracepoint!();
@@ -117,7 +133,63 @@ pub fn hotp(K: &str, C: u64, N: u32, algorithm: &HmacAlgorithm) -> u32 {
 
     // (5)
     tracepoint!();
-    decimal % 10u32.pow(N)
+    Ok(decimal % 10u32.pow(N))
+}
+
+/// Bails if tokens cannot be `digits` long. RFC 4226 requires 6 digits and
+/// suggests 7 and 8
+pub fn check_digits(digits: u32) -> Result<(), OtpError> {
+    if (6..=8).contains(&digits) {
+        Ok(())
+    } else {
+        Err(OtpError::UnsupportedDigits(digits))
+    }
+}
+
+/// Bails if TOTP tokens cannot change every `period` seconds
+pub fn check_period(period: u64) -> Result<(), OtpError> {
+    if period > 0 {
+        Ok(())
+    } else {
+        Err(OtpError::UnsupportedPeriod(period))
+    }
+}
+
+/// Normalizes a base32-encoded secret to the unpadded, uppercase form used by
+/// `hotp`, bailing if it is not valid base32 as laid out in
+/// [IETF RFC 4648](https://tools.ietf.org/html/rfc4648#section-6)
+/**
+ * Blueprint
+ *  1. Strip whitespace and `=` padding, convert to uppercase
+ *  2. Bail if the secret is empty, contains characters outside of the base32
+ *     alphabet or has a length no base32 string can have
+ *  3. Bail if the secret cannot be decoded
+ */
+pub fn normalize_secret(secret: &str) -> Result<String, OtpError> {
+    tracepoint!();
+    // (1)
+    let secret: String = secret
+        .chars()
+        .filter(|c| !c.is_whitespace() && *c != '=')
+        .map(|c| c.to_ascii_uppercase())
+        .collect();
+
+    // (2)
+    // Unpadded base32 strings are made of 8-char blocks, the last of which
+    // may only be 2, 4, 5 or 7 chars long
+    let valid_length = [0, 2, 4, 5, 7].contains(&(secret.len() % 8));
+    let valid_chars = secret
+        .chars()
+        .all(|c| c.is_ascii_uppercase() || ('2'..='7').contains(&c));
+    if secret.is_empty() || !valid_length || !valid_chars {
+        return Err(OtpError::InvalidSecret);
+    }
+
+    // (3)
+    base32::decode(base32::Alphabet::RFC4648 { padding: false }, &secret)
+        .ok_or(OtpError::InvalidSecret)?;
+
+    Ok(secret)
 }
 
 #[cfg(test)]
@@ -136,7 +208,7 @@ mod tests {
     fn hotp_rfc_values() {
         for value in 0..RFC_HOTP_VALUES.len() {
             assert_eq!(
-                otp::hotp(&RFC_HOTP_SECRET, value as u64, 6, &HmacAlgorithm::SHA1),
+                otp::hotp(RFC_HOTP_SECRET, value as u64, 6, &HmacAlgorithm::SHA1).unwrap(),
                 RFC_HOTP_VALUES[value as usize]
             );
         }
@@ -167,7 +239,8 @@ mod tests {
                 8,
                 RFC_TOTP_TIMES[value],
                 &HmacAlgorithm::SHA1,
-            );
+            )
+            .unwrap();
             assert_eq!(v, RFC_TOTP_VALUES_SHA1[value as usize]);
         }
     }
@@ -188,7 +261,8 @@ mod tests {
                 8,
                 RFC_TOTP_TIMES[value],
                 &HmacAlgorithm::SHA256,
-            );
+            )
+            .unwrap();
             assert_eq!(v, RFC_TOTP_VALUES_SHA256[value as usize]);
         }
     }
@@ -209,10 +283,50 @@ mod tests {
                 8,
                 RFC_TOTP_TIMES[value],
                 &HmacAlgorithm::SHA512,
-            );
+            )
+            .unwrap();
             assert_eq!(v, RFC_TOTP_VALUES_SHA512[value as usize]);
         }
     }
+
+    #[test]
+    fn normalize_secret_formatting() {
+        assert_eq!(
+            otp::normalize_secret("gezd gnbv gy3t qojq gezd gnbv gy3t qojq").unwrap(),
+            RFC_HOTP_SECRET
+        );
+        assert_eq!(
+            otp::normalize_secret("GEZDGNBVGY3TQOJQGEZDGNBVGY3TQOJQGEZDGNBVGY3TQOJQGEZA====")
+                .unwrap(),
+            RFC_TOTP_SECRET_SHA256
+        );
+    }
+
+    #[test]
+    fn hotp_invalid_secret() {
+        assert!(otp::hotp("GEZDGNB0", 0, 6, &HmacAlgorithm::SHA1).is_err());
+        assert!(otp::totp_with_now(0, 30, "", 6, 59, &HmacAlgorithm::SHA1).is_err());
+    }
+
+    #[test]
+    fn unsupported_parameters() {
+        let sha1 = &HmacAlgorithm::SHA1;
+        assert!(otp::hotp(RFC_HOTP_SECRET, 0, 10, sha1).is_err());
+        assert!(otp::hotp(RFC_HOTP_SECRET, 0, 5, sha1).is_err());
+        assert!(otp::totp_with_now(0, 0, RFC_HOTP_SECRET, 6, 59, sha1).is_err());
+        assert!(otp::check_digits(8).is_ok());
+        assert!(otp::check_period(1).is_ok());
+    }
+
+    #[test]
+    fn normalize_secret_invalid() {
+        assert!(otp::normalize_secret("").is_err());
+        assert!(otp::normalize_secret("  ==").is_err());
+        // 0, 1, 8 and 9 are not part of the base32 alphabet
+        assert!(otp::normalize_secret("GEZDGNB0").is_err());
+        // No base32 string can be 3 chars long
+        assert!(otp::normalize_secret("GEZ").is_err());
+    }
 }
 
 /// Converts u64 to a u8 array
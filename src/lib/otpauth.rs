@@ -0,0 +1,350 @@
+// Copyright (C) 2018 Alberto Coscia
+//
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at http://mozilla.org/MPL/2.0/.
+
+use failure::Error;
+use lib::error::UrlError;
+use lib::otp;
+use lib::types::{HmacAlgorithm, OtpRecord};
+use std::collections::HashMap;
+use url::percent_encoding::{percent_decode, utf8_percent_encode, EncodeSet};
+use url::Url;
+
+/// Parses an
+/// [otpauth:// URL](https://github.com/google/google-authenticator/wiki/Key-Uri-Format)
+/// to an `OtpRecord` and the record ID derived from its label
+/**
+ * Blueprint
+ *  1. Parse the URL, bail if it is not an `otpauth://` URL
+ *  2. Percent-decode the label and split it into an optional issuer prefix and
+ *     an account name, bail if there is no account name
+ *  3. Parse the query string, bail on missing or malformed parameters
+ *      i) Secret: mandatory, must be valid base32
+ *      ii) Issuer: defaults to the label prefix
+ *      iii) Algorithm: default to SHA1
+ *      iv) Digits: default to 6
+ *      v) Period: default to 30s (TOTP only)
+ *      vi) Counter: mandatory (HOTP only)
+ *  4. Construct a `OtpRecord`, bail if the OTP type is unknown
+ *  5. Use the label, such as "issuer:account", as the record ID
+ */
+pub fn parse_url(url: &str) -> Result<(String, OtpRecord), Error> {
+    tracepoint!();
+
+    // (1)
+    let url = Url::parse(url)?;
+    // Can't work on other schemes
+    ensure!(
+        url.scheme().eq_ignore_ascii_case("otpauth"),
+        UrlError::InvalidScheme
+    );
+    // TOTP/HOTP is the host
+    let kind = url.host_str().unwrap_or("").to_ascii_lowercase();
+
+    // (2)
+    let label = percent_decode(url.path().trim_start_matches('/').as_bytes())
+        .decode_utf8()?
+        .into_owned();
+    let (label_issuer, account) = split_label(&label);
+    ensure!(!account.is_empty(), UrlError::MissingLabel);
+
+    // (3)
+    // Iterator -> HashMap
+    let query: HashMap<String, String> = url.query_pairs().into_owned().collect();
+
+    // (3.i)
+    let secret = query
+        .get("secret")
+        .ok_or(UrlError::MissingParameter("secret"))?;
+    let secret = otp::normalize_secret(secret)?;
+
+    // (3.ii)
+    let issuer = query
+        .get("issuer")
+        .map(|i| i.trim().to_string())
+        .filter(|i| !i.is_empty());
+    if let (Some(issuer), Some(label_issuer)) = (&issuer, label_issuer) {
+        if issuer != label_issuer {
+            warn!(
+                "Issuer parameter ({}) does not match the label prefix ({})",
+                issuer, label_issuer
+            );
+        }
+    }
+    let issuer = issuer.or_else(|| label_issuer.map(|i| i.to_string()));
+
+    // (3.iii)
+    let algorithm: HmacAlgorithm = query
+        .get("algorithm")
+        .map(|a| a.parse())
+        .unwrap_or(Ok(HmacAlgorithm::SHA1))?;
+
+    // (3.iv)
+    let digits: u32 = query
+        .get("digits")
+        .map(|d| d.trim().parse())
+        .unwrap_or(Ok(6))
+        .map_err(|_| UrlError::InvalidParameter("digits"))?;
+    ensure!(
+        (6..=8).contains(&digits),
+        UrlError::InvalidParameter("digits")
+    );
+
+    // (4)
+    let record = match &kind[..] {
+        "totp" => {
+            // (3.v)
+            let period: u64 = query
+                .get("period")
+                .map(|p| p.trim().parse())
+                .unwrap_or(Ok(30))
+                .map_err(|_| UrlError::InvalidParameter("period"))?;
+            ensure!(period > 0, UrlError::InvalidParameter("period"));
+
+            OtpRecord::new_totp(secret, issuer, algorithm, digits, period)
+        }
+        "hotp" => {
+            // (3.vi)
+            let counter: u64 = query
+                .get("counter")
+                .ok_or(UrlError::MissingParameter("counter"))?
+                .trim()
+                .parse()
+                .map_err(|_| UrlError::InvalidParameter("counter"))?;
+
+            OtpRecord::new_hotp(secret, issuer, algorithm, digits, counter)
+        }
+        _ => bail!(UrlError::UnknownOtpType(kind)),
+    };
+
+    // (5)
+    Ok((record_id(label_issuer, account), record))
+}
+
+/// Splits a label such as "issuer:account" into its optional issuer prefix and
+/// its account name
+pub fn split_label(label: &str) -> (Option<&str>, &str) {
+    // Spec allows for optional spaces between the issuer prefix and the account
+    let (issuer, account) = match label.find(':') {
+        Some(i) => (Some(label[..i].trim()), label[i + 1..].trim()),
+        None => (None, label.trim()),
+    };
+
+    (issuer.filter(|i| !i.is_empty()), account)
+}
+
+/// Creates a record ID from a label's issuer prefix and account name, such as
+/// "issuer:account"
+pub fn record_id(issuer: Option<&str>, account: &str) -> String {
+    match issuer {
+        Some(issuer) => format!("{}:{}", issuer, account),
+        None => account.to_string(),
+    }
+}
+
+/// Serializes an `OtpRecord` to an
+/// [otpauth:// URL](https://github.com/google/google-authenticator/wiki/Key-Uri-Format),
+/// using its record ID as the label
+/**
+ * Blueprint
+ *  1. Percent-encode the record ID and use it as the label
+ *  2. Add every parameter of the record to the query string, omitting the
+ *     issuer if there is none
+ */
+pub fn to_url(record_id: &str, record: &OtpRecord) -> String {
+    tracepoint!();
+    let (kind, secret, issuer, algorithm, digits, parameter) = match record {
+        OtpRecord::Totp {
+            secret,
+            issuer,
+            algorithm,
+            digits,
+            period,
+            ..
+        } => (
+            "totp",
+            secret,
+            issuer,
+            algorithm,
+            digits,
+            format!("period={}", period),
+        ),
+        OtpRecord::Hotp {
+            secret,
+            issuer,
+            algorithm,
+            digits,
+            counter,
+            ..
+        } => (
+            "hotp",
+            secret,
+            issuer,
+            algorithm,
+            digits,
+            format!("counter={}", counter),
+        ),
+    };
+
+    // (1)
+    let mut url = format!(
+        "otpauth://{}/{}?secret={}",
+        kind,
+        utf8_percent_encode(record_id, UnreservedEncodeSet),
+        secret
+    );
+
+    // (2)
+    if let Some(issuer) = issuer {
+        url += &format!(
+            "&issuer={}",
+            utf8_percent_encode(issuer, UnreservedEncodeSet)
+        );
+    }
+    url += &format!("&algorithm={:?}&digits={}&{}", algorithm, digits, parameter);
+
+    url
+}
+
+/// Percent-encodes everything but the unreserved characters laid out in
+/// [IETF RFC 3986](https://tools.ietf.org/html/rfc3986#section-2.3)
+#[derive(Clone)]
+struct UnreservedEncodeSet;
+
+impl EncodeSet for UnreservedEncodeSet {
+    fn contains(&self, byte: u8) -> bool {
+        !(byte.is_ascii_alphanumeric() || b"-._~".contains(&byte))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use lib::otpauth::{parse_url, to_url};
+    use lib::types::{HmacAlgorithm, OtpRecord};
+
+    const SECRET: &str = "GEZDGNBVGY3TQOJQGEZDGNBVGY3TQOJQ";
+
+    #[test]
+    fn totp_defaults() {
+        let (id, record) =
+            parse_url("otpauth://totp/alice@example.com?secret=GEZDGNBVGY3TQOJQGEZDGNBVGY3TQOJQ")
+                .unwrap();
+        assert_eq!(id, "alice@example.com");
+        match record {
+            OtpRecord::Totp {
+                secret,
+                issuer,
+                algorithm: HmacAlgorithm::SHA1,
+                period: 30,
+                digits: 6,
+                ..
+            } => {
+                assert_eq!(secret, SECRET);
+                assert_eq!(issuer, None);
+            }
+            _ => panic!("Expected a TOTP record with default parameters"),
+        }
+    }
+
+    #[test]
+    fn totp_parameters() {
+        let (_, record) = parse_url(
+            "otpauth://TOTP/alice?secret=GEZDGNBVGY3TQOJQGEZDGNBVGY3TQOJQ&algorithm=sha256&digits=8&period=60",
+        ).unwrap();
+        match record {
+            OtpRecord::Totp {
+                algorithm: HmacAlgorithm::SHA256,
+                period: 60,
+                digits: 8,
+                ..
+            } => {}
+            _ => panic!("Expected a TOTP record with custom parameters"),
+        }
+    }
+
+    #[test]
+    fn label_issuer_prefix() {
+        let (id, record) = parse_url(
+            "otpauth://totp/Example%20Co:%20alice%40example.com?secret=GEZDGNBVGY3TQOJQGEZDGNBVGY3TQOJQ",
+        ).unwrap();
+        assert_eq!(id, "Example Co:alice@example.com");
+        match record {
+            OtpRecord::Totp { issuer, .. } => assert_eq!(issuer.unwrap(), "Example Co"),
+            _ => panic!("Expected a TOTP record"),
+        }
+    }
+
+    #[test]
+    fn issuer_parameter_precedence() {
+        let (id, record) = parse_url(
+            "otpauth://totp/Bob:service?issuer=service&secret=GEZDGNBVGY3TQOJQGEZDGNBVGY3TQOJQ",
+        )
+        .unwrap();
+        assert_eq!(id, "Bob:service");
+        match record {
+            OtpRecord::Totp { issuer, .. } => assert_eq!(issuer.unwrap(), "service"),
+            _ => panic!("Expected a TOTP record"),
+        }
+    }
+
+    #[test]
+    fn hotp_counter() {
+        let (_, record) =
+            parse_url("otpauth://hotp/alice?secret=GEZDGNBVGY3TQOJQGEZDGNBVGY3TQOJQ&counter=42")
+                .unwrap();
+        match record {
+            OtpRecord::Hotp { counter: 42, .. } => {}
+            _ => panic!("Expected a HOTP record with a counter"),
+        }
+        assert!(parse_url("otpauth://hotp/alice?secret=GEZDGNBVGY3TQOJQGEZDGNBVGY3TQOJQ").is_err());
+        assert!(parse_url(
+            "otpauth://hotp/alice?secret=GEZDGNBVGY3TQOJQGEZDGNBVGY3TQOJQ&counter=-1"
+        )
+        .is_err());
+    }
+
+    #[test]
+    fn secret_normalization() {
+        let (_, record) =
+            parse_url("otpauth://totp/alice?secret=gezd%20gnbv%20gy3t%20qojq+gezdgnbvgy3tqojq====")
+                .unwrap();
+        match record {
+            OtpRecord::Totp { secret, .. } => assert_eq!(secret, SECRET),
+            _ => panic!("Expected a TOTP record"),
+        }
+    }
+
+    #[test]
+    fn invalid_urls() {
+        // Wrong scheme
+        assert!(parse_url("https://totp/alice?secret=GEZDGNBVGY3TQOJQGEZDGNBVGY3TQOJQ").is_err());
+        // Unknown type
+        assert!(parse_url("otpauth://motp/alice?secret=GEZDGNBVGY3TQOJQGEZDGNBVGY3TQOJQ").is_err());
+        // Missing label
+        assert!(parse_url("otpauth://totp/?secret=GEZDGNBVGY3TQOJQGEZDGNBVGY3TQOJQ").is_err());
+        assert!(
+            parse_url("otpauth://totp/issuer:?secret=GEZDGNBVGY3TQOJQGEZDGNBVGY3TQOJQ").is_err()
+        );
+        // Missing or invalid secret
+        assert!(parse_url("otpauth://totp/alice").is_err());
+        assert!(parse_url("otpauth://totp/alice?secret=GEZDGNB0").is_err());
+        // Invalid parameters
+        assert!(parse_url("otpauth://totp/alice?secret=GEZDGNBV&algorithm=MD5").is_err());
+        assert!(parse_url("otpauth://totp/alice?secret=GEZDGNBV&digits=4").is_err());
+        assert!(parse_url("otpauth://totp/alice?secret=GEZDGNBV&period=0").is_err());
+    }
+
+    #[test]
+    fn url_round_trip() {
+        let urls = &[
+            "otpauth://totp/Example%20Co%3Aalice%40example.com?secret=GEZDGNBVGY3TQOJQGEZDGNBVGY3TQOJQ&issuer=Example%20Co&algorithm=SHA512&digits=8&period=60",
+            "otpauth://hotp/alice?secret=GEZDGNBVGY3TQOJQGEZDGNBVGY3TQOJQ&algorithm=SHA1&digits=6&counter=42",
+        ];
+        for url in urls {
+            let (id, record) = parse_url(url).unwrap();
+            assert_eq!(&to_url(&id, &record), url);
+        }
+    }
+}
@@ -0,0 +1,337 @@
+// Copyright (C) 2018 Alberto Coscia
+//
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at http://mozilla.org/MPL/2.0/.
+
+use lib::error::VaultError;
+use lib::types::Vault;
+use std::fmt;
+use strsim::damerau_levenshtein;
+
+/// The kind of record a search looks at. Passwords are listed first
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Kind {
+    Password,
+    Otp,
+}
+
+impl fmt::Display for Kind {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Kind::Password => write!(f, "password"),
+            Kind::Otp => write!(f, "OTP generator"),
+        }
+    }
+}
+
+/// A record matching a search query
+#[derive(Debug, PartialEq)]
+pub struct Match<'a> {
+    pub id: &'a str,
+    pub kind: Kind,
+    /// The field that matched best, such as "name" or "username"
+    pub field: &'static str,
+    pub score: u32,
+}
+
+/// Metadata matches rank below record ID ones
+const METADATA_PENALTY: u32 = 50;
+/// How many close matches to suggest when a record ID is unknown
+const SUGGESTIONS: usize = 5;
+
+/// Fuzzy searches the records of a vault
+/**
+ * Blueprint
+ *  1. Score the ID and the metadata of every record of the `kinds` kinds
+ *      i) Passwords: username, email and homepage
+ *      ii) OTP generators: issuer
+ *  2. Keep the best scoring field of every matching record
+ *  3. Sort by descending score, then by kind and ID
+ */
+pub fn search<'a>(vault: &'a Vault, query: &str, kinds: &[Kind]) -> Vec<Match<'a>> {
+    tracepoint!();
+    let mut matches = Vec::new();
+
+    // (1.i)
+    if kinds.contains(&Kind::Password) {
+        for (id, record) in &vault.passwords {
+            let fields = vec![
+                ("username", record.username.as_ref()),
+                ("email", record.email.as_ref()),
+                ("home", record.home.as_ref()),
+            ];
+            matches.extend(best_match(query, id, Kind::Password, fields));
+        }
+    }
+
+    // (1.ii)
+    if kinds.contains(&Kind::Otp) {
+        for (id, record) in &vault.otps {
+            let fields = vec![("issuer", record.issuer())];
+            matches.extend(best_match(query, id, Kind::Otp, fields));
+        }
+    }
+
+    // (3)
+    matches.sort_by(|a, b| {
+        b.score
+            .cmp(&a.score)
+            .then_with(|| a.kind.cmp(&b.kind))
+            .then_with(|| a.id.cmp(b.id))
+    });
+    matches
+}
+
+/// Returns `record_id` if a record of `kind` has that ID, or the ID of the
+/// only record whose name, or one of its folders, starts with it. Otherwise,
+/// the error suggests the closest matches
+pub fn resolve<'a>(
+    vault: &'a Vault,
+    record_id: &'a str,
+    kind: Kind,
+) -> Result<&'a str, VaultError> {
+    tracepoint!();
+    let exists = match kind {
+        Kind::Password => vault.passwords.contains_key(record_id),
+        Kind::Otp => vault.otps.contains_key(record_id),
+    };
+    if exists {
+        return Ok(record_id);
+    }
+
+    let matches = search(vault, record_id, &[kind]);
+    // Callers reveal secrets, so typos and metadata matches are only
+    // suggested, never used
+    let prefixed: Vec<&Match> = matches
+        .iter()
+        .filter(|m| is_prefix(record_id, m.id))
+        .collect();
+    match (prefixed.len(), matches.len()) {
+        (_, 0) => Err(VaultError::UnknownRecord),
+        (1, _) => Ok(prefixed[0].id),
+        _ => Err(VaultError::AmbiguousRecord(
+            matches
+                .iter()
+                .take(SUGGESTIONS)
+                .map(|m| m.id)
+                .collect::<Vec<_>>()
+                .join(", "),
+        )),
+    }
+}
+
+/// Whether a record ID, or one of its slash-separated parts, starts with
+/// `query`, ignoring case
+fn is_prefix(query: &str, id: &str) -> bool {
+    let query = query.trim().to_lowercase();
+    let id = id.to_lowercase();
+    !query.is_empty() && (id.starts_with(&query) || id.split('/').any(|p| p.starts_with(&query)))
+}
+
+/// Scores a record ID and its metadata, returning the best match, if any
+fn best_match<'a>(
+    query: &str,
+    id: &'a str,
+    kind: Kind,
+    fields: Vec<(&'static str, Option<&String>)>,
+) -> Option<Match<'a>> {
+    let id_score = score(query, id).map(|s| ("name", s));
+    let field_scores = fields.into_iter().filter_map(|(field, value)| {
+        value
+            .and_then(|v| score(query, v))
+            .map(|s| (field, s.saturating_sub(METADATA_PENALTY)))
+    });
+
+    id_score
+        .into_iter()
+        .chain(field_scores)
+        // Earlier fields win ties
+        .fold(
+            None,
+            |best: Option<(&str, u32)>, (field, score)| match best {
+                Some((_, best_score)) if best_score >= score => best,
+                _ => Some((field, score)),
+            },
+        )
+        .map(|(field, score)| Match {
+            id,
+            kind,
+            field,
+            score,
+        })
+}
+
+/// Scores how well `text` matches `query`, ignoring case, or `None` if it
+/// doesn't
+/**
+ * Blueprint
+ *  1. Exact matches score best, then prefixes and substrings, the earlier
+ *     and the longer the better
+ *  2. Then come texts containing the characters of the query in order,
+ *     the fewer the gaps the better
+ *  3. Then come texts, or slash-separated parts of them, within a few typos
+ *     of the query
+ */
+fn score(query: &str, text: &str) -> Option<u32> {
+    let query = query.trim().to_lowercase();
+    let text = text.to_lowercase();
+    if query.is_empty() {
+        return None;
+    }
+    let extra = text.chars().count().saturating_sub(query.chars().count()) as u32;
+
+    // (1)
+    if text == query {
+        return Some(1000);
+    }
+    if text.starts_with(&query) {
+        return Some(900u32.saturating_sub(extra).max(800));
+    }
+    if let Some(i) = text.find(&query) {
+        return Some(800u32.saturating_sub(i as u32 + extra).max(600));
+    }
+
+    // (2)
+    if let Some(gaps) = subsequence_gaps(&query, &text) {
+        return Some(600u32.saturating_sub(10 * gaps).max(400));
+    }
+
+    // (3)
+    let allowed = (query.chars().count() / 4).max(1);
+    let typos = text
+        .split('/')
+        .chain(Some(&text[..]))
+        .map(|part| damerau_levenshtein(&query, part))
+        .min()
+        .unwrap_or(usize::MAX);
+    if typos <= allowed {
+        Some(300 - 50 * (typos as u32).min(5))
+    } else {
+        None
+    }
+}
+
+/// Counts the characters skipped when matching `query` as a subsequence of
+/// `text`, or `None` if it isn't one
+fn subsequence_gaps(query: &str, text: &str) -> Option<u32> {
+    let mut text = text.chars();
+    let mut gaps = 0;
+    for q in query.chars() {
+        loop {
+            match text.next() {
+                Some(t) if t == q => break,
+                Some(_) => gaps += 1,
+                None => return None,
+            }
+        }
+    }
+
+    Some(gaps)
+}
+
+#[cfg(test)]
+mod tests {
+    use lib::error::VaultError;
+    use lib::search::{resolve, search, Kind};
+    use lib::types::{HmacAlgorithm, OtpRecord, Record, Vault};
+
+    fn vault() -> Vault {
+        let mut vault = Vault::default();
+        for (id, username) in &[
+            ("work/github", Some("alice")),
+            ("personal/github", None),
+            ("work/aws/root", Some("admin")),
+            ("bank", Some("alice@example.com")),
+        ] {
+            vault.passwords.insert(
+                id.to_string(),
+                Record::new(
+                    "hunter2".to_string(),
+                    username.map(|u| u.to_string()),
+                    None,
+                    None,
+                ),
+            );
+        }
+        vault.otps.insert(
+            "work/aws/root".to_string(),
+            OtpRecord::new_totp(
+                "GEZDGNBVGY3TQOJQGEZDGNBVGY3TQOJQ".to_string(),
+                Some("Amazon".to_string()),
+                HmacAlgorithm::SHA1,
+                6,
+                30,
+            ),
+        );
+        vault
+    }
+
+    fn ids<'a>(vault: &'a Vault, query: &str, kinds: &[Kind]) -> Vec<(&'a str, &'static str)> {
+        search(vault, query, kinds)
+            .into_iter()
+            .map(|m| (m.id, m.field))
+            .collect()
+    }
+
+    #[test]
+    fn ranking() {
+        let vault = vault();
+        let both = [Kind::Password, Kind::Otp];
+
+        // Substrings rank before subsequences, which rank before typos. Earlier
+        // substrings rank first
+        assert_eq!(
+            ids(&vault, "GitHub", &both),
+            vec![("work/github", "name"), ("personal/github", "name")]
+        );
+        assert_eq!(ids(&vault, "wgh", &both), vec![("work/github", "name")]);
+        assert_eq!(
+            ids(&vault, "githbu", &both),
+            vec![("personal/github", "name"), ("work/github", "name")]
+        );
+
+        // Metadata matches, exact ones first
+        assert_eq!(
+            ids(&vault, "alice", &both),
+            vec![("work/github", "username"), ("bank", "username")]
+        );
+        assert_eq!(
+            ids(&vault, "amazon", &[Kind::Otp]),
+            vec![("work/aws/root", "issuer")]
+        );
+        assert!(ids(&vault, "nothing like it", &both).is_empty());
+    }
+
+    #[test]
+    fn resolution() {
+        let vault = vault();
+
+        assert_eq!(
+            resolve(&vault, "work/aws/root", Kind::Password).unwrap(),
+            "work/aws/root"
+        );
+        assert_eq!(resolve(&vault, "ban", Kind::Password).unwrap(), "bank");
+        assert_eq!(resolve(&vault, "aws", Kind::Otp).unwrap(), "work/aws/root");
+        assert_eq!(
+            resolve(&vault, "work/aw", Kind::Password).unwrap(),
+            "work/aws/root"
+        );
+        // Typos and metadata matches are only suggested
+        match resolve(&vault, "bnak", Kind::Password) {
+            Err(VaultError::AmbiguousRecord(suggestions)) => assert_eq!(suggestions, "bank"),
+            _ => panic!("Expected a suggestion"),
+        }
+        assert!(resolve(&vault, "amazon", Kind::Otp).is_err());
+        match resolve(&vault, "github", Kind::Password) {
+            Err(VaultError::AmbiguousRecord(suggestions)) => {
+                assert_eq!(suggestions, "work/github, personal/github")
+            }
+            _ => panic!("Expected an ambiguous record"),
+        }
+        match resolve(&vault, "bank", Kind::Otp) {
+            Err(VaultError::UnknownRecord) => {}
+            _ => panic!("Expected an unknown record"),
+        }
+    }
+}
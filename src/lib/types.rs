@@ -4,25 +4,45 @@
 // License, v. 2.0. If a copy of the MPL was not distributed with this
 // file, You can obtain one at http://mozilla.org/MPL/2.0/.
 
+use chrono::{DateTime, Utc};
 use lib::error::{OtpError, VaultError};
 use lib::otp;
 use ring::digest;
+use serde::Serialize;
+use serde_json::{self, Value};
 use std::clone::Clone;
-use std::collections::hash_map::Entry;
-use std::collections::HashMap;
+use std::collections::btree_map::Entry;
+use std::collections::BTreeMap;
 use std::str::FromStr;
 
-#[derive(Serialize, Deserialize)]
+#[derive(Serialize, Deserialize, Clone, Default)]
 pub struct Vault {
-    pub passwords: HashMap<String, Record>,
-    pub otps: HashMap<String, OtpRecord>,
+    pub passwords: BTreeMap<String, Record>,
+    pub otps: BTreeMap<String, OtpRecord>,
+    /// The maximum age, in days, of the passwords with a tag
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub max_age: BTreeMap<String, u32>,
 }
 
 impl Vault {
-    pub fn add_record(&mut self, record: Record, record_id: String) -> Result<(), VaultError> {
+    /// A vault holding a record for each of the (ID, password) pairs, to
+    /// build test fixtures
+    #[cfg(test)]
+    pub fn with_passwords(passwords: &[(&str, &str)]) -> Vault {
+        let mut vault = Vault::default();
+        for (id, password) in passwords {
+            let record = Record::new(password.to_string(), None, None, None);
+            vault.add_record(record, id.to_string()).unwrap();
+        }
+        vault
+    }
+
+    pub fn add_record(&mut self, mut record: Record, record_id: String) -> Result<(), VaultError> {
         tracepoint!();
         match self.passwords.entry(record_id) {
             Entry::Vacant(entry) => {
+                record.timestamps.touch(Utc::now());
+                record.password_changed = record.password_changed.or(record.timestamps.created);
                 entry.insert(record);
                 Ok(())
             }
@@ -32,12 +52,13 @@ impl Vault {
 
     pub fn add_otp_record(
         &mut self,
-        record: OtpRecord,
+        mut record: OtpRecord,
         record_id: String,
     ) -> Result<(), VaultError> {
         tracepoint!();
         match self.otps.entry(record_id) {
             Entry::Vacant(entry) => {
+                record.timestamps_mut().touch(Utc::now());
                 entry.insert(record);
                 Ok(())
             }
@@ -45,6 +66,37 @@ impl Vault {
         }
     }
 
+    /// Adds a password record, handling an ID that is already in use
+    /// according to `policy`
+    ///
+    /// Returns the ID the record was stored as, or `None` if it was skipped
+    pub fn import_record(
+        &mut self,
+        mut record: Record,
+        record_id: String,
+        policy: ConflictPolicy,
+    ) -> Option<String> {
+        tracepoint!();
+        record.timestamps.import(Utc::now());
+        record.password_changed = record.password_changed.or(record.timestamps.created);
+        import_into(&mut self.passwords, record, record_id, policy)
+    }
+
+    /// Adds an OTP record, handling an ID that is already in use according to
+    /// `policy`
+    ///
+    /// Returns the ID the record was stored as, or `None` if it was skipped
+    pub fn import_otp_record(
+        &mut self,
+        mut record: OtpRecord,
+        record_id: String,
+        policy: ConflictPolicy,
+    ) -> Option<String> {
+        tracepoint!();
+        record.timestamps_mut().import(Utc::now());
+        import_into(&mut self.otps, record, record_id, policy)
+    }
+
     pub fn remove_record(&mut self, record_id: String) -> Result<(), VaultError> {
         tracepoint!();
         match self.passwords.entry(record_id) {
@@ -67,6 +119,129 @@ impl Vault {
         }
     }
 
+    /// Moves a record, or a folder and its contents, to a new name. Passwords
+    /// and OTP generators are moved together
+    ///
+    /// Returns the number of records that were moved
+    pub fn move_records(&mut self, from: &str, to: &str) -> Result<usize, VaultError> {
+        tracepoint!();
+        let from = from.trim_end_matches('/');
+        let to = to.trim_end_matches('/');
+        if from.is_empty() || to.is_empty() {
+            return Err(VaultError::InvalidRecordId);
+        }
+
+        let passwords = folder_ids(&self.passwords, from);
+        let otps = folder_ids(&self.otps, from);
+        if passwords.is_empty() && otps.is_empty() {
+            return Err(VaultError::UnknownRecord);
+        }
+        // "from/foo" becomes "to/foo"
+        let rename = |id: &str| format!("{}{}", to, &id[from.len()..]);
+        check_move(&self.passwords, &passwords, rename)?;
+        check_move(&self.otps, &otps, rename)?;
+
+        let moved = passwords.len() + otps.len();
+        move_into(&mut self.passwords, passwords, rename);
+        move_into(&mut self.otps, otps, rename);
+
+        Ok(moved)
+    }
+
+    /// Copies a record, or a folder and its contents, to a new name in
+    /// another vault. Passwords and OTP generators are copied together
+    ///
+    /// Returns the number of records that were copied
+    pub fn copy_records_to(
+        &self,
+        from: &str,
+        dest: &mut Vault,
+        to: &str,
+    ) -> Result<usize, VaultError> {
+        tracepoint!();
+        let from = from.trim_end_matches('/');
+        let to = to.trim_end_matches('/');
+        if from.is_empty() || to.is_empty() {
+            return Err(VaultError::InvalidRecordId);
+        }
+
+        let passwords = folder_ids(&self.passwords, from);
+        let otps = folder_ids(&self.otps, from);
+        if passwords.is_empty() && otps.is_empty() {
+            return Err(VaultError::UnknownRecord);
+        }
+        // "from/foo" becomes "to/foo"
+        let rename = |id: &str| format!("{}{}", to, &id[from.len()..]);
+        let taken = passwords
+            .iter()
+            .map(|id| rename(id))
+            .find(|id| dest.passwords.contains_key(id))
+            .or_else(|| {
+                otps.iter()
+                    .map(|id| rename(id))
+                    .find(|id| dest.otps.contains_key(id))
+            });
+        if let Some(new_id) = taken {
+            return Err(VaultError::AlreadyExists(new_id));
+        }
+
+        let copied = passwords.len() + otps.len();
+        for id in passwords {
+            dest.passwords
+                .insert(rename(&id), self.passwords[&id].clone());
+        }
+        for id in otps {
+            dest.otps.insert(rename(&id), self.otps[&id].clone());
+        }
+
+        Ok(copied)
+    }
+
+    /// Removes every password and OTP generator named `record_id`. If
+    /// `recursive`, also removes every record in the `record_id` folder
+    ///
+    /// Returns the number of records that were removed
+    pub fn remove_records(
+        &mut self,
+        record_id: &str,
+        recursive: bool,
+    ) -> Result<usize, VaultError> {
+        tracepoint!();
+        let record_id = record_id.trim_end_matches('/');
+        let (passwords, otps) = if recursive {
+            (
+                folder_ids(&self.passwords, record_id),
+                folder_ids(&self.otps, record_id),
+            )
+        } else {
+            let exact = |ids: Vec<String>| ids.into_iter().filter(|id| id == record_id).collect();
+            (
+                exact(folder_ids(&self.passwords, record_id)),
+                exact(folder_ids(&self.otps, record_id)),
+            )
+        };
+
+        if passwords.is_empty() && otps.is_empty() {
+            let is_folder = !folder_ids(&self.passwords, record_id).is_empty()
+                || !folder_ids(&self.otps, record_id).is_empty();
+            return Err(if is_folder {
+                VaultError::IsFolder(record_id.to_string())
+            } else {
+                VaultError::UnknownRecord
+            });
+        }
+
+        let removed = passwords.len() + otps.len();
+        for id in passwords {
+            self.passwords.remove(&id);
+        }
+        for id in otps {
+            self.otps.remove(&id);
+        }
+
+        Ok(removed)
+    }
+
     pub fn get_record(&self, record_id: &str) -> Result<&Record, VaultError> {
         tracepoint!();
         if let Some(record) = self.passwords.get(record_id) {
@@ -85,24 +260,399 @@ impl Vault {
         }
     }
 
-    pub fn display(&self, disclose: bool, depth: usize) -> String {
+    pub fn get_record_mut(&mut self, record_id: &str) -> Result<&mut Record, VaultError> {
+        tracepoint!();
+        self.passwords
+            .get_mut(record_id)
+            .ok_or(VaultError::UnknownRecord)
+    }
+
+    pub fn get_otp_record_mut(&mut self, record_id: &str) -> Result<&mut OtpRecord, VaultError> {
+        tracepoint!();
+        self.otps
+            .get_mut(record_id)
+            .ok_or(VaultError::UnknownRecord)
+    }
+
+    /// Applies `edit` to a password record and records the modification, and
+    /// whether the password changed
+    pub fn edit_record<F>(&mut self, record_id: &str, edit: F) -> Result<(), VaultError>
+    where
+        F: FnOnce(&mut Record),
+    {
+        tracepoint!();
+        let record = self.get_record_mut(record_id)?;
+        let password = record.password.clone();
+        edit(record);
+        let now = Utc::now();
+        record.timestamps.touch(now);
+        if record.password != password {
+            record.password_changed = Some(now);
+        }
+        Ok(())
+    }
+
+    /// Applies `edit` to an OTP record and records the modification
+    pub fn edit_otp_record<F>(&mut self, record_id: &str, edit: F) -> Result<(), VaultError>
+    where
+        F: FnOnce(&mut OtpRecord),
+    {
+        tracepoint!();
+        let record = self.get_otp_record_mut(record_id)?;
+        edit(record);
+        record.timestamps_mut().touch(Utc::now());
+        Ok(())
+    }
+
+    /// Renders the records of the vault matching `filter` as a tree,
+    /// grouping slash-separated record IDs by folder
+    pub fn display(&self, disclose: bool, depth: usize, filter: &Filter) -> String {
         let mut buf = String::new();
         tree_add_element(&mut buf, "Passwords", depth);
-
-        self.passwords.iter().for_each(|record| {
-            tree_add_element(&mut buf, record.0, depth + 1);
-            buf += &record.1.display(disclose, depth + 2);
+        buf += &display_folders(&self.passwords, filter, depth + 1, |record, depth| {
+            record.display(disclose, depth)
         });
 
         tree_add_element(&mut buf, "OTPs", depth);
-
-        self.otps.iter().for_each(|record| {
-            tree_add_element(&mut buf, record.0, depth + 1);
-            buf += &record.1.display(disclose, depth + 2);
+        buf += &display_folders(&self.otps, filter, depth + 1, |record, depth| {
+            record.display(disclose, depth)
         });
 
         buf
     }
+
+    /// Serializes the records of the vault matching `filter` to JSON, in its
+    /// order. Passwords, secret fields and OTP secrets are left out unless
+    /// `disclose`
+    pub fn display_json(
+        &self,
+        disclose: bool,
+        filter: &Filter,
+    ) -> Result<String, serde_json::Error> {
+        let (password_secrets, otp_secrets): (&[&str], &[&str]) = if disclose {
+            (&[], &[])
+        } else {
+            (&["password"], &["secret"])
+        };
+        let mut passwords = list_json(&self.passwords, filter, password_secrets)?;
+        if !disclose {
+            // Secret fields keep their names, but lose their values
+            for record in passwords.as_array_mut().into_iter().flatten() {
+                let fields = record.get_mut("fields").and_then(|f| f.as_object_mut());
+                for field in fields.into_iter().flat_map(|f| f.values_mut()) {
+                    if let Some(field) = field.as_object_mut() {
+                        if field.get("secret") == Some(&Value::Bool(true)) {
+                            field.remove("value");
+                        }
+                    }
+                }
+            }
+        }
+
+        serde_json::to_string_pretty(&Listing {
+            passwords,
+            otps: list_json(&self.otps, filter, otp_secrets)?,
+        })
+    }
+}
+
+/// Inserts a record into `records`, handling an ID that is already in use
+/// according to `policy`
+fn import_into<T>(
+    records: &mut BTreeMap<String, T>,
+    record: T,
+    record_id: String,
+    policy: ConflictPolicy,
+) -> Option<String> {
+    let record_id = if records.contains_key(&record_id) {
+        match policy {
+            ConflictPolicy::Skip => return None,
+            ConflictPolicy::Overwrite => record_id,
+            // Find the first free "id-N" slot
+            ConflictPolicy::Rename => (2..)
+                .map(|n| format!("{}-{}", record_id, n))
+                .find(|id| !records.contains_key(id))
+                .unwrap(),
+        }
+    } else {
+        record_id
+    };
+
+    records.insert(record_id.clone(), record);
+    Some(record_id)
+}
+
+/// Whether a record ID is `folder` itself or lies in it, such as
+/// "work/aws/root" in "work" or "work/aws"
+pub fn in_folder(record_id: &str, folder: &str) -> bool {
+    let folder = folder.trim_end_matches('/');
+    record_id == folder
+        || (record_id.starts_with(folder) && record_id[folder.len()..].starts_with('/'))
+}
+
+/// Returns the IDs of the records in a folder
+fn folder_ids<T>(records: &BTreeMap<String, T>, folder: &str) -> Vec<String> {
+    records
+        .keys()
+        .filter(|id| in_folder(id, folder))
+        .cloned()
+        .collect()
+}
+
+/// Bails if moving `ids` would overwrite a record that is not being moved
+fn check_move<T, F>(
+    records: &BTreeMap<String, T>,
+    ids: &[String],
+    rename: F,
+) -> Result<(), VaultError>
+where
+    F: Fn(&str) -> String,
+{
+    for id in ids {
+        let new_id = rename(id);
+        if records.contains_key(&new_id) && !ids.contains(&new_id) {
+            return Err(VaultError::AlreadyExists(new_id));
+        }
+    }
+
+    Ok(())
+}
+
+fn move_into<T, F>(records: &mut BTreeMap<String, T>, ids: Vec<String>, rename: F)
+where
+    F: Fn(&str) -> String,
+{
+    // Take all records out first, as old and new IDs may overlap
+    let moved: Vec<(String, T)> = ids
+        .into_iter()
+        .filter_map(|id| records.remove(&id).map(|record| (rename(&id), record)))
+        .collect();
+    records.extend(moved);
+}
+
+/// Restricts and orders the records rendered by `Vault::display`
+#[derive(Debug, Default)]
+pub struct Filter<'a> {
+    /// Only render the records in this folder
+    pub folder: Option<&'a str>,
+    /// Only render the records with this tag, ignoring its case
+    pub tag: Option<&'a str>,
+    /// How to order the records
+    pub sort: SortOrder,
+}
+
+/// How `Vault::display` orders records
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub enum SortOrder {
+    /// By name, grouped by folder
+    #[default]
+    Name,
+    /// By creation date, most recent first
+    Created,
+    /// By modification date, most recent first
+    Modified,
+    /// By last use, most recent first
+    LastUsed,
+}
+
+impl SortOrder {
+    /// The timestamp records are sorted by, if any
+    fn timestamp(self, timestamps: &Timestamps) -> Option<DateTime<Utc>> {
+        match self {
+            SortOrder::Name => None,
+            SortOrder::Created => timestamps.created,
+            SortOrder::Modified => timestamps.modified,
+            SortOrder::LastUsed => timestamps.last_used,
+        }
+    }
+}
+
+impl FromStr for SortOrder {
+    type Err = VaultError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match &s.to_ascii_lowercase()[..] {
+            "name" => Ok(SortOrder::Name),
+            "created" => Ok(SortOrder::Created),
+            "modified" => Ok(SortOrder::Modified),
+            "last-used" => Ok(SortOrder::LastUsed),
+            _ => Err(VaultError::UnknownSortOrder),
+        }
+    }
+}
+
+impl<'a> Filter<'a> {
+    fn matches<T: Listed>(&self, record_id: &str, record: &T) -> bool {
+        self.folder.map_or(true, |f| in_folder(record_id, f))
+            && self.tag.map_or(true, |tag| {
+                record.tags().iter().any(|t| t.eq_ignore_ascii_case(tag))
+            })
+    }
+}
+
+/// Records that `Vault::display` can filter and sort
+trait Listed {
+    fn tags(&self) -> &[String];
+    fn timestamps(&self) -> &Timestamps;
+}
+
+impl Listed for Record {
+    fn tags(&self) -> &[String] {
+        &self.tags
+    }
+
+    fn timestamps(&self) -> &Timestamps {
+        &self.timestamps
+    }
+}
+
+impl Listed for OtpRecord {
+    fn tags(&self) -> &[String] {
+        OtpRecord::tags(self)
+    }
+
+    fn timestamps(&self) -> &Timestamps {
+        OtpRecord::timestamps(self)
+    }
+}
+
+/// Renders records as a tree of slash-separated folders sorted by name, or as
+/// a flat list, most recent first, when sorting by date
+fn display_folders<T, F>(
+    records: &BTreeMap<String, T>,
+    filter: &Filter,
+    depth: usize,
+    display: F,
+) -> String
+where
+    T: Listed,
+    F: Fn(&T, usize) -> String,
+{
+    let flat = filter.sort != SortOrder::Name;
+    let mut buf = String::new();
+    // The folders the previous record was in
+    let mut open: Vec<&str> = Vec::new();
+    for id in listed_ids(records, filter) {
+        let mut path: Vec<&str> = if flat {
+            vec![id]
+        } else {
+            id.split('/').collect()
+        };
+        // Safe unwrap because split always yields at least one item
+        let name = path.pop().unwrap();
+
+        // Close the folders this record is not in and open the new ones
+        let common = open.iter().zip(&path).take_while(|(a, b)| a == b).count();
+        open.truncate(common);
+        for part in &path[common..] {
+            tree_add_element(&mut buf, &format!("{}/", part), depth + open.len());
+            open.push(part);
+        }
+
+        tree_add_element(&mut buf, name, depth + open.len());
+        buf += &display(&records[id], depth + open.len() + 1);
+    }
+
+    buf
+}
+
+/// The JSON rendering of `Vault::display_json`
+#[derive(Serialize)]
+struct Listing {
+    passwords: Value,
+    otps: Value,
+}
+
+/// Returns the IDs of the records matching `filter`, in its order
+fn listed_ids<'a, T: Listed>(records: &'a BTreeMap<String, T>, filter: &Filter) -> Vec<&'a String> {
+    let mut ids: Vec<&String> = records
+        .iter()
+        .filter(|(id, record)| filter.matches(id, *record))
+        .map(|(id, _)| id)
+        .collect();
+    if filter.sort == SortOrder::Name {
+        ids.sort_by(|a, b| a.split('/').cmp(b.split('/')));
+    } else {
+        // Records without the timestamp go last, as `None` is the lowest
+        ids.sort_by(|a, b| {
+            let time = |id: &String| filter.sort.timestamp(records[id].timestamps());
+            time(b).cmp(&time(a)).then_with(|| a.cmp(b))
+        });
+    }
+
+    ids
+}
+
+/// Serializes the records matching `filter` to a JSON array, adding their IDs
+/// and removing the keys that hold secrets
+fn list_json<T: Listed + Serialize>(
+    records: &BTreeMap<String, T>,
+    filter: &Filter,
+    secrets: &[&str],
+) -> Result<Value, serde_json::Error> {
+    let mut list = Vec::new();
+    for id in listed_ids(records, filter) {
+        let mut value = serde_json::to_value(&records[id])?;
+        if let Value::Object(ref mut object) = value {
+            for key in secrets {
+                object.remove(*key);
+            }
+            object.insert("id".to_string(), Value::String(id.clone()));
+        }
+        list.push(value);
+    }
+
+    Ok(Value::Array(list))
+}
+
+/// Adds the timestamps, the tags and the notes of a record, one line at a
+/// time, to a tree
+fn tree_add_metadata(
+    buf: &mut String,
+    timestamps: &Timestamps,
+    tags: &[String],
+    notes: Option<&String>,
+    depth: usize,
+) {
+    // Records that were never modified after being created only show the latter
+    let modified = timestamps
+        .modified
+        .filter(|m| Some(*m) != timestamps.created);
+    for (label, time) in &[
+        ("Created", timestamps.created),
+        ("Modified", modified),
+        ("Last used", timestamps.last_used),
+    ] {
+        if let Some(time) = time {
+            let time = time.format("%Y-%m-%d %H:%M UTC");
+            tree_add_element(buf, &format!("{}: {}", label, time), depth);
+        }
+    }
+    if !tags.is_empty() {
+        tree_add_element(buf, &format!("Tags: {}", tags.join(", ")), depth);
+    }
+    if let Some(notes) = notes {
+        tree_add_element(buf, "Notes:", depth);
+        for line in notes.lines() {
+            tree_add_element(buf, line, depth + 1);
+        }
+    }
+}
+
+/// Adds tags to a record, ignoring empty and duplicate ones. Tags are case
+/// insensitive
+pub fn add_tags(tags: &mut Vec<String>, new: Vec<String>) {
+    for tag in new {
+        let tag = tag.trim().to_string();
+        if !tag.is_empty() && !tags.iter().any(|t| t.eq_ignore_ascii_case(&tag)) {
+            tags.push(tag);
+        }
+    }
+}
+
+/// Removes tags from a record, ignoring their case
+pub fn remove_tags(tags: &mut Vec<String>, old: &[String]) {
+    tags.retain(|tag| !old.iter().any(|o| o.trim().eq_ignore_ascii_case(tag)));
 }
 
 fn tree_add_element(buf: &mut String, item: &str, depth: usize) {
@@ -117,12 +667,28 @@ fn tree_add_element(buf: &mut String, item: &str, depth: usize) {
     ));
 }
 
-#[derive(Serialize, Deserialize, Debug)]
+#[derive(Serialize, Deserialize, Debug, Clone)]
 pub struct Record {
     pub username: Option<String>,
     pub email: Option<String>,
     pub password: String,
     pub home: Option<String>,
+    pub notes: Option<String>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub tags: Vec<String>,
+    /// How many days this password may go without being changed, overriding
+    /// the policies of its tags
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max_age: Option<u32>,
+    /// When the password itself was last changed, which edits to the rest of
+    /// the record don't affect
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub password_changed: Option<DateTime<Utc>>,
+    #[serde(flatten)]
+    pub timestamps: Timestamps,
+    // Tables must be serialized after values
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub fields: BTreeMap<String, Field>,
 }
 
 impl Record {
@@ -137,6 +703,21 @@ impl Record {
             username,
             email,
             home,
+            notes: None,
+            tags: Vec::new(),
+            max_age: None,
+            password_changed: None,
+            timestamps: Timestamps::default(),
+            fields: BTreeMap::new(),
+        }
+    }
+
+    /// Returns the value of a custom field
+    pub fn get_field(&self, key: &str) -> Result<&str, VaultError> {
+        tracepoint!();
+        match self.fields.get(key) {
+            Some(field) => Ok(&field.value),
+            None => Err(VaultError::UnknownField(key.to_string())),
         }
     }
 
@@ -171,12 +752,83 @@ impl Record {
                 depth,
             );
         }
+        if let Some(max_age) = self.max_age {
+            tree_add_element(&mut buf, &format!("Max age: {} days", max_age), depth);
+        }
+        for (key, field) in &self.fields {
+            if disclose || !field.secret {
+                tree_add_element(&mut buf, &format!("{}: {}", key, field.value), depth);
+            }
+        }
+        tree_add_metadata(
+            &mut buf,
+            &self.timestamps,
+            &self.tags,
+            self.notes.as_ref(),
+            depth,
+        );
 
         buf
     }
 }
 
-#[derive(Serialize, Deserialize, Debug)]
+/// A custom field of a password record, such as a PIN or a security question.
+/// Secret fields are only displayed when disclosing secrets
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct Field {
+    pub value: String,
+    #[serde(default)]
+    pub secret: bool,
+}
+
+impl Field {
+    /// Parses a "key=value" field specification, trimming the key
+    pub fn parse(spec: &str, secret: bool) -> Result<(String, Field), VaultError> {
+        match spec.find('=') {
+            Some(i) if !spec[..i].trim().is_empty() => Ok((
+                spec[..i].trim().to_string(),
+                Field {
+                    value: spec[i + 1..].to_string(),
+                    secret,
+                },
+            )),
+            _ => Err(VaultError::InvalidField(spec.to_string())),
+        }
+    }
+}
+
+/// When a record was created, last modified and last used. Records stored
+/// by older versions of Sigil have none of these
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, Default, PartialEq)]
+pub struct Timestamps {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub created: Option<DateTime<Utc>>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub modified: Option<DateTime<Utc>>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub last_used: Option<DateTime<Utc>>,
+}
+
+impl Timestamps {
+    /// Records a modification, which is also the creation of a new record
+    pub fn touch(&mut self, now: DateTime<Utc>) {
+        self.created.get_or_insert(now);
+        self.modified = Some(now);
+    }
+
+    /// Records an import, keeping the dates carried over from the source
+    pub fn import(&mut self, now: DateTime<Utc>) {
+        self.created.get_or_insert(now);
+        self.modified.get_or_insert(now);
+    }
+
+    /// Records a use, such as a password being retrieved
+    pub fn mark_used(&mut self, now: DateTime<Utc>) {
+        self.last_used = Some(now);
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
 #[serde(tag = "type")]
 pub enum OtpRecord {
     Hotp {
@@ -184,6 +836,14 @@ pub enum OtpRecord {
         issuer: Option<String>,
         algorithm: HmacAlgorithm,
         digits: u32,
+        #[serde(default)]
+        counter: u64,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        notes: Option<String>,
+        #[serde(default, skip_serializing_if = "Vec::is_empty")]
+        tags: Vec<String>,
+        #[serde(flatten)]
+        timestamps: Timestamps,
     },
     Totp {
         secret: String,
@@ -191,10 +851,40 @@ pub enum OtpRecord {
         algorithm: HmacAlgorithm,
         period: u64,
         digits: u32,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        notes: Option<String>,
+        #[serde(default, skip_serializing_if = "Vec::is_empty")]
+        tags: Vec<String>,
+        #[serde(flatten)]
+        timestamps: Timestamps,
     },
 }
 
-#[derive(Serialize, Deserialize, Debug, Clone)]
+/// How to handle a record whose ID is already in use when importing it
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ConflictPolicy {
+    /// Keep the existing record and discard the new one
+    Skip,
+    /// Store the new record using the first free "id-N" ID
+    Rename,
+    /// Replace the existing record
+    Overwrite,
+}
+
+impl FromStr for ConflictPolicy {
+    type Err = VaultError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match &s.to_ascii_lowercase()[..] {
+            "skip" => Ok(ConflictPolicy::Skip),
+            "rename" => Ok(ConflictPolicy::Rename),
+            "overwrite" => Ok(ConflictPolicy::Overwrite),
+            _ => Err(VaultError::UnknownConflictPolicy),
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
 pub enum HmacAlgorithm {
     SHA1,
     SHA256,
@@ -239,6 +929,9 @@ impl OtpRecord {
             algorithm,
             digits,
             period,
+            notes: None,
+            tags: Vec::new(),
+            timestamps: Timestamps::default(),
         }
     }
     pub fn new_hotp(
@@ -246,20 +939,68 @@ impl OtpRecord {
         issuer: Option<String>,
         algorithm: HmacAlgorithm,
         digits: u32,
+        counter: u64,
     ) -> OtpRecord {
         OtpRecord::Hotp {
             secret,
             issuer,
             algorithm,
             digits,
+            counter,
+            notes: None,
+            tags: Vec::new(),
+            timestamps: Timestamps::default(),
+        }
+    }
+
+    pub fn issuer(&self) -> Option<&String> {
+        match self {
+            OtpRecord::Totp { issuer, .. } | OtpRecord::Hotp { issuer, .. } => issuer.as_ref(),
+        }
+    }
+
+    pub fn tags(&self) -> &[String] {
+        match self {
+            OtpRecord::Totp { tags, .. } | OtpRecord::Hotp { tags, .. } => tags,
+        }
+    }
+
+    pub fn tags_mut(&mut self) -> &mut Vec<String> {
+        match self {
+            OtpRecord::Totp { tags, .. } | OtpRecord::Hotp { tags, .. } => tags,
+        }
+    }
+
+    pub fn notes(&self) -> Option<&String> {
+        match self {
+            OtpRecord::Totp { notes, .. } | OtpRecord::Hotp { notes, .. } => notes.as_ref(),
+        }
+    }
+
+    pub fn notes_mut(&mut self) -> &mut Option<String> {
+        match self {
+            OtpRecord::Totp { notes, .. } | OtpRecord::Hotp { notes, .. } => notes,
+        }
+    }
+
+    pub fn timestamps(&self) -> &Timestamps {
+        match self {
+            OtpRecord::Totp { timestamps, .. } | OtpRecord::Hotp { timestamps, .. } => timestamps,
         }
     }
 
-    /// Generate a token for this record. `counter` is required for Otp::Hotp
-    /// and ignored by Otp::Totp
+    pub fn timestamps_mut(&mut self) -> &mut Timestamps {
+        match self {
+            OtpRecord::Totp { timestamps, .. } | OtpRecord::Hotp { timestamps, .. } => timestamps,
+        }
+    }
+
+    /// Generate a token for this record. Otp::Hotp uses the stored counter,
+    /// unless `counter` overrides it, and moves it past the one used.
+    /// `counter` is ignored by Otp::Totp
     ///
     /// u64 is the token validity time for Totp and u64::MAX for Hotp
-    pub fn generate_token(&self, counter: Option<u64>) -> Result<(String, u64), OtpError> {
+    pub fn generate_token(&mut self, counter: Option<u64>) -> Result<(String, u64), OtpError> {
         match self {
             OtpRecord::Totp {
                 secret,
@@ -268,7 +1009,7 @@ impl OtpRecord {
                 digits,
                 ..
             } => {
-                let (r, time) = otp::totp(0, *period, secret, *digits, &algorithm);
+                let (r, time) = otp::totp(0, *period, secret, *digits, algorithm)?;
                 // RFC 4226 Requires 6-digit values and suggests 7 and 8-digit
                 // values, so we 0-pad shorter numbers accordingly
                 Ok((
@@ -284,10 +1025,15 @@ impl OtpRecord {
                 secret,
                 algorithm,
                 digits,
+                counter: stored,
                 ..
             } => {
-                let counter = counter.ok_or(OtpError::NoCounterProvided)?;
-                let r = otp::hotp(secret, counter, *digits, &algorithm);
+                let counter = counter.unwrap_or(*stored);
+                let next = counter
+                    .checked_add(1)
+                    .ok_or(OtpError::CounterOverflow(counter))?;
+                let r = otp::hotp(secret, counter, *digits, algorithm)?;
+                *stored = next;
 
                 // RFC 4226 Requires 6-digit values and suggests 7 and 8-digit
                 // values, so we 0-pad shorter numbers accordingly
@@ -312,6 +1058,7 @@ impl OtpRecord {
                 period,
                 digits,
                 issuer,
+                ..
             } => {
                 tree_add_element(&mut buf, "Type: TOTP", depth);
                 if issuer.is_some() {
@@ -333,6 +1080,8 @@ impl OtpRecord {
                 algorithm,
                 digits,
                 issuer,
+                counter,
+                ..
             } => {
                 tree_add_element(&mut buf, "Type: HOTP", depth);
                 if issuer.is_some() {
@@ -344,12 +1093,416 @@ impl OtpRecord {
                 }
                 tree_add_element(&mut buf, &format!("Algorithm: {:?}", algorithm), depth);
                 tree_add_element(&mut buf, &format!("Digits: {}", digits), depth);
+                tree_add_element(&mut buf, &format!("Counter: {}", counter), depth);
                 if disclose {
                     tree_add_element(&mut buf, &format!("Secret: {}", secret), depth);
                 }
             }
         };
+        tree_add_metadata(
+            &mut buf,
+            self.timestamps(),
+            self.tags(),
+            self.notes(),
+            depth,
+        );
 
         buf
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use chrono::{DateTime, Utc};
+    use lib::types::{
+        add_tags, in_folder, remove_tags, ConflictPolicy, Field, Filter, HmacAlgorithm, OtpRecord,
+        Record, SortOrder, Timestamps, Vault,
+    };
+    use serde_json::{self, Value};
+    use toml;
+
+    fn record(issuer: &str) -> OtpRecord {
+        OtpRecord::new_totp(
+            "GEZDGNBVGY3TQOJQGEZDGNBVGY3TQOJQ".to_string(),
+            Some(issuer.to_string()),
+            HmacAlgorithm::SHA1,
+            6,
+            30,
+        )
+    }
+
+    fn issuer(vault: &Vault, record_id: &str) -> String {
+        match vault.get_otp_record(record_id).unwrap() {
+            OtpRecord::Totp { issuer, .. } => issuer.clone().unwrap(),
+            _ => unreachable!(),
+        }
+    }
+
+    #[test]
+    fn import_conflict_policies() {
+        let mut vault = Vault::default();
+        vault.add_otp_record(record("a"), "id".to_string()).unwrap();
+
+        let skipped = vault.import_otp_record(record("b"), "id".to_string(), ConflictPolicy::Skip);
+        assert_eq!(skipped, None);
+        assert_eq!(issuer(&vault, "id"), "a");
+
+        let renamed =
+            vault.import_otp_record(record("c"), "id".to_string(), ConflictPolicy::Rename);
+        assert_eq!(renamed.unwrap(), "id-2");
+        let renamed =
+            vault.import_otp_record(record("d"), "id".to_string(), ConflictPolicy::Rename);
+        assert_eq!(renamed.unwrap(), "id-3");
+        assert_eq!(issuer(&vault, "id-3"), "d");
+
+        let overwritten =
+            vault.import_otp_record(record("e"), "id".to_string(), ConflictPolicy::Overwrite);
+        assert_eq!(overwritten.unwrap(), "id");
+        assert_eq!(issuer(&vault, "id"), "e");
+        assert_eq!(vault.otps.len(), 3);
+    }
+
+    #[test]
+    fn hotp_counter() {
+        let mut record = OtpRecord::new_hotp(
+            "GEZDGNBVGY3TQOJQGEZDGNBVGY3TQOJQ".to_string(),
+            None,
+            HmacAlgorithm::SHA1,
+            6,
+            0,
+        );
+        // RFC 4226 test vectors
+        assert_eq!(record.generate_token(None).unwrap().0, "755224");
+        assert_eq!(record.generate_token(None).unwrap().0, "287082");
+        assert_eq!(record.generate_token(Some(9)).unwrap().0, "520489");
+        match record {
+            OtpRecord::Hotp { counter, .. } => assert_eq!(counter, 10),
+            _ => unreachable!(),
+        }
+        // The counter cannot wrap around
+        assert!(record.generate_token(Some(u64::MAX)).is_err());
+        match record {
+            OtpRecord::Hotp { counter, .. } => assert_eq!(counter, 10),
+            _ => unreachable!(),
+        }
+    }
+
+    fn folders() -> Vault {
+        let mut vault = Vault::default();
+        for id in &[
+            "work/aws/root",
+            "work/aws/ci",
+            "work/mail",
+            "workshop",
+            "personal",
+        ] {
+            // Inserted directly, so that records have no timestamps to display
+            vault.passwords.insert(
+                id.to_string(),
+                Record::new("hunter2".to_string(), None, None, None),
+            );
+        }
+        vault
+            .otps
+            .insert("work/aws/root".to_string(), record("aws"));
+        vault
+    }
+
+    #[test]
+    fn folder_membership() {
+        assert!(in_folder("work/aws/root", "work"));
+        assert!(in_folder("work/aws/root", "work/aws/"));
+        assert!(in_folder("work/aws/root", "work/aws/root"));
+        assert!(!in_folder("workshop", "work"));
+        assert!(!in_folder("work", "work/aws"));
+    }
+
+    #[test]
+    fn folder_display() {
+        let vault = folders();
+        let display = vault.display(
+            false,
+            0,
+            &Filter {
+                folder: Some("work"),
+                ..Filter::default()
+            },
+        );
+        assert!(display.starts_with(
+            "├─ Passwords\n\
+             │  ├─ work/\n\
+             │  │  ├─ aws/\n\
+             │  │  │  ├─ ci\n\
+             │  │  │  ├─ root\n\
+             │  │  ├─ mail\n\
+             ├─ OTPs\n\
+             │  ├─ work/\n\
+             │  │  ├─ aws/\n\
+             │  │  │  ├─ root\n\
+             │  │  │  │  ├─ Type: TOTP\n"
+        ));
+        assert!(!display.contains("workshop"));
+    }
+
+    #[test]
+    fn move_folders() {
+        let mut vault = folders();
+        assert_eq!(vault.move_records("work/aws", "cloud/").unwrap(), 3);
+        assert!(vault.get_record("cloud/root").is_ok());
+        assert!(vault.get_otp_record("cloud/root").is_ok());
+        assert!(vault.get_record("work/aws/root").is_err());
+        assert!(vault.get_record("work/mail").is_ok());
+
+        // Single records and name clashes
+        assert_eq!(vault.move_records("personal", "home/main").unwrap(), 1);
+        assert!(vault.move_records("work/mail", "workshop").is_err());
+        assert!(vault.move_records("unknown", "foo").is_err());
+        assert!(vault.get_record("work/mail").is_ok());
+    }
+
+    #[test]
+    fn copy_folders() {
+        let vault = folders();
+        let mut dest = Vault::default();
+        assert_eq!(
+            vault.copy_records_to("work/aws", &mut dest, "aws").unwrap(),
+            3
+        );
+        assert!(dest.get_record("aws/root").is_ok());
+        assert!(dest.get_otp_record("aws/root").is_ok());
+        assert!(dest.get_record("aws/ci").is_ok());
+        assert!(vault.get_record("work/aws/root").is_ok());
+
+        // Records are never overwritten
+        assert!(vault
+            .copy_records_to("work/aws/ci", &mut dest, "aws/ci")
+            .is_err());
+        assert!(vault.copy_records_to("unknown", &mut dest, "foo").is_err());
+        assert_eq!(dest.passwords.len(), 2);
+    }
+
+    #[test]
+    fn remove_folders() {
+        let mut vault = folders();
+        assert!(vault.remove_records("work/aws", false).is_err());
+        assert_eq!(vault.remove_records("work/aws/root", false).unwrap(), 2);
+        assert_eq!(vault.remove_records("work", true).unwrap(), 2);
+        assert!(vault.get_record("workshop").is_ok());
+        assert!(vault.remove_records("work", true).is_err());
+    }
+
+    #[test]
+    fn tags_and_notes() {
+        let mut vault = folders();
+        {
+            let record = vault.passwords.get_mut("personal").unwrap();
+            add_tags(
+                &mut record.tags,
+                vec!["home".to_string(), " ".to_string(), "Prod".to_string()],
+            );
+            add_tags(
+                &mut record.tags,
+                vec!["prod".to_string(), "work".to_string()],
+            );
+            remove_tags(&mut record.tags, &["HOME".to_string()]);
+            record.notes = Some("Line 1\nLine 2".to_string());
+        }
+        assert_eq!(vault.passwords["personal"].tags, vec!["Prod", "work"]);
+
+        let display = vault.display(
+            false,
+            0,
+            &Filter {
+                tag: Some("prod"),
+                ..Filter::default()
+            },
+        );
+        assert_eq!(
+            display,
+            "├─ Passwords\n\
+             │  ├─ personal\n\
+             │  │  ├─ Tags: Prod, work\n\
+             │  │  ├─ Notes:\n\
+             │  │  │  ├─ Line 1\n\
+             │  │  │  ├─ Line 2\n\
+             ├─ OTPs\n"
+        );
+    }
+
+    #[test]
+    fn custom_fields() {
+        let (key, field) = Field::parse(" PIN =1234=5", true).unwrap();
+        assert_eq!(key, "PIN");
+        assert_eq!(
+            field,
+            Field {
+                value: "1234=5".to_string(),
+                secret: true,
+            }
+        );
+        assert!(Field::parse("=1234", true).is_err());
+        assert!(Field::parse("PIN", true).is_err());
+
+        let mut record = Record::new("hunter2".to_string(), None, None, None);
+        record.fields.insert(key, field);
+        record.fields.insert(
+            "Key ID".to_string(),
+            Field::parse("Key ID=AKIA", false).unwrap().1,
+        );
+        assert_eq!(record.get_field("PIN").unwrap(), "1234=5");
+        assert!(record.get_field("pin").is_err());
+
+        assert_eq!(record.display(false, 0), "├─ Key ID: AKIA\n");
+        assert_eq!(
+            record.display(true, 0),
+            "├─ Password: hunter2\n├─ Key ID: AKIA\n├─ PIN: 1234=5\n"
+        );
+
+        let mut vault = Vault::default();
+        vault.add_record(record, "service".to_string()).unwrap();
+        let vault: Vault = toml::from_str(&toml::to_string(&vault).unwrap()).unwrap();
+        assert_eq!(vault.passwords["service"].fields.len(), 2);
+        assert!(vault.passwords["service"].fields["PIN"].secret);
+    }
+
+    #[test]
+    fn stable_serialization() {
+        let mut vault = Vault::default();
+        for id in &["zeta", "alpha", "work/aws", "beta"] {
+            vault.passwords.insert(
+                id.to_string(),
+                Record::new("hunter2".to_string(), None, None, None),
+            );
+        }
+        let serialized = toml::to_string(&vault).unwrap();
+        let headers: Vec<&str> = serialized.lines().filter(|l| l.starts_with('[')).collect();
+        assert_eq!(
+            headers,
+            vec![
+                "[passwords.alpha]",
+                "[passwords.beta]",
+                "[passwords.\"work/aws\"]",
+                "[passwords.zeta]",
+                "[otps]",
+            ]
+        );
+
+        let vault: Vault = toml::from_str(&serialized).unwrap();
+        assert_eq!(toml::to_string(&vault).unwrap(), serialized);
+    }
+
+    #[test]
+    fn timestamps() {
+        let time = |s: &str| s.parse::<DateTime<Utc>>().unwrap();
+        let mut vault = folders();
+        vault
+            .add_record(
+                Record::new("hunter2".to_string(), None, None, None),
+                "new".to_string(),
+            )
+            .unwrap();
+        assert!(vault.passwords["new"].timestamps.created.is_some());
+        assert!(vault.passwords["workshop"].timestamps.created.is_none());
+
+        {
+            let timestamps = &mut vault.get_record_mut("new").unwrap().timestamps;
+            *timestamps = Timestamps::default();
+            timestamps.touch(time("2018-07-22T10:00:00Z"));
+            timestamps.touch(time("2018-08-01T10:00:00Z"));
+            assert_eq!(timestamps.created, Some(time("2018-07-22T10:00:00Z")));
+            let mut imported = *timestamps;
+            imported.import(time("2018-09-01T10:00:00Z"));
+            assert_eq!(imported, *timestamps);
+            timestamps.mark_used(time("2018-08-02T10:00:00Z"));
+        }
+        vault
+            .get_record_mut("personal")
+            .unwrap()
+            .timestamps
+            .touch(time("2018-07-23T10:00:00Z"));
+
+        let display = vault.display(
+            false,
+            0,
+            &Filter {
+                sort: SortOrder::Created,
+                ..Filter::default()
+            },
+        );
+        assert!(display.starts_with(
+            "├─ Passwords\n\
+             │  ├─ personal\n\
+             │  │  ├─ Created: 2018-07-23 10:00 UTC\n\
+             │  ├─ new\n\
+             │  │  ├─ Created: 2018-07-22 10:00 UTC\n\
+             │  │  ├─ Modified: 2018-08-01 10:00 UTC\n\
+             │  │  ├─ Last used: 2018-08-02 10:00 UTC\n\
+             │  ├─ work/aws/ci\n"
+        ));
+
+        vault
+            .add_otp_record(record("new"), "new".to_string())
+            .unwrap();
+        let serialized = toml::to_string(&vault).unwrap();
+        assert!(serialized.contains("created = \"2018-07-22T10:00:00Z\""));
+        let vault: Vault = toml::from_str(&serialized).unwrap();
+        assert_eq!(
+            vault.passwords["new"].timestamps.last_used,
+            Some(time("2018-08-02T10:00:00Z"))
+        );
+        assert!(vault.otps["work/aws/root"].timestamps().created.is_none());
+        assert!(vault.otps["new"].timestamps().created.is_some());
+    }
+
+    #[test]
+    fn password_changed() {
+        let mut vault = folders();
+        vault
+            .add_record(
+                Record::new("hunter2".to_string(), None, None, None),
+                "new".to_string(),
+            )
+            .unwrap();
+        let created = vault.passwords["new"].timestamps.created;
+        assert_eq!(vault.passwords["new"].password_changed, created);
+
+        // Only changing the password counts
+        vault.get_record_mut("new").unwrap().password_changed = None;
+        vault
+            .edit_record("new", |r| r.tags.push("work".to_string()))
+            .unwrap();
+        assert!(vault.passwords["new"].password_changed.is_none());
+        vault
+            .edit_record("new", |r| r.password = "hunter3".to_string())
+            .unwrap();
+        let record = &vault.passwords["new"];
+        assert_eq!(record.password_changed, record.timestamps.modified);
+    }
+
+    #[test]
+    fn json_display() {
+        let mut vault = folders();
+        vault
+            .get_record_mut("personal")
+            .unwrap()
+            .fields
+            .insert("PIN".to_string(), Field::parse("PIN=1234", true).unwrap().1);
+        let filter = Filter {
+            folder: Some("personal"),
+            ..Filter::default()
+        };
+
+        let json: Value =
+            serde_json::from_str(&vault.display_json(false, &filter).unwrap()).unwrap();
+        let record = &json["passwords"][0];
+        assert_eq!(record["id"], "personal");
+        assert!(record.get("password").is_none());
+        assert!(record["fields"]["PIN"].get("value").is_none());
+        assert_eq!(json["otps"], Value::Array(Vec::new()));
+
+        let json: Value =
+            serde_json::from_str(&vault.display_json(true, &filter).unwrap()).unwrap();
+        assert_eq!(json["passwords"][0]["password"], "hunter2");
+        assert_eq!(json["passwords"][0]["fields"]["PIN"]["value"], "1234");
+    }
+}
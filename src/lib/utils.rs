@@ -4,10 +4,11 @@
 // License, v. 2.0. If a copy of the MPL was not distributed with this
 // file, You can obtain one at http://mozilla.org/MPL/2.0/.
 
-use gpgme::{Context, Key, Protocol};
+use gpgme::{Context, Protocol};
+use lib::backend::{Backend, BackendKind, Crypto};
 use lib::types::Vault;
 use std::error::Error;
-use std::fs::{write, File};
+use std::fs::{read, write, File};
 use std::path::PathBuf;
 use toml::{from_str, to_string};
 
@@ -30,34 +31,72 @@ pub fn unlock_file(path: &PathBuf, ctx: &mut Context) -> Result<String, Box<Erro
     Ok(output)
 }
 
-/// Parses an encrypted vault to `Vault`
-pub fn read_vault(path: &PathBuf, ctx: &mut Context) -> Result<Vault, Box<Error>> {
-    let string = unlock_file(&path, ctx)?;
-    let vault: Vault = from_str(&string)?;
+/// Parses an encrypted vault to `Vault`, using the backend that encrypted it
+pub fn read_vault(path: &PathBuf, ctx: &mut Crypto) -> Result<Vault, Box<Error>> {
+    let input = read(path)?;
+    let output = ctx.backend(BackendKind::detect(&input))?.decrypt(&input)?;
+    let vault: Vault = from_str(&String::from_utf8_lossy(&output))?;
 
     Ok(vault)
 }
 
-/// Serializes a `Vault` to an encrypted TOML file
+/// Serializes a `Vault` to an encrypted TOML file, using the backend that
+/// encrypted the existing file or GPG for new ones
 pub fn write_vault(
     path: &PathBuf,
     vault: &Vault,
-    ctx: &mut Context,
+    ctx: &mut Crypto,
     key: &str,
 ) -> Result<(), Box<Error>> {
-    let mut input: Vec<u8> = Vec::from(to_string(&vault)?);
-
-    let keys = vec![key];
-    let keys: Vec<Key> = ctx
-        .find_keys(keys)?
-        .filter_map(|x| x.ok())
-        .filter(|k| k.can_encrypt())
-        .collect();
+    let kind = BackendKind::detect_file(path).unwrap_or(BackendKind::Gpg);
+    write_vault_with(path, vault, ctx.backend(kind)?, key)
+}
 
-    let mut output: Vec<u8> = Vec::new();
-    ctx.encrypt(&keys, &mut input, &mut output)?;
-    let output = String::from_utf8_lossy(&output).into_owned();
+/// Serializes a `Vault` to a TOML file encrypted by a backend, reporting the
+/// keys it is encrypted to on stderr
+pub fn write_vault_with(
+    path: &PathBuf,
+    vault: &Vault,
+    backend: &mut Backend,
+    key: &str,
+) -> Result<(), Box<Error>> {
+    let input: Vec<u8> = Vec::from(to_string(&vault)?);
+    let encrypted = backend.encrypt(&input, key)?;
+    for key in &encrypted.keys {
+        eprintln!("Encrypting to {}", key);
+    }
+    for warning in &encrypted.warnings {
+        eprintln!("Warning: {}", warning);
+    }
 
-    write(path, output)?;
+    write(path, encrypted.ciphertext)?;
     Ok(())
 }
+
+#[cfg(test)]
+mod tests {
+    use lib::backend::Crypto;
+    use lib::types::{Record, Vault};
+    use lib::utils::{read_vault, write_vault};
+    use std::env;
+    use std::fs::{read_to_string, remove_file};
+
+    #[test]
+    fn vault_round_trip() {
+        let path = env::temp_dir().join("sigil-utils-round-trip.vault");
+        let mut ctx = Crypto::plaintext();
+        let mut vault = Vault::default();
+        let record = Record::new("hunter2".to_string(), Some("bob".to_string()), None, None);
+        vault.add_record(record, "Bob:service".to_string()).unwrap();
+        write_vault(&path, &vault, &mut ctx, "Sigil CI").unwrap();
+        assert!(read_to_string(&path)
+            .unwrap()
+            .starts_with("[passwords.\"Bob:service\"]"));
+
+        let vault = read_vault(&path, &mut ctx).unwrap();
+        let record = vault.get_record("Bob:service").unwrap();
+        assert_eq!(record.password, "hunter2");
+        assert_eq!(record.username, Some("bob".to_string()));
+        remove_file(&path).unwrap();
+    }
+}
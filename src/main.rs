@@ -15,11 +15,22 @@ extern crate failure;
 extern crate log;
 #[macro_use]
 extern crate serde_derive;
+extern crate age;
 extern crate base32;
+extern crate base64;
+extern crate chrono;
+extern crate csv;
 extern crate gpgme;
+extern crate image;
+extern crate keepass;
+extern crate qrcode;
 extern crate rand;
 extern crate ring;
+extern crate rpassword;
+extern crate rqrr;
 extern crate serde;
+extern crate serde_json;
+extern crate strsim;
 extern crate toml;
 extern crate url;
 
@@ -36,6 +47,7 @@ mod cli;
 mod lib;
 
 use cli::args::{match_args, Sigil};
+use std::process;
 use structopt::StructOpt;
 
 fn main() {
@@ -48,5 +60,6 @@ fn main() {
     // Sort of pretty print any error
     if let Err(err) = res {
         eprintln!("Error: {}", err);
+        process::exit(1);
     }
 }
@@ -0,0 +1,628 @@
+// Copyright (C) 2018 Alberto Coscia
+//
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at http://mozilla.org/MPL/2.0/.
+
+// Vaults are left unencrypted so that no keyring is needed, which only builds
+// with the `test-plaintext` feature allow. Run with
+// `cargo test --features test-plaintext`
+
+extern crate ring;
+
+use ring::{digest, hmac};
+use std::env;
+use std::fs::{copy, create_dir_all, read_to_string, remove_dir_all};
+use std::path::{Path, PathBuf};
+use std::process::{Command, Output};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// The secret of the RFC 4226 test vectors, `GEZDGNBVGY3TQOJQGEZDGNBVGY3TQOJQ`
+/// in base32
+const SECRET: &[u8] = b"12345678901234567890";
+
+/// A temporary directory holding the vault of a test
+struct Test {
+    dir: PathBuf,
+}
+
+impl Test {
+    fn new(name: &str) -> Test {
+        let dir = env::temp_dir().join(format!("sigil-cli-{}-{}", std::process::id(), name));
+        create_dir_all(&dir).unwrap();
+        Test { dir }
+    }
+
+    fn vault(&self) -> PathBuf {
+        self.dir.join("test.vault")
+    }
+
+    /// Uses one of the vaults in `tests/inputs`
+    fn move_input(&self, input: &str) {
+        copy(fixture("inputs", input), self.vault()).unwrap();
+    }
+
+    fn command(&self, args: &[&str]) -> Command {
+        let mut command = Command::new(env!("CARGO_BIN_EXE_sigil"));
+        command
+            .args(args)
+            .env("SIGIL_VAULT", self.vault())
+            .env("SIGIL_GPGKEY", "Sigil CI")
+            .env("XDG_CONFIG_HOME", &self.dir)
+            .env_remove("SIGIL_PLAINTEXT")
+            .env_remove("SIGIL_TRACK_USAGE")
+            .env_remove("SIGIL_AGE_IDENTITY");
+        command
+    }
+
+    /// Runs Sigil on an unencrypted vault
+    fn run(&self, args: &[&str]) -> Output {
+        self.command(args)
+            .env("SIGIL_PLAINTEXT", "1")
+            .output()
+            .unwrap()
+    }
+
+    /// Compares the vault to one of those in `tests/outputs`, ignoring
+    /// whitespace, blank lines and timestamps, which change on every run
+    fn compare_output(&self, output: &str) {
+        let vault = read_to_string(self.vault()).unwrap();
+        let expected = read_to_string(fixture("outputs", output)).unwrap();
+        assert_eq!(normalize(&vault), normalize(&expected));
+    }
+}
+
+impl Drop for Test {
+    fn drop(&mut self) {
+        let _ = remove_dir_all(&self.dir);
+    }
+}
+
+fn fixture(kind: &str, name: &str) -> PathBuf {
+    Path::new(env!("CARGO_MANIFEST_DIR"))
+        .join("tests")
+        .join(kind)
+        .join(name)
+}
+
+fn normalize(vault: &str) -> Vec<String> {
+    vault
+        .lines()
+        .map(|l| l.split_whitespace().collect::<Vec<_>>().join(" "))
+        .filter(|l| !l.is_empty())
+        .filter(|l| {
+            !(l.starts_with("created = ")
+                || l.starts_with("modified = ")
+                || l.starts_with("password_changed = ")
+                || l.starts_with("last_used = "))
+        })
+        .collect()
+}
+
+fn stdout(output: &Output) -> String {
+    String::from_utf8_lossy(&output.stdout).into_owned()
+}
+
+fn assert_success(output: &Output) {
+    assert!(
+        output.status.success(),
+        "{}",
+        String::from_utf8_lossy(&output.stderr)
+    );
+}
+
+/// Computes an HOTP token as RFC 4226 defines it
+fn hotp(counter: u64) -> String {
+    let key = hmac::SigningKey::new(&digest::SHA1, SECRET);
+    let mut message = [0; 8];
+    for (i, byte) in message.iter_mut().enumerate() {
+        *byte = (counter >> (56 - 8 * i)) as u8;
+    }
+    let hash = hmac::sign(&key, &message);
+    let hash = hash.as_ref();
+    let offset = (hash[hash.len() - 1] & 0xf) as usize;
+    let code = (u32::from(hash[offset]) & 0x7f) << 24
+        | u32::from(hash[offset + 1]) << 16
+        | u32::from(hash[offset + 2]) << 8
+        | u32::from(hash[offset + 3]);
+    format!("{:06}", code % 1_000_000)
+}
+
+fn totp() -> String {
+    let now = SystemTime::now().duration_since(UNIX_EPOCH).unwrap();
+    hotp(now.as_secs() / 30)
+}
+
+#[test]
+fn run() {
+    let test = Test::new("run");
+    assert!(!test.run(&[]).status.success());
+}
+
+#[test]
+fn touch() {
+    let test = Test::new("touch");
+    assert_success(&test.run(&["touch"]));
+    test.compare_output("touch");
+
+    // Existing vaults are only overwritten with --force
+    assert!(!test.run(&["touch"]).status.success());
+    assert_success(&test.run(&["touch", "--force"]));
+}
+
+#[test]
+fn password_add() {
+    let test = Test::new("password_add");
+    test.move_input("password_add");
+
+    let output = test.run(&[
+        "password",
+        "add",
+        "Bob:service",
+        "hunter2",
+        "-u",
+        "bob",
+        "--email",
+        "bob@example.com",
+        "--home",
+        "https://service.tld",
+    ]);
+    assert_success(&output);
+    test.compare_output("password_add");
+}
+
+#[test]
+fn password_rm() {
+    let test = Test::new("password_rm");
+    test.move_input("password_rm");
+
+    assert_success(&test.run(&["password", "rm", "Bob:service"]));
+    test.compare_output("password_rm");
+}
+
+#[test]
+fn password_get() {
+    let test = Test::new("password_get");
+    test.move_input("password_get");
+
+    let output = test.run(&["password", "get", "Bob:service"]);
+    assert_success(&output);
+    assert_eq!(stdout(&output), "hunter2\n");
+}
+
+#[test]
+fn password_generate() {
+    let test = Test::new("password_generate");
+
+    let output = test.run(&["password", "generate", "32"]);
+    assert_success(&output);
+    assert_eq!(stdout(&output).trim_end().chars().count(), 32);
+}
+
+#[test]
+fn otp_add_totp() {
+    let test = Test::new("otp_add_totp");
+    test.move_input("otp_add");
+
+    let output = test.run(&[
+        "otp",
+        "add",
+        "--totp",
+        "Bob:service",
+        "GEZDGNBVGY3TQOJQGEZDGNBVGY3TQOJQ",
+        "--issuer",
+        "service",
+    ]);
+    assert_success(&output);
+    test.compare_output("otp_add_totp");
+}
+
+#[test]
+fn otp_add_unsupported() {
+    let test = Test::new("otp_add_unsupported");
+    test.move_input("otp_add");
+    let add = [
+        "otp",
+        "add",
+        "--totp",
+        "a",
+        "GEZDGNBVGY3TQOJQGEZDGNBVGY3TQOJQ",
+    ];
+
+    assert!(!test
+        .run(&[&add[..], &["--digits", "10"]].concat())
+        .status
+        .success());
+    assert!(!test
+        .run(&[&add[..], &["--period", "0"]].concat())
+        .status
+        .success());
+    // Defaults from the configuration file are checked too
+    assert_success(&test.run(&["config", "set", "otp.digits", "10"]));
+    assert!(!test.run(&add).status.success());
+    let vault = read_to_string(test.vault()).unwrap();
+    assert_eq!(vault, read_to_string(fixture("inputs", "otp_add")).unwrap());
+}
+
+#[test]
+fn otp_add_hotp() {
+    let test = Test::new("otp_add_hotp");
+    test.move_input("otp_add");
+
+    let output = test.run(&[
+        "otp",
+        "add",
+        "--hotp",
+        "Bob:service",
+        "GEZDGNBVGY3TQOJQGEZDGNBVGY3TQOJQ",
+        "--issuer",
+        "service",
+    ]);
+    assert_success(&output);
+    test.compare_output("otp_add_hotp");
+}
+
+#[test]
+fn otp_add_normalize() {
+    let test = Test::new("otp_add_normalize");
+    test.move_input("otp_add");
+
+    let output = test.run(&[
+        "otp",
+        "add",
+        "--totp",
+        "Bob:service",
+        "gezd gnbv gy3t qojq gezd gnbv gy3t qojq",
+        "--issuer",
+        "service",
+    ]);
+    assert_success(&output);
+    test.compare_output("otp_add_totp");
+}
+
+#[test]
+fn otp_import() {
+    let test = Test::new("otp_import");
+    test.move_input("otp_add");
+
+    let output = test.run(&[
+        "otp",
+        "import",
+        "otpauth://totp/Bob:service?issuer=service&secret=GEZDGNBVGY3TQOJQGEZDGNBVGY3TQOJQ",
+    ]);
+    assert_success(&output);
+    test.compare_output("otp_add_totp");
+}
+
+#[test]
+fn otp_import_hotp() {
+    let test = Test::new("otp_import_hotp");
+    test.move_input("otp_add");
+
+    let output = test.run(&[
+        "otp",
+        "import",
+        "otpauth://hotp/Bob:service?issuer=service&secret=gezd%20gnbv%20gy3t%20qojq%20gezd%20gnbv%20gy3t%20qojq&counter=0",
+    ]);
+    assert_success(&output);
+    test.compare_output("otp_add_hotp");
+}
+
+#[test]
+fn otp_rm() {
+    let test = Test::new("otp_rm");
+    test.move_input("otp_rm");
+
+    assert_success(&test.run(&["otp", "rm", "Bob:service"]));
+    test.compare_output("otp_rm");
+}
+
+#[test]
+fn otp_token_totp() {
+    let test = Test::new("otp_token_totp");
+    test.move_input("otp_token_totp");
+
+    let before = totp();
+    let output = test.run(&["otp", "token", "Bob:service"]);
+    let after = totp();
+    assert_success(&output);
+    let stdout = stdout(&output);
+    let first = stdout.lines().next().unwrap();
+    // The token may have changed while running
+    assert!(
+        first == format!("Your token is {}", before) || first == format!("Your token is {}", after),
+        "{}",
+        first
+    );
+}
+
+#[test]
+fn otp_token_hotp() {
+    let test = Test::new("otp_token_hotp");
+    test.move_input("otp_token_hotp");
+
+    let output = test.run(&["otp", "token", "Bob:service", "51064264"]);
+    assert_success(&output);
+    assert_eq!(
+        stdout(&output),
+        format!("Your token is {}\n", hotp(51064264))
+    );
+}
+
+#[test]
+fn otp_token_hotp_stored_counter() {
+    let test = Test::new("otp_token_hotp_stored_counter");
+    test.move_input("otp_token_hotp");
+
+    // Tokens are generated from the stored counter, which moves forward
+    for counter in 0..2 {
+        let output = test.run(&["otp", "token", "Bob:service"]);
+        assert_success(&output);
+        assert_eq!(
+            stdout(&output),
+            format!("Your token is {}\n", hotp(counter))
+        );
+    }
+    assert!(read_to_string(test.vault())
+        .unwrap()
+        .contains("counter = 2"));
+
+    // A given counter replaces the stored one
+    assert_success(&test.run(&["otp", "token", "Bob:service", "51064264"]));
+    assert!(read_to_string(test.vault())
+        .unwrap()
+        .contains("counter = 51064265"));
+    let max = u64::MAX.to_string();
+    assert!(!test
+        .run(&["otp", "token", "Bob:service", &max])
+        .status
+        .success());
+}
+
+#[test]
+fn rfc_4226_test_vectors() {
+    assert_eq!(hotp(0), "755224");
+    assert_eq!(hotp(9), "520489");
+}
+
+#[test]
+fn rekey() {
+    let test = Test::new("rekey");
+    test.move_input("password_get");
+
+    assert!(!test.run(&["rekey"]).status.success());
+    assert_success(&test.run(&["rekey", "--to", "Sigil CI", "--to", "Sigil CD"]));
+    test.compare_output("password_add");
+}
+
+#[cfg(unix)]
+#[test]
+fn rekey_permissions() {
+    use std::fs::{metadata, set_permissions, Permissions};
+    use std::os::unix::fs::PermissionsExt;
+
+    let test = Test::new("rekey_permissions");
+    test.move_input("password_get");
+    set_permissions(test.vault(), Permissions::from_mode(0o640)).unwrap();
+
+    assert_success(&test.run(&["rekey", "--to", "Sigil CI"]));
+    let mode = metadata(test.vault()).unwrap().permissions().mode();
+    assert_eq!(mode & 0o777, 0o640);
+    assert!(!test.vault().with_extension("rekey").exists());
+}
+
+#[cfg(unix)]
+#[test]
+fn export_overwrite() {
+    use std::fs::{metadata, set_permissions, write, Permissions};
+    use std::os::unix::fs::PermissionsExt;
+
+    let test = Test::new("export_overwrite");
+    test.move_input("password_get");
+    let dest = test.dir.join("export.csv");
+    write(&dest, "").unwrap();
+    set_permissions(&dest, Permissions::from_mode(0o644)).unwrap();
+    let dest = dest.to_str().unwrap();
+
+    let export = ["export", "--format", "csv", "--plaintext", dest];
+    assert!(!test.run(&export).status.success());
+    assert_success(&test.run(&[&export[..], &["--force"]].concat()));
+    assert!(read_to_string(dest).unwrap().contains("hunter2"));
+    // Overwritten exports are made private too
+    assert_eq!(metadata(dest).unwrap().permissions().mode() & 0o777, 0o600);
+}
+
+#[test]
+fn config() {
+    let test = Test::new("config");
+    test.move_input("otp_add");
+
+    assert!(!test.run(&["config", "get", "otp.digits"]).status.success());
+    assert!(!test
+        .run(&["config", "set", "otp.digits", "eight"])
+        .status
+        .success());
+    assert!(!test
+        .run(&["config", "set", "editor", "vim"])
+        .status
+        .success());
+    assert!(!test
+        .run(&["config", "pin", "89ABCDEF01234567"])
+        .status
+        .success());
+    let fingerprint = "0123 4567 89ab cdef 0123 4567 89ab cdef 0123 4567";
+    assert_success(&test.run(&["config", "pin", fingerprint]));
+    assert_success(&test.run(&["config", "pin", fingerprint]));
+    let output = test.run(&["config", "get", "pinned_keys"]);
+    assert_eq!(
+        stdout(&output),
+        "0123456789ABCDEF0123456789ABCDEF01234567\n"
+    );
+
+    assert_success(&test.run(&["config", "set", "otp.digits", "8"]));
+    assert_success(&test.run(&["config", "set", "generator.length", "12"]));
+    let output = test.run(&["config", "get", "otp.digits"]);
+    assert_success(&output);
+    assert_eq!(stdout(&output), "8\n");
+
+    // Settings are used unless an argument overrides them
+    let output = test.run(&["password", "generate"]);
+    assert_success(&output);
+    assert_eq!(stdout(&output).trim_end().chars().count(), 12);
+    let output = test.run(&["password", "generate", "16"]);
+    assert_eq!(stdout(&output).trim_end().chars().count(), 16);
+
+    let output = test.run(&[
+        "otp",
+        "add",
+        "--totp",
+        "Bob:service",
+        "GEZDGNBVGY3TQOJQGEZDGNBVGY3TQOJQ",
+    ]);
+    assert_success(&output);
+    let vault = read_to_string(test.vault()).unwrap();
+    assert!(normalize(&vault).contains(&"digits = 8".to_string()));
+}
+
+#[test]
+fn profiles() {
+    let test = Test::new("profiles");
+    test.move_input("password_get");
+    let personal = test.dir.join("personal.vault");
+    let personal = personal.to_str().unwrap();
+    assert_success(&test.run(&["config", "set", "profiles.personal.vault", personal]));
+    assert_success(&test.run(&["config", "set", "profiles.personal.key", "Sigil CI"]));
+    assert_success(&test.run(&["-P", "personal", "touch"]));
+
+    let output = test.run(&["vaults", "ls"]);
+    assert_success(&output);
+    assert_eq!(
+        stdout(&output),
+        format!("  personal: {} (Sigil CI)\n", personal)
+    );
+    assert!(!test.run(&["-P", "work", "ls"]).status.success());
+
+    let copy = ["cp", "--to-vault", "personal", "Bob:service", "Bob:copy"];
+    assert_success(&test.run(&copy));
+    assert!(!test.run(&copy).status.success());
+    // Moving within a vault needs a new name
+    assert!(!test.run(&["mv", "Bob:service"]).status.success());
+    assert_success(&test.run(&["mv", "--to-vault", "personal", "Bob:service"]));
+    test.compare_output("password_rm");
+
+    let output = test.run(&["-P", "personal", "password", "get", "Bob:service"]);
+    assert_success(&output);
+    assert_eq!(stdout(&output), "hunter2\n");
+    let output = test.run(&["-P", "personal", "password", "get", "Bob:copy"]);
+    assert_eq!(stdout(&output), "hunter2\n");
+
+    // The same vault spelled differently is still moved within
+    create_dir_all(test.dir.join("again")).unwrap();
+    let again = test.dir.join("again").join("..").join("personal.vault");
+    let again = again.to_str().unwrap();
+    assert_success(&test.run(&["config", "set", "profiles.again.vault", again]));
+    assert_success(&test.run(&["config", "set", "profiles.again.key", "Sigil CI"]));
+    let moved = ["-P", "personal", "mv", "--to-vault", "again", "Bob:copy", "Bob:moved"];
+    assert_success(&test.run(&moved));
+    let output = test.run(&["-P", "personal", "password", "get", "Bob:moved"]);
+    assert_success(&output);
+    assert_eq!(stdout(&output), "hunter2\n");
+    assert!(!test
+        .run(&["-P", "personal", "password", "get", "Bob:copy"])
+        .status
+        .success());
+}
+
+/// A GPG home holding a "Sigil CI" key, whose agent is stopped when dropped
+struct GpgHome {
+    dir: PathBuf,
+}
+
+impl GpgHome {
+    /// Generates a key without a passphrase, or returns `None` if GPG is not
+    /// installed
+    fn new(test: &Test) -> Option<GpgHome> {
+        if Command::new("gpg").arg("--version").output().is_err() {
+            return None;
+        }
+        let home = GpgHome {
+            dir: test.dir.join("gnupg"),
+        };
+        create_dir_all(&home.dir).unwrap();
+        assert_success(&home.gpg(&[
+            "--batch",
+            "--passphrase",
+            "",
+            "--quick-gen-key",
+            "Sigil CI <sigil@ci.tld>",
+            "default",
+            "default",
+            "never",
+        ]));
+        Some(home)
+    }
+
+    fn gpg(&self, args: &[&str]) -> Output {
+        Command::new("gpg")
+            .args(args)
+            .env("GNUPGHOME", &self.dir)
+            .output()
+            .unwrap()
+    }
+
+    fn fingerprint(&self) -> String {
+        let output = self.gpg(&["--with-colons", "--list-keys", "Sigil CI"]);
+        assert_success(&output);
+        stdout(&output)
+            .lines()
+            .find(|l| l.starts_with("fpr:"))
+            .and_then(|l| l.split(':').nth(9))
+            .unwrap()
+            .to_string()
+    }
+}
+
+impl Drop for GpgHome {
+    fn drop(&mut self) {
+        let _ = Command::new("gpgconf")
+            .args(["--kill", "gpg-agent"])
+            .env("GNUPGHOME", &self.dir)
+            .output();
+    }
+}
+
+#[test]
+fn gpg() {
+    let test = Test::new("gpg");
+    let home = match GpgHome::new(&test) {
+        Some(home) => home,
+        None => {
+            eprintln!("Skipping the GPG scenario, gpg is not installed");
+            return;
+        }
+    };
+    let run = |args: &[&str]| {
+        test.command(args)
+            .env("GNUPGHOME", &home.dir)
+            .output()
+            .unwrap()
+    };
+
+    // Keys must be pinned before a vault is written
+    let output = run(&["touch"]);
+    assert!(!output.status.success());
+    assert!(String::from_utf8_lossy(&output.stderr).contains("sigil config pin"));
+    assert!(!test.vault().exists());
+    let fingerprint = home.fingerprint();
+    assert_success(&run(&["config", "pin", &fingerprint]));
+    let output = run(&["password", "add", "Bob:service", "hunter2"]);
+    assert_success(&output);
+    assert!(!String::from_utf8_lossy(&output.stderr).contains("Warning"));
+    let vault = read_to_string(test.vault()).unwrap();
+    assert!(vault.starts_with("-----BEGIN PGP MESSAGE-----"));
+    assert!(!vault.contains("hunter2"));
+
+    let output = run(&["password", "get", "Bob:service"]);
+    assert_success(&output);
+    assert_eq!(stdout(&output), "hunter2\n");
+}